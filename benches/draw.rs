@@ -0,0 +1,144 @@
+//! `draw()` into an image surface, at the lengths and angles that make it do
+//! more or less work.
+//!
+//! Drawing lives in the binary, so the modules it needs are compiled into the
+//! benchmark from their sources. Only part of them is used here.
+
+use std::hint::black_box;
+
+use cairo::{Context, Format, ImageSurface};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::DVec2;
+
+use ruler::state::{Mode, RulerState};
+use ruler::unit::Unit;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/backend/mod.rs"]
+mod backend;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/capture.rs"]
+mod capture;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/config.rs"]
+mod config;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/draw.rs"]
+mod draw;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/edid.rs"]
+mod edid;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/env.rs"]
+mod env;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/keys.rs"]
+mod keys;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/loupe.rs"]
+mod loupe;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/persist.rs"]
+mod persist;
+#[cfg(feature = "dbus")]
+#[allow(dead_code, unused_imports)]
+#[path = "../src/portal.rs"]
+mod portal;
+#[cfg(feature = "scripting")]
+#[allow(dead_code, unused_imports)]
+#[path = "../src/overlay.rs"]
+mod overlay;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/stats.rs"]
+mod stats;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/suggest.rs"]
+mod suggest;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/xdg.rs"]
+mod xdg;
+
+use config::Settings;
+use draw::{draw, View};
+
+fn view() -> View {
+    View {
+        dock: None,
+        px_per_mm: DVec2::splat(96.0 / 25.4),
+        #[cfg(feature = "scripting")]
+        overlay: None,
+        labels: Default::default(),
+        stats: Default::default(),
+        loupe: false,
+        edge_snap: None,
+        sampling: false,
+        samples: Default::default(),
+        luminance: false,
+        profile: Default::default(),
+        gap: None,
+        calibrating: false,
+        zoom_strip: false,
+        strip: None,
+        committed: Default::default(),
+        reference: None,
+        reference_angle: None,
+        flip_ticks: false,
+        precise: false,
+    }
+}
+
+/// A ruler of `length` pixels at `angle` degrees counterclockwise, in a surface just large enough to hold it.
+fn ruler(length: f64, angle: f64, half_width: f64) -> (RulerState, ImageSurface) {
+    let direction = DVec2::from_angle(-angle.to_radians()) * length;
+    let min = direction.min(DVec2::ZERO) - half_width;
+    let size = direction.abs() + half_width * 2.0;
+    let state = RulerState::new(-min, direction - min, Mode::Line, size);
+    let surface = ImageSurface::create(Format::ARgb32, size.x.ceil() as i32, size.y.ceil() as i32).unwrap();
+    (state, surface)
+}
+
+fn lengths(c: &mut Criterion) {
+    let settings = Settings::default();
+    let view = view();
+    let mut group = c.benchmark_group("draw length");
+    for length in [100.0, 400.0, 1600.0] {
+        let (state, surface) = ruler(length, 0.0, settings.sizes.half_width);
+        let ctx = Context::new(&surface).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(length), &state, |b, state| {
+            b.iter(|| draw(&ctx, black_box(state), DVec2::ZERO, &settings, &view).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn angles(c: &mut Criterion) {
+    let settings = Settings::default();
+    let view = view();
+    let mut group = c.benchmark_group("draw angle");
+    for angle in [0.0, 30.0, 90.0, 135.0] {
+        let (state, surface) = ruler(800.0, angle, settings.sizes.half_width);
+        let ctx = Context::new(&surface).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(angle), &state, |b, state| {
+            b.iter(|| draw(&ctx, black_box(state), DVec2::ZERO, &settings, &view).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn units(c: &mut Criterion) {
+    let settings = Settings::default();
+    let view = view();
+    let mut group = c.benchmark_group("draw unit");
+    for unit in [Unit::Px, Unit::Mm, Unit::In] {
+        let (mut state, surface) = ruler(800.0, 0.0, settings.sizes.half_width);
+        state.unit = Some(unit);
+        let ctx = Context::new(&surface).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{:?}", unit)), &state, |b, state| {
+            b.iter(|| draw(&ctx, black_box(state), DVec2::ZERO, &settings, &view).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lengths, angles, units);
+criterion_main!(benches);
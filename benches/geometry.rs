@@ -0,0 +1,46 @@
+//! The constraint math run for every pointer motion of a drag.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use glam::DVec2;
+
+use ruler::geom::closest_point_on_circle_in_rect;
+use ruler::state::{Constraints, Mode, PixelSnap, RulerState};
+
+const SCREEN: DVec2 = DVec2::new(1920.0, 1080.0);
+
+fn clamp_to_edges(c: &mut Criterion) {
+    c.bench_function("confine past a corner", |b| {
+        b.iter(|| closest_point_on_circle_in_rect(black_box(DVec2::new(100.0, 100.0)), 300.0, DVec2::ZERO, SCREEN, black_box(DVec2::new(-112.0, -112.0))))
+    });
+}
+
+fn drag(c: &mut Criterion) {
+    let cases = [
+        ("free", false, false, false),
+        ("clamped", false, false, true),
+        ("fixed distance", true, false, false),
+        ("fixed distance, clamped", true, false, true),
+        ("fixed angle", false, true, false),
+    ];
+    let mut group = c.benchmark_group("drag");
+    for (name, fix_distance, fix_angle, clamp_to_screen) in cases {
+        let constraints = Constraints { fix_distance, fix_angle, symmetric: false, clamp_to_screen, min_length: 20.0, snap_angle: 0.0, snap_tolerance: 0.0, snap_to_pixels: PixelSnap::Off };
+        let mut state = RulerState::new(DVec2::new(100.0, 540.0), DVec2::new(500.0, 540.0), Mode::Line, SCREEN);
+        state.grab(state.to, 40.0, 1);
+        // Back and forth across the left edge, so clamping has work to do.
+        let mut step = 0u32;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                step = step.wrapping_add(1);
+                let cursor = DVec2::new(-300.0 + (step % 600) as f64, 300.0 + (step % 480) as f64);
+                state.drag(black_box(cursor), constraints)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, clamp_to_edges, drag);
+criterion_main!(benches);
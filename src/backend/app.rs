@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+
+use xcb::x::Window;
+
+use super::{Render, XCBObjects};
+
+/// What one of the app's windows is for, which decides the events it gets
+/// and how it is drawn. Auxiliary windows such as menus, panels or guides
+/// each add a role here.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// The ruler itself, created along with the connection.
+    Ruler,
+    /// The magnified view beside the dragged endpoint.
+    Loupe,
+    /// The layout grid across the screen.
+    Grid,
+    /// The construction lines across the screen.
+    Construction,
+    /// The typographic baselines across the screen.
+    Baselines,
+    /// The distance from the pointer to the line, beside the pointer.
+    Hud,
+    /// A point dropped beside the ruler and its perpendicular to the line.
+    Marker,
+    /// A ruler added beside the first, of which there may be any number.
+    Extra,
+    /// The settings window.
+    Dialog,
+}
+
+/// A window of the app along with the surface drawing into it.
+pub struct AppWindow {
+    pub role: Role,
+    pub render: Render,
+}
+
+/// The X connection and every window open on it, so all of them share the
+/// one event loop. Events are told apart by the window they are for.
+/// Windows can come and go while the loop runs.
+pub struct App {
+    // Declared first so the surfaces are dropped while the connection they
+    // draw over is still open.
+    pub windows: RefCell<HashMap<Window, AppWindow>>,
+    pub xcb: XCBObjects,
+}
+
+impl App {
+    /// Takes over the connection and sets up drawing into its ruler window,
+    /// `width` by `height` as it was created.
+    pub fn new(xcb: XCBObjects, width: u16, height: u16) -> Result<App, Box<dyn Error>> {
+        let app = App { windows: RefCell::new(HashMap::new()), xcb };
+        app.open(app.xcb.window, Role::Ruler, width, height)?;
+        Ok(app)
+    }
+
+    /// Starts drawing into `window`, replacing whatever it was used for.
+    pub fn open(&self, window: Window, role: Role, width: u16, height: u16) -> Result<(), Box<dyn Error>> {
+        let render = Render::setup(&self.xcb, window, width, height)?;
+        self.windows.borrow_mut().insert(window, AppWindow { role, render });
+        Ok(())
+    }
+
+    /// Stops drawing into `window`, before it is destroyed.
+    pub fn close(&self, window: Window) {
+        self.windows.borrow_mut().remove(&window);
+    }
+
+    /// The surface of the first window with `role`.
+    pub fn render(&self, role: Role) -> Option<Render> {
+        self.windows.borrow().values().find(|window| window.role == role).map(|window| window.render.clone())
+    }
+
+    /// The surface of `window`, if it is one of the app's.
+    pub fn render_of(&self, window: Window) -> Option<Render> {
+        self.windows.borrow().get(&window).map(|window| window.render.clone())
+    }
+
+    pub fn ruler(&self) -> Render {
+        self.render(Role::Ruler).expect("the ruler window is opened with the app")
+    }
+}
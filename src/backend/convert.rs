@@ -0,0 +1,27 @@
+use tracing::warn;
+
+/// `value` as an X coordinate, rounded towards zero. Values past the ends of
+/// `i16` are saturated with a warning naming `what` they were, where an `as`
+/// cast would clamp them silently.
+pub fn coordinate(value: f64, what: &'static str) -> i16 {
+    saturate(value, i16::MIN as f64, i16::MAX as f64, what) as i16
+}
+
+/// `value` as an X width or height, rounded towards zero and saturated at the
+/// ends of `u16` like `coordinate`.
+pub fn extent(value: f64, what: &'static str) -> u16 {
+    saturate(value, 0.0, u16::MAX as f64, what) as u16
+}
+
+fn saturate(value: f64, min: f64, max: f64, what: &'static str) -> f64 {
+    if value.is_nan() {
+        warn!(what, "Sending NaN to the X server as 0");
+        return 0.0;
+    }
+    let truncated = value.trunc();
+    let clamped = truncated.clamp(min, max);
+    if clamped != truncated {
+        warn!(what, value, clamped, "Clamped a value the X protocol cannot carry");
+    }
+    clamped
+}
@@ -0,0 +1,158 @@
+use std::cell::Cell;
+use std::error::Error;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{Duration, Instant};
+
+use xcb::Connection;
+
+// Ctrl-C, `kill` and the end of the session, which all ask the ruler to quit.
+const SHUTDOWN_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// What woke the main loop up.
+pub enum Wakeup {
+    Event(xcb::Event),
+    /// The delay given to `EventLoop::schedule` ran out.
+    Timer,
+    /// One of SIGINT, SIGTERM or SIGHUP arrived.
+    Signal(libc::c_int),
+}
+
+fn shutdown_signals() -> libc::sigset_t {
+    unsafe {
+        let mut set: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut set);
+        for signal in SHUTDOWN_SIGNALS {
+            libc::sigaddset(&mut set, signal);
+        }
+        set
+    }
+}
+
+/// Gives the signals `EventLoop` takes over back their default handling,
+/// for child processes, which inherit the blocked mask. It only makes
+/// async-signal-safe calls, so it can run between fork and exec.
+pub fn unblock_shutdown_signals() -> io::Result<()> {
+    let set = shutdown_signals();
+    if unsafe { libc::sigprocmask(libc::SIG_UNBLOCK, &set, std::ptr::null_mut()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Waits on the X connection, a timerfd and a signalfd together, so the main
+/// loop can put work off until later without a thread of its own and shut
+/// down cleanly between requests when asked to quit.
+pub struct EventLoop {
+    timer: OwnedFd,
+    signals: OwnedFd,
+    /// Time spent flushing since `take_flush` was last called.
+    flushed: Cell<Option<Duration>>,
+}
+
+impl EventLoop {
+    /// Blocks the shutdown signals to read them from the signalfd instead.
+    /// Has to happen before any threads are spawned, so they inherit the
+    /// mask and the signals are not delivered to one of them.
+    pub fn new() -> io::Result<EventLoop> {
+        let fd = unsafe { libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_NONBLOCK | libc::TFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let timer = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let set = shutdown_signals();
+        if unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { libc::signalfd(-1, &set, libc::SFD_NONBLOCK | libc::SFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let signals = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        Ok(EventLoop { timer, signals, flushed: Cell::new(None) })
+    }
+
+    /// Fires the timer once after `delay`, replacing whatever was scheduled.
+    pub fn schedule(&self, delay: Duration) -> io::Result<()> {
+        // A zero expiry would disarm the timer instead.
+        let delay = delay.max(Duration::from_nanos(1));
+        let spec = libc::itimerspec {
+            it_interval: libc::timespec { tv_sec: 0, tv_nsec: 0 },
+            it_value: libc::timespec { tv_sec: delay.as_secs() as libc::time_t, tv_nsec: delay.subsec_nanos() as libc::c_long },
+        };
+        if unsafe { libc::timerfd_settime(self.timer.as_raw_fd(), 0, &spec, std::ptr::null_mut()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Fires the timer after `delay` at the latest, leaving it be if it is
+    /// already due sooner.
+    pub fn schedule_within(&self, delay: Duration) -> io::Result<()> {
+        let mut current: libc::itimerspec = unsafe { mem::zeroed() };
+        if unsafe { libc::timerfd_gettime(self.timer.as_raw_fd(), &mut current) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let remaining = Duration::new(current.it_value.tv_sec as u64, current.it_value.tv_nsec as u32);
+        // A zero remaining time means the timer is disarmed.
+        if remaining.is_zero() || remaining > delay {
+            self.schedule(delay)?;
+        }
+        Ok(())
+    }
+
+    /// Blocks until there is an X event, the timer fires or a signal arrives. Events already
+    /// read off the connection are handed out first, and the requests made
+    /// while handling them are flushed together before blocking, so handlers
+    /// only send requests and leave flushing to this.
+    pub fn next(&self, conn: &Connection) -> Result<Wakeup, Box<dyn Error>> {
+        loop {
+            if let Some(event) = conn.poll_for_queued_event()? {
+                return Ok(Wakeup::Event(event));
+            }
+            let start = Instant::now();
+            conn.flush()?;
+            self.flushed.set(Some(self.flushed.get().unwrap_or_default() + start.elapsed()));
+
+            let mut fds = [
+                libc::pollfd { fd: conn.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+                libc::pollfd { fd: self.timer.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+                libc::pollfd { fd: self.signals.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+            ];
+            if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(Box::new(error));
+            }
+
+            if fds[2].revents & libc::POLLIN != 0 {
+                let mut info: libc::signalfd_siginfo = unsafe { mem::zeroed() };
+                let size = mem::size_of::<libc::signalfd_siginfo>();
+                if unsafe { libc::read(self.signals.as_raw_fd(), (&mut info as *mut libc::signalfd_siginfo).cast(), size) } == size as isize {
+                    return Ok(Wakeup::Signal(info.ssi_signo as libc::c_int));
+                }
+            }
+            if fds[1].revents & libc::POLLIN != 0 {
+                let mut expirations = [0u8; 8];
+                unsafe { libc::read(self.timer.as_raw_fd(), expirations.as_mut_ptr().cast(), expirations.len()) };
+                return Ok(Wakeup::Timer);
+            }
+            if fds[0].revents != 0 {
+                if let Some(event) = conn.poll_for_event()? {
+                    return Ok(Wakeup::Event(event));
+                }
+            }
+        }
+    }
+
+    /// How long the flushes before blocking took since the last call, `None`
+    /// if `next` had queued events to hand out and did not flush.
+    pub fn take_flush(&self) -> Option<Duration> {
+        self.flushed.take()
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use tracing::{debug, info};
+use xcb::x::QueryExtension;
+use xcb::{randr, render, shape, xfixes, xinput, Connection};
+
+use super::round_trip;
+
+/// X extensions ruler can make use of.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Capability {
+    Render,
+    /// Input shapes limit clicks to the handles; without it the whole window takes them.
+    Shape,
+    XFixes,
+    RandR,
+    XInput,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [Capability::Render, Capability::Shape, Capability::XFixes, Capability::RandR, Capability::XInput];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Render => render::XNAME,
+            Capability::Shape => shape::XNAME,
+            Capability::XFixes => xfixes::XNAME,
+            Capability::RandR => randr::XNAME,
+            Capability::XInput => xinput::XNAME,
+        }
+    }
+
+    /// Whether the ruler can't draw at all without it.
+    fn required(self) -> bool {
+        self == Capability::Render
+    }
+
+    /// Asks the server for the version it speaks, offering the one the
+    /// bindings were generated for.
+    fn query_version(self, conn: &Connection) -> xcb::Result<(u32, u32)> {
+        Ok(match self {
+            Capability::Render => {
+                let reply = round_trip(conn, &render::QueryVersion { client_major_version: render::MAJOR_VERSION, client_minor_version: render::MINOR_VERSION })?;
+                (reply.major_version(), reply.minor_version())
+            }
+            Capability::Shape => {
+                let reply = round_trip(conn, &shape::QueryVersion {})?;
+                (reply.major_version() as u32, reply.minor_version() as u32)
+            }
+            Capability::XFixes => {
+                let reply = round_trip(conn, &xfixes::QueryVersion { client_major_version: xfixes::MAJOR_VERSION, client_minor_version: xfixes::MINOR_VERSION })?;
+                (reply.major_version(), reply.minor_version())
+            }
+            Capability::RandR => {
+                let reply = round_trip(conn, &randr::QueryVersion { major_version: randr::MAJOR_VERSION, minor_version: randr::MINOR_VERSION })?;
+                (reply.major_version(), reply.minor_version())
+            }
+            Capability::XInput => {
+                let reply = round_trip(conn, &xinput::XiQueryVersion { major_version: xinput::MAJOR_VERSION as u16, minor_version: xinput::MINOR_VERSION as u16 })?;
+                (reply.major_version() as u32, reply.minor_version() as u32)
+            }
+        })
+    }
+
+    fn client_version(self) -> (u32, u32) {
+        match self {
+            Capability::Render => (render::MAJOR_VERSION, render::MINOR_VERSION),
+            Capability::Shape => (shape::MAJOR_VERSION, shape::MINOR_VERSION),
+            Capability::XFixes => (xfixes::MAJOR_VERSION, xfixes::MINOR_VERSION),
+            Capability::RandR => (randr::MAJOR_VERSION, randr::MINOR_VERSION),
+            Capability::XInput => (xinput::MAJOR_VERSION, xinput::MINOR_VERSION),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct VersionMismatchError {
+    client_major_version: u32,
+    client_minor_version: u32,
+    server_major_version: u32,
+    server_minor_version: u32,
+    extension_name: &'static str,
+}
+
+impl Display for VersionMismatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Versions of extension '{}' do not match. Server: (major {}, minor {}) Client: (major {}, minor {})", self.extension_name, self.server_major_version, self.server_minor_version, self.client_major_version, self.client_minor_version)
+    }
+}
+
+impl Error for VersionMismatchError {}
+
+#[derive(Debug, Copy, Clone)]
+pub struct MissingExtensionError(&'static str);
+
+impl Display for MissingExtensionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The X server lacks the '{}' extension, which ruler needs", self.0)
+    }
+}
+
+impl Error for MissingExtensionError {}
+
+/// An extension that ruler does without, missing for a feature that does not.
+#[derive(Debug, Copy, Clone)]
+pub struct MissingCapabilityError(pub Capability);
+
+impl Display for MissingCapabilityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The X server lacks the '{}' extension", self.0.name())
+    }
+}
+
+impl Error for MissingCapabilityError {}
+
+/// The extensions the server has, probed once at startup so features can
+/// check for what they need and do without it, instead of assuming it.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    versions: HashMap<Capability, (u32, u32)>,
+}
+
+impl Capabilities {
+    /// Fails only if a required extension is missing or speaks an
+    /// incompatible major version. Others are left out with a note.
+    pub fn probe(conn: &Connection) -> Result<Capabilities, Box<dyn Error>> {
+        let mut versions = HashMap::new();
+        for capability in Capability::ALL {
+            let present = round_trip(conn, &QueryExtension { name: capability.name().as_bytes() })?.present();
+            if !present {
+                if capability.required() {
+                    return Err(Box::new(MissingExtensionError(capability.name())));
+                }
+                info!(extension = capability.name(), "X extension unavailable, doing without it");
+                continue;
+            }
+
+            let (major, minor) = capability.query_version(conn)?;
+            let (client_major, client_minor) = capability.client_version();
+            if major != client_major {
+                let error = VersionMismatchError {
+                    client_major_version: client_major,
+                    client_minor_version: client_minor,
+                    server_major_version: major,
+                    server_minor_version: minor,
+                    extension_name: capability.name(),
+                };
+                if capability.required() {
+                    return Err(Box::new(error));
+                }
+                info!("{}, doing without it", error);
+                continue;
+            }
+            debug!(extension = capability.name(), major, minor, "X extension available");
+            versions.insert(capability, (major, minor));
+        }
+        Ok(Capabilities { versions })
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        self.versions.contains_key(&capability)
+    }
+}
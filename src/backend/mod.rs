@@ -0,0 +1,15 @@
+//! The X11 connection, windows, rendering and event loop. The other modules
+//! still use xcb's types directly, so this gathers the setup in one place
+//! rather than hiding which display server the ruler runs on.
+
+mod app;
+mod convert;
+mod events;
+mod extensions;
+mod x11;
+
+pub use app::*;
+pub use convert::*;
+pub use events::*;
+pub use extensions::*;
+pub use x11::*;
@@ -0,0 +1,714 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cairo::{Context, Format, ImageSurface, Operator, XCBConnection, XCBDrawable, XCBSurface, XCBVisualType};
+use glam::{DVec2, IVec2, Vec2Swizzles};
+use tracing::{debug, trace_span};
+use xcb::{Connection, CookieWithReplyChecked, randr, Request, RequestWithReply, RequestWithoutReply, shape, VoidCookie, xinput, Xid};
+use xcb::x::*;
+
+use ruler::geom::Rect;
+
+use crate::config::Edge;
+use crate::edid;
+
+use super::{coordinate, extent, Capabilities, Capability, MissingCapabilityError};
+
+xcb::atoms_struct! {
+    #[derive(Debug)]
+    pub struct Atoms {
+        wm_protocols => b"WM_PROTOCOLS",
+        pub wm_del_window => b"WM_DELETE_WINDOW",
+        motif_wm_hints => b"_MOTIF_WM_HINTS",
+        net_wm_state => b"_NET_WM_STATE",
+        new_wm_state_skip_pager => b"_NET_WM_STATE_SKIP_PAGER",
+        net_wm_state_above => b"_NET_WM_STATE_ABOVE",
+        net_wm_state_sticky => b"_NET_WM_STATE_STICKY",
+        net_wm_allowed_actions => b"_NET_WM_ALLOWED_ACTIONS",
+        new_wm_action_close => b"_NEW_WM_ACTION_CLOSE",
+        net_wm_window_type => b"_NET_WM_WINDOW_TYPE",
+        net_wm_window_type_dock => b"_NET_WM_WINDOW_TYPE_DOCK",
+        net_wm_strut => b"_NET_WM_STRUT",
+        net_wm_strut_partial => b"_NET_WM_STRUT_PARTIAL",
+        net_client_list_stacking => b"_NET_CLIENT_LIST_STACKING",
+        pub net_active_window => b"_NET_ACTIVE_WINDOW",
+        net_frame_extents => b"_NET_FRAME_EXTENTS",
+        gtk_frame_extents => b"_GTK_FRAME_EXTENTS",
+        edid => b"EDID",
+        pub ruler_reload => b"_RULER_RELOAD",
+        pub ruler_instance => b"_RULER_INSTANCE",
+        pub ruler_command => b"_RULER_COMMAND",
+        pub ruler_control => b"_RULER_CONTROL",
+        pub ruler_paste => b"_RULER_PASTE",
+        pub ruler_sync => b"_RULER_SYNC",
+        pub clipboard => b"CLIPBOARD",
+        pub targets => b"TARGETS",
+        pub utf8_string => b"UTF8_STRING",
+        pub text => b"TEXT",
+    }
+}
+
+const TITLE: &str = "Ruler";
+const UNMAP_SETTLE: Duration = Duration::from_millis(50);
+// Round trips taking longer are logged at the debug level, to spot a busy
+// window manager or a slow remote display.
+const SLOW_ROUND_TRIP: Duration = Duration::from_millis(100);
+// Assumed when the server does not know the physical screen size.
+const FALLBACK_PX_PER_MM: f64 = 96.0 / 25.4;
+
+#[derive(Debug, Copy, Clone)]
+pub struct UnsupportedDepthError(u8);
+
+impl Display for UnsupportedDepthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Can't capture a screen of depth {}", self.0)
+    }
+}
+
+impl Error for UnsupportedDepthError {}
+
+/// A monitor the screen is shown on, as a RandR output driving a CRTC.
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// As `xrandr` lists it, such as "DP-1".
+    pub name: String,
+    pub rect: Rect,
+    /// The physical size the driver reports, zero if it does not know it.
+    pub size_mm: DVec2,
+    /// The physical size in the monitor's EDID, if it has a readable one.
+    pub edid_size_mm: Option<DVec2>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i16,
+    pub y: i16,
+    pub w: u16,
+    pub h: u16,
+}
+
+impl WindowGeometry {
+    pub fn pos(&self) -> IVec2 {
+        IVec2::new(self.x as i32, self.y as i32)
+    }
+}
+
+/// The part of the window a run of Expose events asked to repaint, as the
+/// bounding box of their rectangles.
+#[derive(Debug, Default)]
+pub struct Damage {
+    area: Option<Rectangle>,
+}
+
+impl Damage {
+    /// Adds the area of `ev`, returning the whole damaged area once it is the
+    /// last of its run.
+    pub fn expose(&mut self, ev: &ExposeEvent) -> Option<Rectangle> {
+        let (x, y) = (ev.x() as f64, ev.y() as f64);
+        let (right, bottom) = (x + ev.width() as f64, y + ev.height() as f64);
+        let area = match self.area {
+            Some(area) => {
+                let (area_right, area_bottom) = (area.x as f64 + area.width as f64, area.y as f64 + area.height as f64);
+                let (x, y) = (x.min(area.x as f64), y.min(area.y as f64));
+                Rectangle {
+                    x: coordinate(x, "exposed x"),
+                    y: coordinate(y, "exposed y"),
+                    width: extent(right.max(area_right) - x, "exposed width"),
+                    height: extent(bottom.max(area_bottom) - y, "exposed height"),
+                }
+            }
+            None => Rectangle { x: coordinate(x, "exposed x"), y: coordinate(y, "exposed y"), width: ev.width(), height: ev.height() },
+        };
+        if ev.count() > 0 {
+            self.area = Some(area);
+            return None;
+        }
+        self.area = None;
+        Some(area)
+    }
+}
+
+
+pub struct XCBObjects {
+    pub conn: Connection,
+    pub atoms: Atoms,
+    pub capabilities: Capabilities,
+    pub screen: ScreenBuf,
+    pub window: Window,
+    colormap: Colormap,
+    depth: DepthBuf,
+    gcontext: Gcontext,
+    visual_type: Visualtype,
+}
+
+/// Sends `request` and blocks for its reply in a span named after the
+/// request, so `-vv` shows how long the server took.
+pub fn round_trip<R>(conn: &Connection, request: &R) -> xcb::Result<R::Reply>
+where
+    R: RequestWithReply + Debug,
+    <R as Request>::Cookie: CookieWithReplyChecked<Reply = R::Reply>,
+{
+    traced::<R, _>(|| conn.wait_for_reply(conn.send_request(request)))
+}
+
+/// Sends `request` and blocks until the server has handled it, like
+/// `round_trip` for requests without a reply.
+pub fn checked<R>(conn: &Connection, request: &R) -> xcb::ProtocolResult<()>
+where
+    R: RequestWithoutReply + Debug,
+{
+    traced::<R, _>(|| conn.send_and_check_request(request))
+}
+
+fn traced<R, T>(wait: impl FnOnce() -> T) -> T {
+    let name = std::any::type_name::<R>().rsplit("::").next().unwrap_or_default();
+    let _span = trace_span!("x_request", request = name).entered();
+    let start = Instant::now();
+    let result = wait();
+    let elapsed = start.elapsed();
+    if elapsed >= SLOW_ROUND_TRIP {
+        debug!(request = name, ?elapsed, "slow X round trip");
+    }
+    result
+}
+
+impl XCBObjects {
+    pub fn setup(width: u16, height: u16) -> Result<XCBObjects, Box<dyn Error>> {
+        // XInput for the pointer's motion anywhere on the screen, when it is there.
+        let (conn, screen_num) = Connection::connect_with_extensions(None, &[], &[xcb::Extension::Input])?;
+
+        let capabilities = Capabilities::probe(&conn)?;
+
+        let xcb = {
+            let atoms = Atoms::intern_all(&conn)?;
+            let screen = conn.get_setup().roots().nth(screen_num as usize).unwrap();
+            let screen_buf = screen.to_owned();
+            let colormap: Colormap = conn.generate_id();
+            let depth = screen.allowed_depths().find(|d| d.depth() == 32).unwrap().to_owned();
+            let visual_type = *depth.visuals().iter().find(|v| v.class() == VisualClass::TrueColor).unwrap();
+            let window: Window = conn.generate_id();
+            let gcontext = conn.generate_id();
+
+            XCBObjects { conn, atoms, capabilities, screen: screen_buf, depth, visual_type, window, gcontext, colormap }
+        };
+
+        let root = xcb.screen.root();
+
+        checked(&xcb.conn, &CreateColormap {
+            alloc: ColormapAlloc::None,
+            mid: xcb.colormap,
+            window: root,
+            visual: xcb.visual_type.visual_id(),
+        })?;
+
+        xcb.create_ruler_window(xcb.window, width, height)?;
+
+        checked(&xcb.conn, &CreateGc {
+            cid: xcb.gcontext,
+            drawable: Drawable::Window(xcb.window),
+            value_list: &[Gc::Background(xcb.screen.black_pixel()), Gc::GraphicsExposures(false)],
+        })?;
+
+        checked(&xcb.conn, &ChangeWindowAttributes {
+            window: root,
+            value_list: &[Cw::EventMask(EventMask::PROPERTY_CHANGE)],
+        })?;
+
+        Ok(xcb)
+    }
+
+    /// Creates an unmapped ruler window, managed by the window manager but
+    /// kept above others and out of the pager, which asks to be closed
+    /// rather than killed.
+    fn create_ruler_window(&self, window: Window, width: u16, height: u16) -> Result<(), Box<dyn Error>> {
+        checked(&self.conn, &CreateWindow {
+            depth: self.depth.depth(),
+            wid: window,
+            parent: self.screen.root(),
+            x: 0,
+            y: 0,
+            width,
+            height,
+            border_width: 0,
+            class: WindowClass::InputOutput,
+            visual: self.visual_type.visual_id(),
+            value_list: &[
+                Cw::BorderPixel(0x00000000),
+                Cw::WinGravity(Gravity::NorthWest),
+                Cw::EventMask(EventMask::EXPOSURE | EventMask::KEY_PRESS | EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION | EventMask::LEAVE_WINDOW | EventMask::STRUCTURE_NOTIFY),
+                Cw::Colormap(self.colormap)
+            ],
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: self.atoms.motif_wm_hints,
+            r#type: ATOM_INTEGER,
+            data: &[2u32, 0u32, 0u32, 0u32, 0u32],
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: ATOM_WM_NAME,
+            r#type: ATOM_STRING,
+            data: TITLE.as_bytes(),
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: self.atoms.wm_protocols,
+            r#type: ATOM_ATOM,
+            data: &[self.atoms.wm_del_window],
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: self.atoms.net_wm_state,
+            r#type: ATOM_ATOM,
+            data: &[self.atoms.net_wm_state_above, self.atoms.new_wm_state_skip_pager],
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: self.atoms.net_wm_allowed_actions,
+            r#type: ATOM_ATOM,
+            data: &[self.atoms.new_wm_action_close],
+        })?;
+
+        Ok(())
+    }
+
+    /// Creates another window for a ruler like the first's.
+    pub fn create_ruler(&self, width: u16, height: u16) -> Result<Window, Box<dyn Error>> {
+        let window = self.conn.generate_id();
+        self.create_ruler_window(window, width, height)?;
+        Ok(window)
+    }
+
+    /// Creates an unmapped window the window manager decorates like any
+    /// other, kept with the ruler and asking to be closed rather than killed.
+    pub fn create_dialog(&self, width: u16, height: u16, title: &str) -> Result<Window, Box<dyn Error>> {
+        let window = self.conn.generate_id();
+        checked(&self.conn, &CreateWindow {
+            depth: self.depth.depth(),
+            wid: window,
+            parent: self.screen.root(),
+            x: 0,
+            y: 0,
+            width,
+            height,
+            border_width: 0,
+            class: WindowClass::InputOutput,
+            visual: self.visual_type.visual_id(),
+            value_list: &[
+                Cw::BorderPixel(0x00000000),
+                Cw::EventMask(EventMask::EXPOSURE | EventMask::KEY_PRESS | EventMask::BUTTON_PRESS | EventMask::STRUCTURE_NOTIFY),
+                Cw::Colormap(self.colormap)
+            ],
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: ATOM_WM_NAME,
+            r#type: ATOM_STRING,
+            data: title.as_bytes(),
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: ATOM_WM_TRANSIENT_FOR,
+            r#type: ATOM_WINDOW,
+            data: &[self.window],
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: self.atoms.wm_protocols,
+            r#type: ATOM_ATOM,
+            data: &[self.atoms.wm_del_window],
+        })?;
+
+        Ok(window)
+    }
+
+    /// Marks the window as a dock reserving `thickness` pixels along `edge`,
+    /// so the window manager keeps it in place and other windows clear of it.
+    /// Has to happen before the window is mapped.
+    pub fn dock(&self, edge: Edge, thickness: u32, screen_size: DVec2) -> Result<(), Box<dyn Error>> {
+        let (w, h) = (screen_size.x as u32, screen_size.y as u32);
+        // left, right, top, bottom, followed by the start and end of each along its edge.
+        let strut = match edge {
+            Edge::Left => [thickness, 0, 0, 0, 0, h, 0, 0, 0, 0, 0, 0],
+            Edge::Right => [0, thickness, 0, 0, 0, 0, 0, h, 0, 0, 0, 0],
+            Edge::Top => [0, 0, thickness, 0, 0, 0, 0, 0, 0, w, 0, 0],
+            Edge::Bottom => [0, 0, 0, thickness, 0, 0, 0, 0, 0, 0, 0, w],
+        };
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window: self.window,
+            property: self.atoms.net_wm_window_type,
+            r#type: ATOM_ATOM,
+            data: &[self.atoms.net_wm_window_type_dock],
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window: self.window,
+            property: self.atoms.net_wm_strut_partial,
+            r#type: ATOM_CARDINAL,
+            data: &strut,
+        })?;
+
+        checked(&self.conn, &ChangeProperty {
+            mode: PropMode::Replace,
+            window: self.window,
+            property: self.atoms.net_wm_strut,
+            r#type: ATOM_CARDINAL,
+            data: &strut[..4],
+        })?;
+
+        Ok(())
+    }
+
+    /// Creates an unmapped window for things drawn off the ruler, such as the
+    /// loupe. The window manager leaves it alone and the pointer passes
+    /// through it, so it never takes input from the windows below.
+    pub fn create_overlay(&self, width: u16, height: u16) -> Result<Window, Box<dyn Error>> {
+        let window: Window = self.conn.generate_id();
+        checked(&self.conn, &CreateWindow {
+            depth: self.depth.depth(),
+            wid: window,
+            parent: self.screen.root(),
+            x: 0,
+            y: 0,
+            width,
+            height,
+            border_width: 0,
+            class: WindowClass::InputOutput,
+            visual: self.visual_type.visual_id(),
+            value_list: &[
+                Cw::BorderPixel(0x00000000),
+                Cw::OverrideRedirect(true),
+                Cw::EventMask(EventMask::EXPOSURE),
+                Cw::Colormap(self.colormap),
+            ],
+        })?;
+        if self.capabilities.has(Capability::Shape) {
+            self.conn.send_request(&shape::Rectangles {
+                operation: shape::So::Set,
+                destination_kind: shape::Sk::Input,
+                ordering: ClipOrdering::Unsorted,
+                destination_window: window,
+                x_offset: 0,
+                y_offset: 0,
+                rectangles: &[],
+            });
+        }
+        Ok(window)
+    }
+
+    /// Has the server send an Expose for the whole window, as it does after a
+    /// resize, without clearing what is on it.
+    pub fn request_redraw(&self) {
+        self.conn.send_request(&ClearArea { exposures: true, window: self.window, x: 0, y: 0, width: 0, height: 0 });
+    }
+
+    /// Limits input to the handles around `from` and `to`. Without SHAPE the
+    /// whole window keeps taking input.
+    /// Limits where the window takes pointer input to `region`, in window
+    /// coordinates. Does nothing without the SHAPE extension.
+    pub fn set_input_shape(&self, window: Window, region: &[Rect]) -> Option<VoidCookie> {
+        if !self.capabilities.has(Capability::Shape) {
+            return None;
+        }
+        let rectangles: Vec<Rectangle> = region
+            .iter()
+            .map(|rect| Rectangle {
+                x: coordinate(rect.min.x, "input shape x"),
+                y: coordinate(rect.min.y, "input shape y"),
+                width: extent(rect.max.x - rect.min.x, "input shape width"),
+                height: extent(rect.max.y - rect.min.y, "input shape height"),
+            })
+            .collect();
+
+        Some(self.set_window_shape(window, shape::Sk::Input, &rectangles))
+    }
+
+    fn set_window_shape(&self, window: Window, kind: shape::Sk, rectangles: &[Rectangle]) -> VoidCookie {
+        self.conn.send_request(&shape::Rectangles {
+            operation: shape::So::Set,
+            destination_kind: kind,
+            ordering: ClipOrdering::Unsorted,
+            destination_window: window,
+            x_offset: 0,
+            y_offset: 0,
+            rectangles,
+        })
+    }
+
+    pub fn get_window_geometry(&self, window: Window) -> Result<WindowGeometry, Box<dyn Error>> {
+        let reply = round_trip(&self.conn, &GetGeometry {
+            drawable: Drawable::Window(window),
+        })?;
+        Ok(WindowGeometry { x: reply.x(), y: reply.y(), w: reply.width(), h: reply.height() })
+    }
+
+    /// The outer edges of the windows on the screen other than the ruler,
+    /// from the bottom of the stack up. Window manager frames are included
+    /// and the shadows of client-side decorations left out. Windows that go
+    /// away while they are looked at are skipped.
+    pub fn windows(&self) -> Result<Vec<Rect>, Box<dyn Error>> {
+        let root = self.screen.root();
+        let clients = self.property32::<Window>(root, self.atoms.net_client_list_stacking, ATOM_WINDOW);
+        if clients.is_empty() {
+            // Without an EWMH window manager the top-level windows, frames if
+            // it reparents, are all there is to go by.
+            let tree = round_trip(&self.conn, &QueryTree { window: root })?;
+            return Ok(tree.children().iter().filter(|&&window| window != self.window).filter_map(|&window| self.outer_edges(window, false)).collect());
+        }
+        Ok(clients.into_iter().filter(|&window| window != self.window).filter_map(|window| self.outer_edges(window, true)).collect())
+    }
+
+    /// The window the window manager says has the focus, if any.
+    pub fn active_window(&self) -> Option<Window> {
+        self.property32::<Window>(self.screen.root(), self.atoms.net_active_window, ATOM_WINDOW).first().copied().filter(|window| !window.is_none())
+    }
+
+    /// The outer edges of a client window as `windows` has them.
+    pub fn client_edges(&self, window: Window) -> Option<Rect> {
+        self.outer_edges(window, true)
+    }
+
+    /// Where `window` is on the screen, with the extents its frame or
+    /// shadows say it has if `client`. `None` if it is not shown, is an
+    /// override-redirect window such as a menu or the ruler's own overlays,
+    /// or is gone.
+    fn outer_edges(&self, window: Window, client: bool) -> Option<Rect> {
+        let attributes = round_trip(&self.conn, &GetWindowAttributes { window }).ok()?;
+        if attributes.map_state() != MapState::Viewable || attributes.override_redirect() {
+            return None;
+        }
+        let geometry = round_trip(&self.conn, &GetGeometry { drawable: Drawable::Window(window) }).ok()?;
+        let position = round_trip(&self.conn, &TranslateCoordinates { src_window: window, dst_window: self.screen.root(), src_x: 0, src_y: 0 }).ok()?;
+        let border = geometry.border_width() as f64;
+        let min = DVec2::new(position.dst_x() as f64, position.dst_y() as f64) - border;
+        let size = DVec2::new(geometry.width() as f64, geometry.height() as f64) + border * 2.0;
+        let mut rect = Rect { min, max: min + size };
+        if client {
+            // Both as left, right, top and bottom.
+            if let [left, right, top, bottom] = self.property32::<u32>(window, self.atoms.net_frame_extents, ATOM_CARDINAL)[..] {
+                rect.min -= DVec2::new(left as f64, top as f64);
+                rect.max += DVec2::new(right as f64, bottom as f64);
+            }
+            if let [left, right, top, bottom] = self.property32::<u32>(window, self.atoms.gtk_frame_extents, ATOM_CARDINAL)[..] {
+                rect.min += DVec2::new(left as f64, top as f64);
+                rect.max -= DVec2::new(right as f64, bottom as f64);
+            }
+        }
+        Some(rect)
+    }
+
+    /// A property made of 32-bit values such as windows or cardinals, empty
+    /// if it is not set or has some other format.
+    fn property32<T: PropEl + Copy>(&self, window: Window, property: Atom, r#type: Atom) -> Vec<T> {
+        let request = GetProperty { delete: false, window, property, r#type, long_offset: 0, long_length: u32::MAX / 4 };
+        match round_trip(&self.conn, &request) {
+            Ok(reply) if reply.format() == 32 => reply.value::<T>().to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The monitors making up the screen, from RandR 1.5. Empty without it.
+    pub fn monitors(&self) -> Result<Vec<WindowGeometry>, Box<dyn Error>> {
+        if !self.capabilities.has(Capability::RandR) {
+            return Ok(Vec::new());
+        }
+        let reply = round_trip(&self.conn, &randr::GetMonitors { window: self.screen.root(), get_active: true })?;
+        Ok(reply.monitors().map(|monitor| WindowGeometry { x: monitor.x(), y: monitor.y(), w: monitor.width(), h: monitor.height() }).collect())
+    }
+
+    /// The connected outputs that show part of the screen. Empty without
+    /// RandR.
+    pub fn outputs(&self) -> Result<Vec<Output>, Box<dyn Error>> {
+        if !self.capabilities.has(Capability::RandR) {
+            return Ok(Vec::new());
+        }
+        let resources = round_trip(&self.conn, &randr::GetScreenResourcesCurrent { window: self.screen.root() })?;
+        let config_timestamp = resources.config_timestamp();
+        let mut outputs = Vec::new();
+        for &output in resources.outputs() {
+            let info = round_trip(&self.conn, &randr::GetOutputInfo { output, config_timestamp })?;
+            if info.connection() != randr::Connection::Connected || info.crtc().is_none() {
+                continue;
+            }
+            let crtc = round_trip(&self.conn, &randr::GetCrtcInfo { crtc: info.crtc(), config_timestamp })?;
+            let min = DVec2::new(crtc.x() as f64, crtc.y() as f64);
+            let rect = Rect { min, max: min + DVec2::new(crtc.width() as f64, crtc.height() as f64) };
+            // Outputs without an EDID property, such as virtual ones, fail
+            // the request on some drivers rather than leave it empty.
+            let request = randr::GetOutputProperty { output, property: self.atoms.edid, r#type: ATOM_ANY, long_offset: 0, long_length: 128, delete: false, pending: false };
+            let edid_size_mm = match round_trip(&self.conn, &request) {
+                Ok(reply) if reply.format() == 8 => edid::image_size_mm(reply.data::<u8>()),
+                _ => None,
+            };
+            // The physical sizes are of the panel as it stands, the CRTC's of
+            // the picture after rotation.
+            let rotated = crtc.rotation().intersects(randr::Rotation::ROTATE_90 | randr::Rotation::ROTATE_270);
+            let turn = |size: DVec2| if rotated { size.yx() } else { size };
+            outputs.push(Output {
+                name: String::from_utf8_lossy(info.name()).into_owned(),
+                rect,
+                size_mm: turn(DVec2::new(info.mm_width() as f64, info.mm_height() as f64)),
+                edid_size_mm: edid_size_mm.map(turn),
+            });
+        }
+        Ok(outputs)
+    }
+
+    pub fn px_per_mm(&self) -> DVec2 {
+        let screen = &self.screen;
+        let per_axis = |px: u16, mm: u16| if mm == 0 { FALLBACK_PX_PER_MM } else { px as f64 / mm as f64 };
+        DVec2::new(
+            per_axis(screen.width_in_pixels(), screen.width_in_millimeters()),
+            per_axis(screen.height_in_pixels(), screen.height_in_millimeters()),
+        )
+    }
+
+    /// Starts or stops the raw motion events that tell the pointer moved
+    /// anywhere on the screen, not just over the ruler.
+    pub fn watch_pointer(&self, on: bool) -> Result<(), Box<dyn Error>> {
+        if !self.capabilities.has(Capability::XInput) {
+            return Err(Box::new(MissingCapabilityError(Capability::XInput)));
+        }
+        let mask: &[xinput::XiEventMask] = if on { &[xinput::XiEventMask::RAW_MOTION] } else { &[] };
+        checked(&self.conn, &xinput::XiSelectEvents {
+            window: self.screen.root(),
+            masks: &[xinput::EventMaskBuf::new(xinput::Device::AllMaster, mask)],
+        })?;
+        Ok(())
+    }
+
+    pub fn query_pointer(&self) -> Result<DVec2, Box<dyn Error>> {
+        let reply = round_trip(&self.conn, &QueryPointer {
+            window: self.screen.root(),
+        })?;
+        Ok(DVec2::new(reply.root_x() as f64, reply.root_y() as f64))
+    }
+
+    /// Runs `f` with the ruler unmapped, so it does not show up in its own
+    /// screenshots.
+    pub fn hidden<T>(&self, f: impl FnOnce() -> T) -> Result<T, Box<dyn Error>> {
+        checked(&self.conn, &UnmapWindow { window: self.window })?;
+        round_trip(&self.conn, &GetInputFocus {})?;
+        // Give the windows below a moment to repaint what the ruler covered.
+        thread::sleep(UNMAP_SETTLE);
+        let result = f();
+        checked(&self.conn, &MapWindow { window: self.window })?;
+        Ok(result)
+    }
+
+    /// Reads the screen contents in `area`.
+    pub fn get_image(&self, area: WindowGeometry) -> Result<ImageSurface, Box<dyn Error>> {
+        let reply = round_trip(&self.conn, &GetImage {
+            format: ImageFormat::ZPixmap,
+            drawable: Drawable::Window(self.screen.root()),
+            x: area.x,
+            y: area.y,
+            width: area.w,
+            height: area.h,
+            plane_mask: u32::MAX,
+        })?;
+
+        let (w, h) = (area.w as usize, area.h as usize);
+        if reply.data().len() != w * h * 4 {
+            return Err(Box::new(UnsupportedDepthError(reply.depth())));
+        }
+        let surface = ImageSurface::create_for_data(reply.data().to_vec(), Format::Rgb24, w as i32, h as i32, w as i32 * 4)?;
+        Ok(surface)
+    }
+}
+
+/// Frees what the ruler created on the server once it is done with it,
+/// rather than leaving it to the server to clean up after the connection.
+impl Drop for XCBObjects {
+    fn drop(&mut self) {
+        self.conn.send_request(&DestroyWindow { window: self.window });
+        self.conn.send_request(&FreeGc { gc: self.gcontext });
+        self.conn.send_request(&FreeColormap { cmap: self.colormap });
+        let _ = self.conn.flush();
+    }
+}
+
+/// Wakes up the main event loop from another thread by sending a client
+/// message to the ruler window over a connection of its own.
+#[derive(Clone)]
+pub struct Waker {
+    conn: Arc<Connection>,
+    window: Window,
+    atom: Atom,
+}
+
+impl Waker {
+    pub fn new(window: Window, atom: Atom) -> Result<Waker, Box<dyn Error>> {
+        let (conn, _) = Connection::connect(None)?;
+        Ok(Waker { conn: Arc::new(conn), window, atom })
+    }
+
+    pub fn wake(&self) -> Result<(), Box<dyn Error>> {
+        checked(&self.conn, &SendEvent {
+            propagate: false,
+            destination: SendEventDest::Window(self.window),
+            event_mask: EventMask::NO_EVENT,
+            event: &ClientMessageEvent::new(self.window, self.atom, ClientMessageData::Data32([0; 5])),
+        })?;
+        Ok(())
+    }
+}
+
+/// Drawing into a window. Clones draw into the same surface.
+#[derive(Clone)]
+pub struct Render {
+    surface: XCBSurface,
+    pub ctx: Context,
+}
+
+impl Render {
+    /// Draws into `window`, which is `width` by `height` to begin with.
+    pub fn setup(xcb: &XCBObjects, window: Window, width: u16, height: u16) -> Result<Render, Box<dyn Error>> {
+        let surface = unsafe {
+            let cairo_conn = XCBConnection::from_raw_none(xcb.conn.get_raw_conn() as *mut cairo::ffi::xcb_connection_t);
+            let visual_type = XCBVisualType::from_raw_none(&xcb.visual_type as *const Visualtype as *mut cairo::ffi::xcb_visualtype_t);
+            let drawable = XCBDrawable(window.resource_id());
+            XCBSurface::create(&cairo_conn, &drawable, &visual_type, width as i32, height as i32)?
+        };
+        xcb.conn.flush()?;
+        let cairo = Context::new(&surface)?;
+        Ok(Render { ctx: cairo, surface })
+    }
+
+    pub fn resize(&self, width: i32, height: i32) -> Result<(), Box<dyn Error>> {
+        self.surface.set_size(width, height)?;
+        Ok(())
+    }
+
+    /// Makes the whole window transparent, before it is painted in full.
+    pub fn clear(&self) -> Result<(), Box<dyn Error>> {
+        self.ctx.set_operator(Operator::Source);
+        self.ctx.set_source_rgba(0.0, 0.0, 0.0, 0.0);
+        self.ctx.paint()?;
+        self.ctx.set_operator(Operator::Over);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use tracing::warn;
+
+#[derive(Debug, Clone)]
+pub struct NotAFifoError {
+    path: PathBuf,
+}
+
+impl Display for NotAFifoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} exists and is not a named pipe", self.path.display())
+    }
+}
+
+impl Error for NotAFifoError {}
+
+/// Writes the current measurement, one line per change, to a named pipe for
+/// status bars such as polybar or i3blocks. Opening a pipe blocks until a
+/// reader shows up, so writing happens on a thread of its own, which skips
+/// straight to the latest line whenever it falls behind.
+pub struct Bar {
+    sender: Sender<String>,
+    last: String,
+}
+
+impl Bar {
+    /// Starts writing to the pipe at `path`, creating it if needed.
+    pub fn spawn(path: &Path) -> Result<Bar, Box<dyn Error>> {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.file_type().is_fifo() => {}
+            Ok(_) => return Err(Box::new(NotAFifoError { path: path.to_path_buf() })),
+            Err(e) if e.kind() == ErrorKind::NotFound => mkfifo(path)?,
+            Err(e) => return Err(Box::new(e)),
+        }
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_path_buf();
+        thread::spawn(move || deliver(&path, receiver));
+        Ok(Bar { sender, last: String::new() })
+    }
+
+    pub fn update(&mut self, line: String) {
+        if line != self.last {
+            self.last = line.clone();
+            let _ = self.sender.send(line);
+        }
+    }
+}
+
+fn mkfifo(path: &Path) -> io::Result<()> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+    // SAFETY: `c_path` is a valid NUL-terminated string that outlives the call.
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn deliver(path: &Path, lines: Receiver<String>) {
+    let mut pipe: Option<File> = None;
+    let mut pending = None;
+    loop {
+        let Some(line) = pending.take().or_else(|| lines.recv().ok()) else { return };
+        let file = match &mut pipe {
+            Some(file) => file,
+            None => match OpenOptions::new().write(true).open(path) {
+                Ok(file) => pipe.insert(file),
+                Err(e) => {
+                    warn!("Stopped writing to {}: {}", path.display(), e);
+                    return;
+                }
+            },
+        };
+        // Opening waits for a reader, by then there may be newer lines.
+        let line = lines.try_iter().last().unwrap_or(line);
+        if writeln!(file, "{}", line).is_err() {
+            // The reader went away, the line goes to the next one.
+            pipe = None;
+            pending = Some(line);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::error::Error;
+
+use cairo::{Context, FontSlant, FontWeight};
+use glam::DVec2;
+use xcb::x::{ClearArea, ExposeEvent, MapWindow, UnmapWindow, Window};
+
+use ruler::state::RulerState;
+
+use crate::backend::{Render, XCBObjects};
+use crate::config::Settings;
+
+/// Pixels a key press changes the line height by.
+const STEP: f64 = 1.0;
+/// Gap between the start of the ruler and the label on its baseline.
+const LABEL_GAP: f64 = 8.0;
+
+/// Horizontal lines every `baseline.line_height` pixels across the whole
+/// screen, one through the start of the ruler, in a window of its own that
+/// the pointer passes through. The line height the keys set lasts until the
+/// settings change.
+pub struct Baselines {
+    pub window: Window,
+    visible: bool,
+    line_height: f64,
+    /// The start of the ruler and the line height when last painted.
+    at: Option<(DVec2, f64)>,
+}
+
+impl Baselines {
+    pub fn create(xcb: &XCBObjects, screen_size: DVec2, settings: &Settings) -> Result<Baselines, Box<dyn Error>> {
+        let window = xcb.create_overlay(screen_size.x as u16, screen_size.y as u16)?;
+        Ok(Baselines { window, visible: false, line_height: settings.baseline.line_height(), at: None })
+    }
+
+    pub fn shown(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self, xcb: &XCBObjects, state: &RulerState) {
+        self.visible = !self.visible;
+        if self.visible {
+            xcb.conn.send_request(&MapWindow { window: self.window });
+            self.follow(xcb, state);
+        } else {
+            xcb.conn.send_request(&UnmapWindow { window: self.window });
+            self.at = None;
+        }
+    }
+
+    /// Makes the baselines a step further apart, or closer together, while
+    /// they are shown.
+    pub fn adjust(&mut self, xcb: &XCBObjects, state: &RulerState, taller: bool) {
+        if self.visible {
+            self.line_height = (self.line_height + if taller { STEP } else { -STEP }).max(2.0);
+            self.follow(xcb, state);
+        }
+    }
+
+    /// Has the baselines painted again if the ruler's start or the line
+    /// height moved them.
+    pub fn follow(&mut self, xcb: &XCBObjects, state: &RulerState) {
+        let at = (state.from.floor(), self.line_height);
+        if self.visible && self.at != Some(at) {
+            self.at = Some(at);
+            xcb.conn.send_request(&ClearArea { exposures: true, window: self.window, x: 0, y: 0, width: 0, height: 0 });
+        }
+    }
+
+    /// Takes the line height from `settings` again and forgets what was
+    /// painted, so the next `follow` paints again.
+    pub fn reset(&mut self, settings: &Settings) {
+        self.line_height = settings.baseline.line_height();
+        self.at = None;
+    }
+
+    /// Paints the baselines once the last of a series of Expose events for
+    /// them arrives, since they are drawn in full.
+    pub fn expose(&self, ev: &ExposeEvent, render: &Render, state: &RulerState, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        if ev.count() > 0 {
+            return Ok(());
+        }
+        render.clear()?;
+        paint(&render.ctx, state.from.floor(), self.line_height, state.screen_size, settings)
+    }
+
+    /// Draws the baselines onto `ctx` as they are on the screen, if shown.
+    pub fn export(&self, ctx: &Context, state: &RulerState, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        if self.visible {
+            paint(ctx, state.from.floor(), self.line_height, state.screen_size, settings)?;
+        }
+        Ok(())
+    }
+}
+
+fn paint(ctx: &Context, origin: DVec2, line_height: f64, screen_size: DVec2, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let theme = &settings.theme;
+    let accent = theme.accent;
+
+    ctx.set_line_width(1.0);
+
+    // The baselines that land on the screen, counted from the one through the start.
+    let first = (-origin.y / line_height).ceil() as i64;
+    let last = ((screen_size.y - origin.y) / line_height).floor() as i64;
+    for line in first..=last {
+        let alpha = if line == 0 { theme.opacity } else { theme.opacity * 0.5 };
+        ctx.set_source_rgba(accent.r, accent.g, accent.b, alpha);
+        // Half a pixel in, so a line covers one row of pixels exactly.
+        let y = (origin.y + line as f64 * line_height).floor() + 0.5;
+        ctx.move_to(0.0, y);
+        ctx.line_to(screen_size.x, y);
+        ctx.stroke()?;
+    }
+
+    ctx.select_font_face(&theme.font, FontSlant::Normal, FontWeight::Normal);
+    ctx.set_font_size(theme.font_size * 0.75);
+    ctx.set_source_rgba(accent.r, accent.g, accent.b, theme.opacity.max(0.8));
+    ctx.move_to(origin.x + LABEL_GAP, origin.y - 3.0);
+    ctx.show_text(&format!("{} px", line_height))?;
+    Ok(())
+}
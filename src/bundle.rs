@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, Config};
+
+const BUNDLE_VERSION: u32 = 1;
+
+/// Everything needed to recreate a setup on another machine. The config is
+/// kept verbatim, so its comments survive the trip, and profiles come along
+/// as part of it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    version: u32,
+    config: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleError {
+    message: String,
+}
+
+impl Display for BundleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid bundle: {}", self.message)
+    }
+}
+
+impl Error for BundleError {}
+
+/// Writes a bundle of the config at `config_path` to `output`, or to stdout.
+pub fn export(config_path: &Path, output: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let config = fs::read_to_string(config_path)?;
+    let bundle = toml::to_string(&Bundle { version: BUNDLE_VERSION, config })?;
+    match output {
+        Some(path) => fs::write(path, bundle)?,
+        None => print!("{}", bundle),
+    }
+    Ok(())
+}
+
+/// Installs the bundle read from `input`, or from stdin, as the config at
+/// `config_path`. An existing config is only replaced with `force`.
+pub fn import(config_path: PathBuf, input: Option<&Path>, force: bool) -> Result<(), Box<dyn Error>> {
+    let text = match input {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let bundle: Bundle = toml::from_str(&text).map_err(|e| BundleError { message: e.message().to_string() })?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(Box::new(BundleError { message: format!("version {} is newer than this ruler supports", bundle.version) }));
+    }
+    Config::parse(config_path.clone(), &bundle.config)?;
+    config::write_new(&config_path, &bundle.config, force)
+}
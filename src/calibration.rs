@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::xdg;
+
+const CALIBRATION_FILE: &str = "calibration.toml";
+// Screens from a watch to a wall projector fall well within these.
+const PLAUSIBLE_PX_PER_MM: (f64, f64) = (0.5, 50.0);
+
+/// The pixels per millimeter the `calibrate` key measured, kept next to the
+/// history rather than in the config file so that stays as the user wrote it.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct Saved {
+    px_per_mm: f64,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ImplausibleCalibrationError(f64);
+
+impl Display for ImplausibleCalibrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} px/mm is not a plausible screen density, was the ruler laid along the whole object?", self.0)
+    }
+}
+
+impl Error for ImplausibleCalibrationError {}
+
+#[derive(Debug, Copy, Clone)]
+pub struct NoDataDirError;
+
+impl Display for NoDataDirError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Nowhere to save the calibration, neither XDG_DATA_HOME nor HOME is set")
+    }
+}
+
+impl Error for NoDataDirError {}
+
+pub fn path() -> Option<PathBuf> {
+    xdg::data_dir().map(|dir| dir.join(CALIBRATION_FILE))
+}
+
+/// The pixels per millimeter of a ruler `length_px` long laid along an
+/// object `reference_mm` long.
+pub fn px_per_mm(length_px: f64, reference_mm: f64) -> Result<f64, ImplausibleCalibrationError> {
+    let px_per_mm = length_px / reference_mm;
+    if (PLAUSIBLE_PX_PER_MM.0..=PLAUSIBLE_PX_PER_MM.1).contains(&px_per_mm) {
+        Ok(px_per_mm)
+    } else {
+        Err(ImplausibleCalibrationError(px_per_mm))
+    }
+}
+
+/// The saved calibration, `None` if there is none or it cannot be read.
+pub fn load() -> Option<f64> {
+    let path = path()?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!("Failed to read the calibration from {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    match toml::from_str::<Saved>(&text) {
+        Ok(saved) if px_per_mm(saved.px_per_mm, 1.0).is_ok() => Some(saved.px_per_mm),
+        Ok(saved) => {
+            warn!("Ignoring the calibration in {}: {}", path.display(), ImplausibleCalibrationError(saved.px_per_mm));
+            None
+        }
+        Err(e) => {
+            warn!("Ignoring the calibration in {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+pub fn save(px_per_mm: f64) -> Result<PathBuf, Box<dyn Error>> {
+    let path = path().ok_or(NoDataDirError)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, toml::to_string(&Saved { px_per_mm })?)?;
+    Ok(path)
+}
@@ -0,0 +1,83 @@
+use std::env;
+use std::error::Error;
+#[cfg(not(feature = "dbus"))]
+use std::fmt::{Display, Formatter};
+
+use cairo::ImageSurface;
+use glam::DVec2;
+
+use ruler::color::Rgb;
+use ruler::geom::Rect;
+
+use crate::backend::{coordinate, extent, WindowGeometry, XCBObjects};
+#[cfg(feature = "dbus")]
+use crate::portal;
+
+#[cfg(not(feature = "dbus"))]
+#[derive(Debug, Copy, Clone)]
+pub struct NoPortalError;
+
+#[cfg(not(feature = "dbus"))]
+impl Display for NoPortalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Capturing the screen under Wayland needs the screenshot portal, ruler was built without the dbus feature")
+    }
+}
+
+#[cfg(not(feature = "dbus"))]
+impl Error for NoPortalError {}
+
+/// Reads the screen contents in `area`, the ruler included where it covers
+/// them, so callers that want what is under it hide it first. Under XWayland
+/// the root window only holds other X clients, so the picture comes from the
+/// desktop's screenshot portal instead of the X server.
+pub fn capture(xcb: &XCBObjects, area: WindowGeometry) -> Result<ImageSurface, Box<dyn Error>> {
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        from_portal(area)
+    } else {
+        xcb.get_image(area)
+    }
+}
+
+/// Reads the color of the pixel under each of `points` in one capture,
+/// `None` for those off the screen.
+pub fn pixels(xcb: &XCBObjects, points: &[DVec2]) -> Result<Vec<Option<Rgb>>, Box<dyn Error>> {
+    let screen = Rect::from_size(DVec2::new(xcb.screen.width_in_pixels() as f64, xcb.screen.height_in_pixels() as f64) - 1.0);
+    let pixels = points.iter().map(|point| point.floor()).filter(|&point| screen.contains(point));
+    let (min, max) = pixels.fold((screen.max, screen.min), |(min, max), point| (min.min(point), max.max(point)));
+    if min.cmpgt(max).any() {
+        return Ok(vec![None; points.len()]);
+    }
+    let area = WindowGeometry {
+        x: coordinate(min.x, "sample x"),
+        y: coordinate(min.y, "sample y"),
+        w: extent(max.x - min.x + 1.0, "sample width"),
+        h: extent(max.y - min.y + 1.0, "sample height"),
+    };
+    let mut image = capture(xcb, area)?;
+    let stride = image.stride() as usize;
+    let data = image.data()?;
+    Ok(points
+        .iter()
+        .map(|point| {
+            let pixel = point.floor();
+            if !screen.contains(pixel) {
+                return None;
+            }
+            let offset = (pixel.y - min.y) as usize * stride + (pixel.x - min.x) as usize * 4;
+            // Both captures are laid out blue first.
+            let [b, g, r, _] = data[offset..offset + 4] else { unreachable!() };
+            Some(Rgb { r, g, b })
+        })
+        .collect())
+}
+
+#[cfg(feature = "dbus")]
+fn from_portal(area: WindowGeometry) -> Result<ImageSurface, Box<dyn Error>> {
+    portal::screenshot(area)
+}
+
+#[cfg(not(feature = "dbus"))]
+fn from_portal(_area: WindowGeometry) -> Result<ImageSurface, Box<dyn Error>> {
+    Err(Box::new(NoPortalError))
+}
@@ -0,0 +1,276 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{ArgAction, ArgGroup, Parser};
+
+use ruler::format::Template;
+use ruler::output::{RegionFormat, StreamFormat};
+use ruler::state::Mode;
+use ruler::unit::Unit;
+
+use crate::config::{Anchor, Color, Edge, Orientation, Settings};
+use crate::instance::Command;
+use crate::watch::Watch;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "A simple on-screen ruler for X11")]
+pub struct Args {
+    #[command(subcommand)]
+    pub subcommand: Option<Subcommand>,
+
+    /// Path to the config file, defaults to $XDG_CONFIG_HOME/ruler/config.toml
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Profile from the config file to start with
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
+    /// Template for printed measurements, e.g. "{len_mm} mm at {angle}°"
+    #[arg(short, long)]
+    pub format: Option<Template>,
+
+    /// Unit of the scale and the length readout
+    #[arg(short, long)]
+    pub unit: Option<Unit>,
+
+    /// Measurement mode to start in
+    #[arg(short, long)]
+    pub mode: Option<Mode>,
+
+    /// Initial direction: horizontal, vertical or an angle in degrees
+    #[arg(short, long, allow_negative_numbers = true)]
+    pub orientation: Option<Orientation>,
+
+    /// Where the ruler is placed at startup
+    #[arg(short, long)]
+    pub anchor: Option<Anchor>,
+
+    /// Dock a full-length ruler along a screen edge
+    #[arg(short, long)]
+    pub dock: Option<Edge>,
+
+    /// Accent color for this ruler, e.g. "#e06c75", to tell several apart
+    #[arg(long)]
+    pub color: Option<Color>,
+
+    /// Maximum window updates per second while dragging, 0 for uncapped
+    #[arg(long)]
+    pub max_fps: Option<u32>,
+
+    /// Print measurements, history and diagnostics as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Print more diagnostics, repeat for even more
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Print every finished drag to stdout, as text or JSON
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "text")]
+    pub stream: Option<StreamFormat>,
+
+    /// Print the ruler's state with a timestamp whenever it changes, at most every MS milliseconds
+    #[arg(long, value_name = "MS", num_args = 0..=1, default_missing_value = "100")]
+    pub watch: Option<u64>,
+
+    /// Write the current measurement to this named pipe on every change, for status bars
+    #[arg(long, value_name = "PATH")]
+    pub bar_fifo: Option<PathBuf>,
+
+    /// Put an icon with a menu in the system tray
+    #[arg(long)]
+    pub tray: bool,
+
+    /// Serve the current measurement as JSON on this localhost port
+    #[arg(long, value_name = "PORT")]
+    pub http: Option<u16>,
+
+    /// Run this shell command after every drag, with the measurement in MEASURE_* variables
+    #[arg(long, value_name = "CMD")]
+    pub on_measure: Option<String>,
+
+    /// Record every move of the ruler's endpoints to this file
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Play back a session made with --record, then quit
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// How many times faster than recorded to replay
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0, value_parser = positive, requires = "replay")]
+    pub speed: f64,
+
+    /// Show a desktop notification for every finished drag
+    #[arg(long)]
+    pub notify: bool,
+
+    /// On exit, print the rectangle between the endpoints instead of the measurement
+    #[arg(long, value_name = "FORMAT", conflicts_with = "json")]
+    pub region: Option<RegionFormat>,
+
+    /// Append every finished drag to this CSV file
+    #[arg(long, value_name = "FILE")]
+    pub log: Option<PathBuf>,
+
+    /// Forward the command line to a running ruler instead of opening a new one
+    #[arg(short, long)]
+    pub single_instance: bool,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Subcommand {
+    /// Show or clear past measurements
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+    /// Print a commented config file with every default
+    DumpConfig {
+        /// Write it to the config path instead of stdout
+        #[arg(long)]
+        write: bool,
+        /// Replace an existing config when writing
+        #[arg(long, requires = "write")]
+        force: bool,
+    },
+    /// Move the config between machines
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Resize or turn the running ruler, keeping its start point
+    #[command(group(ArgGroup::new("change").required(true).multiple(true)))]
+    Set {
+        /// New length in pixels
+        #[arg(long, group = "change")]
+        length: Option<f64>,
+        /// New counterclockwise angle in degrees
+        #[arg(long, group = "change", allow_negative_numbers = true)]
+        angle: Option<f64>,
+    },
+    /// Show or hide the running ruler
+    Toggle,
+    /// Close the running ruler
+    Quit,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ConfigAction {
+    /// Bundle the config and its profiles into a single file
+    Export {
+        /// Where to write the bundle, stdout if omitted
+        output: Option<PathBuf>,
+    },
+    /// Install a bundle created by `config export`
+    Import {
+        /// The bundle to install, stdin if omitted
+        input: Option<PathBuf>,
+        /// Replace an existing config
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Copy, Clone, clap::Subcommand)]
+pub enum HistoryAction {
+    /// Print every recorded measurement
+    List,
+    /// Delete the history file
+    Clear,
+}
+
+impl Args {
+    /// The command a running instance should carry out for this invocation.
+    pub fn command(&self) -> Command {
+        match self.subcommand {
+            Some(Subcommand::Set { length, angle }) => Command::Set { length, angle },
+            Some(Subcommand::Toggle) => Command::Toggle,
+            Some(Subcommand::Quit) => Command::Quit,
+            _ => Command::Place { orientation: self.orientation, anchor: self.anchor },
+        }
+    }
+
+    /// How finished drags are streamed, if at all. `--json` wins over the
+    /// format given to `--stream`.
+    pub fn stream_format(&self) -> Option<StreamFormat> {
+        self.stream.map(|format| if self.json { StreamFormat::Json } else { format })
+    }
+
+    /// The `--watch` printer, in JSON with `--json`.
+    pub fn watch(&self) -> Option<Watch> {
+        let format = if self.json { StreamFormat::Json } else { StreamFormat::Text };
+        self.watch.map(|ms| Watch::new(Duration::from_millis(ms), format))
+    }
+
+    /// Whether this invocation only makes sense with a running instance.
+    pub fn requires_instance(&self) -> bool {
+        matches!(self.subcommand, Some(Subcommand::Set { .. } | Subcommand::Toggle | Subcommand::Quit))
+    }
+
+    /// Applies the flags that take precedence over the config file.
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(format) = &self.format {
+            settings.format = format.clone();
+        }
+        if let Some(unit) = self.unit {
+            settings.unit = unit;
+        }
+        if let Some(mode) = self.mode {
+            settings.startup.mode = mode;
+        }
+        if let Some(orientation) = self.orientation {
+            settings.startup.orientation = orientation;
+        }
+        if let Some(anchor) = self.anchor {
+            settings.startup.anchor = anchor;
+        }
+        if let Some(dock) = self.dock {
+            settings.startup.dock = Some(dock);
+        }
+        if let Some(color) = self.color {
+            settings.theme.accent = color;
+        }
+        if let Some(max_fps) = self.max_fps {
+            settings.performance.max_fps = max_fps;
+        }
+        if self.notify {
+            settings.notify = true;
+        }
+        if self.single_instance {
+            settings.startup.single_instance = true;
+        }
+        if self.tray {
+            settings.startup.tray = true;
+        }
+    }
+}
+
+fn positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        _ => Err(format!("'{}' is not a positive number", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn flags_apply_over_the_config() {
+        let config = Config::parse(PathBuf::from("config.toml"), "unit = \"mm\"\n[profiles.dim.theme]\naccent = \"#444444\"\n").unwrap();
+        let mut settings = config.settings(Some("dim")).unwrap();
+        Args::parse_from(["ruler", "--color", "#666666", "--unit", "in"]).apply(&mut settings);
+        assert_eq!((settings.theme.accent.to_string(), settings.unit), ("#666666".to_string(), Unit::In));
+
+        // Flags left out leave the config as it is.
+        let mut settings = config.settings(None).unwrap();
+        Args::parse_from(["ruler"]).apply(&mut settings);
+        assert_eq!(settings.unit, Unit::Mm);
+    }
+}
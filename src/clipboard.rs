@@ -0,0 +1,180 @@
+use std::error::Error;
+
+use glam::DVec2;
+use xcb::Connection;
+use xcb::x::{ATOM_ANY, ATOM_ATOM, ATOM_NONE, ATOM_PRIMARY, ATOM_STRING, Atom, ChangeProperty, ConvertSelection, EventMask, GetProperty, PropMode, SelectionClearEvent, SelectionNotifyEvent, SelectionRequestEvent, SendEvent, SendEventDest, SetSelectionOwner, Timestamp, Window};
+
+use crate::backend::{Atoms, checked, round_trip};
+
+/// Serves a piece of text on the CLIPBOARD and PRIMARY selections. X has no
+/// clipboard storage, so the text is handed out on request for as long as
+/// we own a selection. Measurements are short, so INCR transfers are never
+/// needed.
+#[derive(Default)]
+pub struct Clipboard {
+    text: Option<String>,
+    owned: Vec<Atom>,
+}
+
+impl Clipboard {
+    pub fn set(&mut self, conn: &Connection, atoms: &Atoms, window: Window, time: Timestamp, text: String) -> Result<(), Box<dyn Error>> {
+        self.owned.clear();
+        for selection in [atoms.clipboard, ATOM_PRIMARY] {
+            checked(conn, &SetSelectionOwner { owner: window, selection, time })?;
+            self.owned.push(selection);
+        }
+        self.text = Some(text);
+        Ok(())
+    }
+
+    /// Converts the text for a requestor, or refuses when the target is not a
+    /// text format or the selection is not ours.
+    pub fn handle_request(&self, conn: &Connection, atoms: &Atoms, ev: &SelectionRequestEvent) -> Result<(), Box<dyn Error>> {
+        // Obsolete clients leave the property unset and expect the target to be used.
+        let property = if ev.property() == ATOM_NONE { ev.target() } else { ev.property() };
+        let text = self.text.as_deref().filter(|_| self.owned.contains(&ev.selection()));
+
+        let converted = match text {
+            Some(_) if ev.target() == atoms.targets => {
+                conn.send_request(&ChangeProperty {
+                    mode: PropMode::Replace,
+                    window: ev.requestor(),
+                    property,
+                    r#type: ATOM_ATOM,
+                    data: &[atoms.targets, atoms.utf8_string, ATOM_STRING, atoms.text],
+                });
+                true
+            }
+            Some(text) if [atoms.utf8_string, ATOM_STRING, atoms.text].contains(&ev.target()) => {
+                let r#type = if ev.target() == atoms.text { atoms.utf8_string } else { ev.target() };
+                conn.send_request(&ChangeProperty {
+                    mode: PropMode::Replace,
+                    window: ev.requestor(),
+                    property,
+                    r#type,
+                    data: text.as_bytes(),
+                });
+                true
+            }
+            _ => false,
+        };
+
+        checked(conn, &SendEvent {
+            propagate: false,
+            destination: SendEventDest::Window(ev.requestor()),
+            event_mask: EventMask::NO_EVENT,
+            event: &SelectionNotifyEvent::new(ev.time(), ev.requestor(), ev.selection(), ev.target(), if converted { property } else { ATOM_NONE }),
+        })?;
+        Ok(())
+    }
+
+    /// Forgets a selection another client took over.
+    pub fn handle_clear(&mut self, ev: &SelectionClearEvent) {
+        self.owned.retain(|&selection| selection != ev.selection());
+        if self.owned.is_empty() {
+            self.text = None;
+        }
+    }
+}
+
+/// Asks the owner of PRIMARY for its text. The answer arrives as a
+/// SelectionNotify event, to be read with `read_primary`.
+pub fn request_primary(conn: &Connection, atoms: &Atoms, window: Window, time: Timestamp) -> Result<(), Box<dyn Error>> {
+    checked(conn, &ConvertSelection {
+        requestor: window,
+        selection: ATOM_PRIMARY,
+        target: atoms.utf8_string,
+        property: atoms.ruler_paste,
+        time,
+    })?;
+    Ok(())
+}
+
+/// The text of a converted PRIMARY selection, or `None` if the owner
+/// refused or there was none.
+pub fn read_primary(conn: &Connection, ev: &SelectionNotifyEvent) -> Result<Option<String>, Box<dyn Error>> {
+    if ev.property() == ATOM_NONE {
+        return Ok(None);
+    }
+    let reply = round_trip(conn, &GetProperty {
+        delete: true,
+        window: ev.requestor(),
+        property: ev.property(),
+        r#type: ATOM_ANY,
+        long_offset: 0,
+        long_length: u32::MAX / 4,
+    })?;
+    Ok(Some(String::from_utf8_lossy(reply.value::<u8>()).into_owned()))
+}
+
+/// Parses pasted coordinates, `X,Y` for one point or `X1,Y1 X2,Y2` for both.
+/// The numbers may be separated by commas or spaces and surrounded by other
+/// text, as in `(120, 40) to (300, 40)`, but there must be two or four.
+pub fn parse_points(text: &str) -> Option<(DVec2, Option<DVec2>)> {
+    match numbers(text)?[..] {
+        [x, y] => Some((DVec2::new(x, y), None)),
+        [x1, y1, x2, y2] => Some((DVec2::new(x1, y1), Some(DVec2::new(x2, y2)))),
+        _ => None,
+    }
+}
+
+/// The numbers in `text`, such as `-12` or `.5`, ignoring whatever is
+/// around them.
+fn numbers(text: &str) -> Option<Vec<f64>> {
+    let mut numbers = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit() || c == '.') {
+        // A sign just before the digits belongs to the number.
+        let start = if rest[..start].ends_with('-') { start - 1 } else { start };
+        let len = rest[start + 1..].find(|c: char| !c.is_ascii_digit() && c != '.').map_or(rest.len() - start, |end| end + 1);
+        let number = &rest[start..start + len];
+        rest = &rest[start + len..];
+        match number {
+            "." | "-." => continue,
+            _ => numbers.push(number.parse().ok()?),
+        }
+    }
+    Some(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_point_or_two() {
+        assert_eq!(parse_points("120,40"), Some((DVec2::new(120.0, 40.0), None)));
+        assert_eq!(parse_points("120,40 300,80"), Some((DVec2::new(120.0, 40.0), Some(DVec2::new(300.0, 80.0)))));
+    }
+
+    #[test]
+    fn commas_or_spaces_between_numbers() {
+        let both = Some((DVec2::new(1.0, 2.0), Some(DVec2::new(3.0, 4.0))));
+        assert_eq!(parse_points("1, 2, 3, 4"), both);
+        assert_eq!(parse_points("1 2 3 4"), both);
+        assert_eq!(parse_points("1,2\n3,4\n"), both);
+        assert_eq!(parse_points("  10\t20 "), Some((DVec2::new(10.0, 20.0), None)));
+    }
+
+    #[test]
+    fn negative_and_fractional_values() {
+        assert_eq!(parse_points("-12.5,0.25"), Some((DVec2::new(-12.5, 0.25), None)));
+        assert_eq!(parse_points(".5,-.5"), Some((DVec2::new(0.5, -0.5), None)));
+    }
+
+    #[test]
+    fn surrounding_text() {
+        assert_eq!(parse_points("(120, 40) to (300, 40)"), Some((DVec2::new(120.0, 40.0), Some(DVec2::new(300.0, 40.0)))));
+        assert_eq!(parse_points("x: 5px, y: 7px."), Some((DVec2::new(5.0, 7.0), None)));
+    }
+
+    #[test]
+    fn refuses_the_wrong_count_of_numbers() {
+        assert_eq!(parse_points(""), None);
+        assert_eq!(parse_points("no numbers here"), None);
+        assert_eq!(parse_points("120"), None);
+        assert_eq!(parse_points("1,2 3"), None);
+        assert_eq!(parse_points("1 2 3 4 5"), None);
+        assert_eq!(parse_points("1.2.3,4"), None);
+    }
+}
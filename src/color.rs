@@ -0,0 +1,123 @@
+use std::fmt::{Display, Formatter};
+
+/// The contrast ratio WCAG 2 level AA asks of normal-sized text. Large text
+/// gets by with 3.
+pub const AA: f64 = 4.5;
+/// The contrast ratio WCAG 2 level AAA asks of normal-sized text. Large text
+/// gets by with 4.5.
+pub const AAA: f64 = 7.0;
+
+/// A color as read off the screen, 8 bits a channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    /// The channels as decimals, such as `30 30 46`.
+    pub fn channels(&self) -> String {
+        format!("{} {} {}", self.r, self.g, self.b)
+    }
+
+    /// Relative luminance as WCAG 2 defines it, 0 for black and 1 for white.
+    pub fn luminance(&self) -> f64 {
+        let linear = |channel: u8| {
+            let value = channel as f64 / 255.0;
+            if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+        };
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+}
+
+/// `#rrggbb`.
+impl Display for Rgb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// The WCAG 2 contrast ratio between two colors, whichever way round, from 1
+/// for the same luminance to 21 for black on white.
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (a, b) = (a.luminance(), b.luminance());
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Changes in luminance between neighbouring pixels smaller than this are
+/// noise rather than an edge.
+pub const EDGE_THRESHOLD: f64 = 0.02;
+
+/// The first pixel past the largest change in luminance between neighbours
+/// in `profile`, where an edge is. `None` stands for a pixel that could not
+/// be read, and a profile without any change past `EDGE_THRESHOLD` has no
+/// edge.
+pub fn steepest_change(profile: &[Option<f64>]) -> Option<usize> {
+    let changes = profile.windows(2).enumerate().filter_map(|(i, pair)| Some((i + 1, (pair[1]? - pair[0]?).abs())));
+    // The first of several equal changes, so a hard edge is found where it starts.
+    let (index, change) = changes.fold(None, |best: Option<(usize, f64)>, (i, change)| match best {
+        Some((_, largest)) if largest >= change => best,
+        _ => Some((i, change)),
+    })?;
+    (change > EDGE_THRESHOLD).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgb = Rgb { r: 0, g: 0, b: 0 };
+    const WHITE: Rgb = Rgb { r: 255, g: 255, b: 255 };
+
+    fn gray(value: u8) -> Rgb {
+        Rgb { r: value, g: value, b: value }
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(Rgb { r: 30, g: 30, b: 46 }.to_string(), "#1e1e2e");
+    }
+
+    #[test]
+    fn black_on_white() {
+        assert_eq!(contrast_ratio(BLACK, WHITE), 21.0);
+        assert_eq!(contrast_ratio(WHITE, BLACK), 21.0);
+    }
+
+    #[test]
+    fn same_color() {
+        assert_eq!(contrast_ratio(gray(128), gray(128)), 1.0);
+    }
+
+    #[test]
+    fn gray_at_the_aa_threshold() {
+        // #767676 is the lightest gray that passes AA on white, #777777 just misses.
+        assert!(contrast_ratio(gray(0x76), WHITE) >= AA);
+        assert!(contrast_ratio(gray(0x77), WHITE) < AA);
+    }
+
+    #[test]
+    fn primaries() {
+        assert!((Rgb { r: 255, g: 0, b: 0 }.luminance() - 0.2126).abs() < 1e-9);
+        assert!((contrast_ratio(Rgb { r: 0, g: 0, b: 255 }, WHITE) - 8.59).abs() < 0.01);
+    }
+
+    #[test]
+    fn edge_in_a_profile() {
+        let profile = [Some(0.1), Some(0.1), Some(0.12), Some(0.8), Some(0.8)];
+        assert_eq!(steepest_change(&profile), Some(3));
+    }
+
+    #[test]
+    fn flat_profile_has_no_edge() {
+        assert_eq!(steepest_change(&[Some(0.5), Some(0.51), Some(0.5)]), None);
+        assert_eq!(steepest_change(&[Some(0.5)]), None);
+    }
+
+    #[test]
+    fn edge_skips_unread_pixels() {
+        // Off the screen past the second pixel, so the jump to there does not count.
+        assert_eq!(steepest_change(&[Some(0.0), Some(0.3), None, Some(1.0)]), Some(1));
+    }
+}
@@ -0,0 +1,921 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::ValueEnum;
+use glam::DVec2;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use toml::{Table, Value};
+use tracing::warn;
+
+use ruler::format::Template;
+use ruler::state::{Mode, PixelSnap};
+use ruler::unit::Unit;
+
+use crate::keys::{default_keymap, Keymap, Modifiers};
+use crate::{env, persist, suggest, xdg};
+
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+impl Color {
+    pub const fn gray(value: f64) -> Color {
+        Color { r: value, g: value, b: value }
+    }
+
+    pub fn lerp(self, other: Color, t: f64) -> Color {
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidColorError {
+    color: String,
+}
+
+impl Display for InvalidColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid color '{}', expected #rrggbb or rgb:rr/gg/bb", self.color)
+    }
+}
+
+impl Error for InvalidColorError {}
+
+impl std::str::FromStr for Color {
+    type Err = InvalidColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || InvalidColorError { color: s.to_string() };
+        if let Some(channels) = s.strip_prefix("rgb:") {
+            // The X color syntax, with 1 to 4 hex digits per channel.
+            let channel = |hex: &str| match hex.len() {
+                1..=4 => u16::from_str_radix(hex, 16).map(|c| c as f64 / ((1u32 << (hex.len() * 4)) - 1) as f64).map_err(|_| error()),
+                _ => Err(error()),
+            };
+            return match channels.split('/').collect::<Vec<_>>()[..] {
+                [r, g, b] => Ok(Color { r: channel(r)?, g: channel(g)?, b: channel(b)? }),
+                _ => Err(error()),
+            };
+        }
+        let hex = s.strip_prefix('#').filter(|hex| hex.len() == 6).ok_or_else(error)?;
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).ok_or_else(error)?, 16).map(|c| c as f64 / 255.0).map_err(|_| error());
+        Ok(Color { r: channel(0)?, g: channel(2)?, b: channel(4)? })
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        write!(f, "#{:02x}{:02x}{:02x}", channel(self.r), channel(self.g), channel(self.b))
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub background: Color,
+    pub accent: Color,
+    pub opacity: f64,
+    pub font: String,
+    pub font_size: f64,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            background: Color::gray(1.0),
+            accent: Color::gray(0.7),
+            opacity: 0.6,
+            font: "sans-serif".to_string(),
+            font_size: 14.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sizes {
+    pub half_width: f64,
+    pub control_radius: f64,
+    pub initial_length: f64,
+    /// Shortest length a drag can produce, 0 to disable.
+    pub min_length: f64,
+    /// Fewest pixels between neighbouring ticks, 0 to draw all of them.
+    pub tick_spacing: f64,
+}
+
+impl Default for Sizes {
+    fn default() -> Self {
+        Sizes {
+            half_width: 40.0,
+            control_radius: 20.0,
+            initial_length: 400.0,
+            min_length: 20.0,
+            tick_spacing: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "OrientationRepr", into = "OrientationRepr")]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+    /// Counterclockwise from the screen's x-axis, in degrees.
+    Angle(f64),
+}
+
+impl Orientation {
+    pub fn degrees(self) -> f64 {
+        match self {
+            Orientation::Horizontal => 0.0,
+            Orientation::Vertical => 90.0,
+            Orientation::Angle(degrees) => degrees,
+        }
+    }
+
+    pub fn direction(self) -> DVec2 {
+        let (sin, cos) = self.degrees().to_radians().sin_cos();
+        DVec2::new(cos, -sin)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidOrientationError {
+    orientation: String,
+}
+
+impl Display for InvalidOrientationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid orientation '{}', expected 'horizontal', 'vertical' or an angle in degrees", self.orientation)
+    }
+}
+
+impl Error for InvalidOrientationError {}
+
+impl std::str::FromStr for Orientation {
+    type Err = InvalidOrientationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "horizontal" => Ok(Orientation::Horizontal),
+            "vertical" => Ok(Orientation::Vertical),
+            _ => s.parse().map(Orientation::Angle).map_err(|_| InvalidOrientationError { orientation: s.to_string() }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OrientationRepr {
+    Name(String),
+    Angle(f64),
+}
+
+impl TryFrom<OrientationRepr> for Orientation {
+    type Error = InvalidOrientationError;
+
+    fn try_from(repr: OrientationRepr) -> Result<Self, Self::Error> {
+        match repr {
+            OrientationRepr::Name(name) => name.parse(),
+            OrientationRepr::Angle(degrees) => Ok(Orientation::Angle(degrees)),
+        }
+    }
+}
+
+impl From<Orientation> for OrientationRepr {
+    fn from(orientation: Orientation) -> Self {
+        match orientation {
+            Orientation::Horizontal => OrientationRepr::Name("horizontal".to_string()),
+            Orientation::Vertical => OrientationRepr::Name("vertical".to_string()),
+            Orientation::Angle(degrees) => OrientationRepr::Angle(degrees),
+        }
+    }
+}
+
+/// Where clamped endpoints are kept.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ConfinementRepr", into = "ConfinementRepr")]
+pub enum Confinement {
+    /// The whole screen, every monitor included.
+    Desktop,
+    /// The monitor the endpoint that stays in place is on.
+    Monitor,
+    /// `[x, y, width, height]` in screen pixels.
+    Rect([f64; 4]),
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidConfinementError {
+    confinement: String,
+}
+
+impl Display for InvalidConfinementError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid confinement '{}', expected 'desktop', 'monitor' or [x, y, width, height]", self.confinement)
+    }
+}
+
+impl Error for InvalidConfinementError {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConfinementRepr {
+    Name(String),
+    Rect([f64; 4]),
+}
+
+impl TryFrom<ConfinementRepr> for Confinement {
+    type Error = InvalidConfinementError;
+
+    fn try_from(repr: ConfinementRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ConfinementRepr::Name(name) if name == "desktop" => Ok(Confinement::Desktop),
+            ConfinementRepr::Name(name) if name == "monitor" => Ok(Confinement::Monitor),
+            ConfinementRepr::Name(name) => Err(InvalidConfinementError { confinement: name }),
+            ConfinementRepr::Rect(rect) => Ok(Confinement::Rect(rect)),
+        }
+    }
+}
+
+impl From<Confinement> for ConfinementRepr {
+    fn from(confinement: Confinement) -> Self {
+        match confinement {
+            Confinement::Desktop => ConfinementRepr::Name("desktop".to_string()),
+            Confinement::Monitor => ConfinementRepr::Name("monitor".to_string()),
+            Confinement::Rect(rect) => ConfinementRepr::Rect(rect),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    /// Centered on the screen
+    Center,
+    /// Starting at the pointer
+    Cursor,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    /// Endpoints of a ruler spanning the whole edge, with the window flush against it.
+    pub fn endpoints(self, half_width: f64, screen_size: DVec2) -> (DVec2, DVec2) {
+        match self {
+            Edge::Top => (DVec2::new(0.0, half_width), DVec2::new(screen_size.x, half_width)),
+            Edge::Bottom => (DVec2::new(0.0, screen_size.y - half_width), DVec2::new(screen_size.x, screen_size.y - half_width)),
+            Edge::Left => (DVec2::new(half_width, 0.0), DVec2::new(half_width, screen_size.y)),
+            Edge::Right => (DVec2::new(screen_size.x - half_width, 0.0), DVec2::new(screen_size.x - half_width, screen_size.y)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Startup {
+    pub mode: Mode,
+    pub orientation: Orientation,
+    pub anchor: Anchor,
+    /// Hand the command line over to an already running ruler instead of
+    /// opening another one.
+    pub single_instance: bool,
+    /// Dock a fixed, full-length ruler along this screen edge instead of a
+    /// free one. Only read at startup.
+    pub dock: Option<Edge>,
+    /// Show an icon in the system tray. Only read at startup.
+    pub tray: bool,
+}
+
+impl Default for Startup {
+    fn default() -> Self {
+        Startup { mode: Mode::Line, orientation: Orientation::Horizontal, anchor: Anchor::Center, single_instance: false, dock: None, tray: false }
+    }
+}
+
+/// Modifiers that apply a constraint while held. Constraints can also be
+/// latched with the `toggle_fix_*` key bindings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Drag {
+    pub fix_distance: Modifiers,
+    pub fix_angle: Modifiers,
+    /// Moves the other endpoint the mirrored way, keeping the middle in place.
+    pub symmetric: Modifiers,
+    /// Keep endpoints on the screen. Flipped at runtime by `toggle_clamp`.
+    pub clamp_to_screen: bool,
+    /// Lets endpoints off the screen and closer together than `min_length`
+    /// while held, whatever `clamp_to_screen` says.
+    pub ignore_limits: Modifiers,
+    /// The part of the screen `clamp_to_screen` keeps endpoints in.
+    pub confine_to: Confinement,
+    /// Snap the angle to multiples of this many degrees, 0 to not snap.
+    pub snap_angle: f64,
+    /// Degrees off a multiple of `snap_angle` that still snap.
+    pub snap_tolerance: f64,
+    /// Grab the bar between the endpoints to move the whole ruler.
+    pub body: bool,
+    /// Keeps dragged endpoints on pixel centers or boundaries.
+    pub snap_to_pixels: PixelSnap,
+    /// Snaps the dragged endpoint to the strongest edge on the screen nearby
+    /// while held.
+    pub snap_to_edges: Modifiers,
+    /// Snaps the dragged endpoint to the nearest border or corner of a window
+    /// while held.
+    pub snap_to_windows: Modifiers,
+    /// Has `snap_to_windows` snap to the lines through the middle of the
+    /// monitor and its diagonals too.
+    pub snap_to_center: bool,
+    /// Pixels from the pointer that `snap_to_edges` and `snap_to_windows`
+    /// look for an edge within.
+    pub edge_reach: f64,
+}
+
+impl Drag {
+    /// `edge_reach` in whole pixels, at most 32 to keep the capture small.
+    pub fn edge_reach(&self) -> usize {
+        self.edge_reach.clamp(1.0, 32.0) as usize
+    }
+}
+
+impl Default for Drag {
+    fn default() -> Self {
+        Drag { fix_distance: Modifiers::CONTROL, fix_angle: Modifiers::SHIFT, symmetric: Modifiers::NONE, clamp_to_screen: true, ignore_limits: Modifiers::ALTGR, confine_to: Confinement::Desktop, snap_angle: 0.0, snap_tolerance: 2.0, body: false, snap_to_pixels: PixelSnap::Off, snap_to_edges: Modifiers::ALT, snap_to_windows: Modifiers::SUPER, snap_to_center: true, edge_reach: 8.0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Performance {
+    /// Upper bound on window updates per second while dragging, 0 for uncapped.
+    pub max_fps: u32,
+    /// Log a summary of frame timings on exit.
+    pub frame_stats: bool,
+}
+
+impl Performance {
+    pub fn min_interval(&self) -> Duration {
+        match self.max_fps {
+            0 => Duration::ZERO,
+            fps => Duration::from_secs(1) / fps,
+        }
+    }
+}
+
+impl Default for Performance {
+    fn default() -> Self {
+        Performance { max_fps: 60, frame_stats: false }
+    }
+}
+
+/// The magnified view of the screen around the dragged endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Loupe {
+    /// Show the loupe while dragging. Flipped at runtime by `toggle_loupe`.
+    pub enabled: bool,
+    /// How many times the pixels are magnified, from 4 to 8.
+    pub zoom: u16,
+}
+
+impl Loupe {
+    /// `zoom` brought within the range the loupe supports.
+    pub fn zoom(&self) -> u16 {
+        self.zoom.clamp(4, 8)
+    }
+}
+
+impl Default for Loupe {
+    fn default() -> Self {
+        Loupe { enabled: false, zoom: 6 }
+    }
+}
+
+/// Which endpoint the grid is lined up with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridOrigin {
+    From,
+    To,
+}
+
+/// The layout grid shown across the screen with `toggle_grid`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Grid {
+    /// Size of a cell in pixels, at least 2.
+    pub cell: f64,
+    /// Every how many lines one is drawn stronger, 0 for none.
+    pub major_every: u32,
+    pub origin: GridOrigin,
+}
+
+impl Grid {
+    /// `cell` raised to the smallest size the grid draws.
+    pub fn cell(&self) -> f64 {
+        self.cell.max(2.0)
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid { cell: 8.0, major_every: 8, origin: GridOrigin::From }
+    }
+}
+
+/// Baselines across the screen shown with `toggle_baseline`, to check that
+/// text keeps to a vertical rhythm.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Baseline {
+    /// Pixels from one baseline to the next, at least 2.
+    pub line_height: f64,
+}
+
+impl Baseline {
+    /// `line_height` raised to the smallest the baselines are drawn at.
+    pub fn line_height(&self) -> f64 {
+        self.line_height.max(2.0)
+    }
+}
+
+impl Default for Baseline {
+    fn default() -> Self {
+        Baseline { line_height: 24.0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calibration {
+    /// How long the object the ruler is laid along for `calibrate` is.
+    pub reference_mm: f64,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        // The long edge of a bank card, ISO/IEC 7810 ID-1.
+        Calibration { reference_mm: 85.6 }
+    }
+}
+
+/// What to take a monitor's physical size as, over what it reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputOverride {
+    /// The width and height of the picture.
+    pub size_mm: [f64; 2],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub format: Template,
+    /// Append finished measurements to the history file.
+    pub history: bool,
+    /// Show a desktop notification for every finished drag.
+    pub notify: bool,
+    /// Share the runtime unit and the profile with other rulers that sync.
+    pub sync: bool,
+    /// Minutes without use after which the ruler hides until it is shown
+    /// again with the `recall` key or `ruler toggle`, 0 to never hide.
+    pub auto_hide_minutes: f64,
+    pub unit: Unit,
+    /// Where screenshots and SVG exports are saved, $XDG_PICTURES_DIR or ~/Pictures if unset.
+    pub screenshot_dir: Option<PathBuf>,
+    /// A rhai script drawing extra lines and labels on the ruler.
+    pub overlay_script: Option<PathBuf>,
+    pub startup: Startup,
+    pub theme: Theme,
+    pub sizes: Sizes,
+    pub keymap: Keymap,
+    pub drag: Drag,
+    pub performance: Performance,
+    pub loupe: Loupe,
+    pub grid: Grid,
+    pub baseline: Baseline,
+    /// Pixels per millimeter on the screen, over what `calibrate` saved and
+    /// what the X server says.
+    pub px_per_mm: Option<f64>,
+    pub calibration: Calibration,
+    /// Physical sizes by RandR output name, for monitors whose EDID is wrong.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub outputs: BTreeMap<String, OutputOverride>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            format: Template::default(),
+            history: true,
+            notify: false,
+            sync: false,
+            auto_hide_minutes: 0.0,
+            unit: Unit::Px,
+            screenshot_dir: None,
+            overlay_script: None,
+            startup: Startup::default(),
+            theme: Theme::default(),
+            sizes: Sizes::default(),
+            keymap: default_keymap(),
+            drag: Drag::default(),
+            performance: Performance::default(),
+            loupe: Loupe::default(),
+            grid: Grid::default(),
+            baseline: Baseline::default(),
+            px_per_mm: None,
+            calibration: Calibration::default(),
+            outputs: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message.trim_end())
+    }
+}
+
+impl Error for ConfigError {}
+
+#[derive(Debug, Clone)]
+pub struct UnknownProfileError {
+    name: String,
+}
+
+impl Display for UnknownProfileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown profile '{}'", self.name)
+    }
+}
+
+impl Error for UnknownProfileError {}
+
+/// The parsed config file. Profiles are kept as raw tables and layered over
+/// the base settings on demand, so a profile only needs to list what it changes.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub path: Option<PathBuf>,
+    pub default_profile: Option<String>,
+    base: Table,
+    profiles: BTreeMap<String, Table>,
+    resources: Table,
+    env: Table,
+}
+
+/// The parts of a config file beside the base settings, for validation.
+#[derive(Deserialize)]
+struct ProfileSection {
+    #[serde(rename = "profile")]
+    _default: Option<String>,
+    #[serde(rename = "profiles", default)]
+    _profiles: BTreeMap<String, Settings>,
+}
+
+/// Checks a config file against the settings structs, so errors point at
+/// the offending line, and returns the keys that were not recognized.
+fn check(text: &str) -> Result<Vec<String>, toml::de::Error> {
+    let mut unknown = Vec::new();
+    let _: Settings = serde_ignored::deserialize(toml::Deserializer::parse(text)?, |path| {
+        let path = path.to_string();
+        if path != "profile" && path != "profiles" {
+            unknown.push(path);
+        }
+    })?;
+    let _: ProfileSection = serde_ignored::deserialize(toml::Deserializer::parse(text)?, |path| {
+        let path = path.to_string();
+        if path.starts_with("profiles.") {
+            unknown.push(path);
+        }
+    })?;
+    Ok(unknown)
+}
+
+/// Suggests a known setting for a misspelled key path such as `theme.colour`.
+fn suggestion(path: &str) -> String {
+    let mut keys: Vec<&str> = path.split('.').collect();
+    if keys.first() == Some(&"profiles") {
+        keys.drain(..keys.len().min(2));
+    }
+    let Some((name, parents)) = keys.split_last() else { return String::new() };
+
+    let defaults = Table::try_from(Settings::default()).unwrap_or_default();
+    let mut table = &defaults;
+    for parent in parents {
+        match table.get(*parent) {
+            Some(Value::Table(inner)) => table = inner,
+            _ => return String::new(),
+        }
+    }
+    match suggest::closest(name, table.keys().map(String::as_str)) {
+        Some(known) => format!(", did you mean '{}'?", known),
+        None => String::new(),
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct NoConfigPathError;
+
+impl Display for NoConfigPathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No config path given and neither $XDG_CONFIG_HOME nor $HOME is set")
+    }
+}
+
+impl Error for NoConfigPathError {}
+
+#[derive(Debug, Clone)]
+pub struct ConfigExistsError {
+    path: PathBuf,
+}
+
+impl Display for ConfigExistsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} already exists, pass --force to replace it", self.path.display())
+    }
+}
+
+impl Error for ConfigExistsError {}
+
+/// Writes a whole config file, creating its directory. An existing file is
+/// only replaced with `force`.
+pub fn write_new(path: &Path, text: &str, force: bool) -> Result<(), Box<dyn Error>> {
+    if path.exists() && !force {
+        return Err(Box::new(ConfigExistsError { path: path.to_path_buf() }));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    xdg::config_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+impl Config {
+    fn new(path: Option<PathBuf>) -> Config {
+        let defaults = Table::try_from(Settings::default()).unwrap_or_default();
+        Config {
+            path,
+            default_profile: None,
+            base: Table::new(),
+            profiles: BTreeMap::new(),
+            resources: Table::new(),
+            env: env::overlay(&defaults, |overlay| {
+                let mut table = defaults.clone();
+                merge(&mut table, overlay.clone());
+                table.try_into::<Settings>().map(|_| ()).map_err(|e| e.message().to_string())
+            }),
+        }
+    }
+
+    /// Loads the config from `path`, or from the XDG location if none is given.
+    /// A missing file at the default location is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Config, Box<dyn Error>> {
+        match path {
+            Some(path) => Config::read(path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => Config::read(path, false),
+                None => Ok(Config::new(None)),
+            },
+        }
+    }
+
+    /// Reads the config again from the same file. A file that has been removed
+    /// since yields the defaults.
+    pub fn reload(&self) -> Result<Config, Box<dyn Error>> {
+        let config = match &self.path {
+            Some(path) => Config::read(path.clone(), false)?,
+            None => Config::new(None),
+        };
+        Ok(Config { resources: self.resources.clone(), ..config })
+    }
+
+    fn read(path: PathBuf, required: bool) -> Result<Config, Box<dyn Error>> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => return Ok(Config::new(Some(path))),
+            Err(e) => return Err(Box::new(ConfigError { path, message: e.to_string() })),
+        };
+        Config::parse(path, &text)
+    }
+
+    /// Parses and validates config file contents, attributing errors to `path`.
+    pub fn parse(path: PathBuf, text: &str) -> Result<Config, Box<dyn Error>> {
+        let error = |message: String| ConfigError { path: path.clone(), message };
+
+        for unknown in check(text).map_err(|e| error(e.to_string()))? {
+            warn!("{}: unknown setting '{}'{}", path.display(), unknown, suggestion(&unknown));
+        }
+
+        let mut base: Table = text.parse().map_err(|e: toml::de::Error| error(e.to_string()))?;
+
+        let default_profile = match base.remove("profile") {
+            Some(Value::String(name)) => Some(name),
+            Some(_) => return Err(Box::new(error("'profile' must be a string".to_string()))),
+            None => None,
+        };
+
+        let profiles = match base.remove("profiles") {
+            Some(Value::Table(profiles)) => profiles.into_iter()
+                .map(|(name, profile)| match profile {
+                    Value::Table(table) => Ok((name, table)),
+                    _ => Err(error(format!("profile '{}' must be a table", name))),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(Box::new(error("'profiles' must be a table".to_string()))),
+            None => BTreeMap::new(),
+        };
+
+        let config = Config { default_profile, base, profiles, ..Config::new(Some(path.clone())) };
+        for name in config.profile_names() {
+            config.settings(Some(name))?;
+        }
+        config.settings(None)?;
+        Ok(config)
+    }
+
+    /// Sets each of `changes`, dotted paths such as `theme.opacity`, in the
+    /// config file's base settings, which a profile may still override, and
+    /// writes it back. Returns the config as it now reads.
+    pub fn save(&self, changes: &[(&str, Value)]) -> Result<Config, Box<dyn Error>> {
+        let path = self.path.clone().ok_or(NoConfigPathError)?;
+        let mut text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Box::new(ConfigError { path, message: e.to_string() })),
+        };
+        for (key, value) in changes {
+            text = persist::set(&text, key, value)?;
+        }
+        let config = Config::parse(path.clone(), &text)?;
+        write_new(&path, &text, true)?;
+        Ok(Config { resources: self.resources.clone(), ..config })
+    }
+
+    /// Sets settings from the X resource database, which sit between the
+    /// defaults and the config file.
+    pub fn set_resources(&mut self, resources: Table) {
+        self.resources = resources;
+    }
+
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles.contains_key(name)
+    }
+
+    pub fn profile_names(&self) -> impl Iterator<Item=&str> {
+        self.profiles.keys().map(String::as_str)
+    }
+
+    /// Resolves the settings for a profile, `None` being the base config. Layers
+    /// are applied as defaults, X resources, config file, profile, environment.
+    pub fn settings(&self, profile: Option<&str>) -> Result<Settings, Box<dyn Error>> {
+        let mut table = Table::try_from(Settings::default())?;
+        merge(&mut table, self.resources.clone());
+        merge(&mut table, self.base.clone());
+        if let Some(name) = profile {
+            let overlay = self.profiles.get(name).ok_or_else(|| UnknownProfileError { name: name.to_string() })?;
+            merge(&mut table, overlay.clone());
+        }
+        merge(&mut table, self.env.clone());
+        table.try_into().map_err(|e: toml::de::Error| {
+            let path = self.path.clone().unwrap_or_default();
+            let message = match profile {
+                Some(name) => format!("profile '{}': {}", name, e.message()),
+                None => e.message().to_string(),
+            };
+            Box::new(ConfigError { path, message }) as Box<dyn Error>
+        })
+    }
+
+    /// Returns the profile that follows `current` in the cycling order, which
+    /// starts with the base config.
+    pub fn next_profile(&self, current: Option<&str>) -> Option<String> {
+        match current {
+            None => self.profile_names().next(),
+            Some(current) => self.profile_names().skip_while(|&name| name != current).nth(1),
+        }.map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+[theme]
+accent = \"#333333\"
+
+[profiles.dim.theme]
+accent = \"#444444\"
+
+[profiles.bright]
+unit = \"mm\"
+";
+
+    fn config() -> Config {
+        let mut config = Config::parse(PathBuf::from("config.toml"), FILE).unwrap();
+        // Whatever RULER_* variables the tests happen to run with.
+        config.env = Table::new();
+        config
+    }
+
+    fn table(text: &str) -> Table {
+        text.parse().unwrap()
+    }
+
+    fn accent(config: &Config, profile: Option<&str>) -> String {
+        config.settings(profile).unwrap().theme.accent.to_string()
+    }
+
+    #[test]
+    fn layers_apply_in_order() {
+        let mut config = config();
+        assert_eq!(accent(&Config { env: Table::new(), ..Config::new(None) }, None), Color::gray(0.7).to_string());
+
+        // X resources sit over the defaults and under the file.
+        config.set_resources(table("sizes.half_width = 30.0\ntheme.accent = \"#222222\""));
+        assert_eq!(config.settings(None).unwrap().sizes.half_width, 30.0);
+        assert_eq!(accent(&config, None), "#333333");
+
+        // A profile over the file, changing only what it lists.
+        assert_eq!(accent(&config, Some("dim")), "#444444");
+        let bright = config.settings(Some("bright")).unwrap();
+        assert_eq!((bright.theme.accent.to_string(), bright.unit), ("#333333".to_string(), Unit::Mm));
+
+        // The environment over any profile.
+        config.env = table("theme.accent = \"#555555\"");
+        assert_eq!(accent(&config, None), "#555555");
+        assert_eq!(accent(&config, Some("dim")), "#555555");
+    }
+
+    #[test]
+    fn unknown_profiles_are_errors() {
+        let error = config().settings(Some("dark")).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown profile 'dark'");
+    }
+
+    #[test]
+    fn profiles_cycle_through_the_base_config() {
+        let config = config();
+        let mut cycle = vec![None];
+        for _ in 0..4 {
+            cycle.push(config.next_profile(cycle.last().unwrap().as_deref()));
+        }
+        assert_eq!(cycle, [None, Some("bright".to_string()), Some("dim".to_string()), None, Some("bright".to_string())]);
+        assert_eq!(config.next_profile(Some("removed")), None);
+        assert_eq!(Config::new(None).next_profile(None), None);
+    }
+}
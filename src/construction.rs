@@ -0,0 +1,102 @@
+use std::error::Error;
+
+use cairo::Context;
+use glam::DVec2;
+use xcb::x::{ClearArea, ExposeEvent, MapWindow, UnmapWindow, Window};
+
+use ruler::state::RulerState;
+
+use crate::backend::{Render, XCBObjects};
+use crate::config::Settings;
+
+const DASH: [f64; 2] = [6.0, 4.0];
+
+/// Dashed lines across the whole screen to line the ruler up with what is
+/// not under it: the perpendiculars to it through each endpoint, and its
+/// line mirrored across the horizontal through `from`. They are in a window
+/// of their own that the pointer passes through.
+pub struct Construction {
+    pub window: Window,
+    visible: bool,
+    /// The endpoints the lines were last painted for.
+    at: Option<(DVec2, DVec2)>,
+}
+
+impl Construction {
+    pub fn create(xcb: &XCBObjects, screen_size: DVec2) -> Result<Construction, Box<dyn Error>> {
+        let window = xcb.create_overlay(screen_size.x as u16, screen_size.y as u16)?;
+        Ok(Construction { window, visible: false, at: None })
+    }
+
+    pub fn shown(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self, xcb: &XCBObjects, state: &RulerState) {
+        self.visible = !self.visible;
+        if self.visible {
+            xcb.conn.send_request(&MapWindow { window: self.window });
+            self.follow(xcb, state);
+        } else {
+            xcb.conn.send_request(&UnmapWindow { window: self.window });
+            self.at = None;
+        }
+    }
+
+    /// Has the lines painted again if the endpoints moved, or anyway after
+    /// `invalidate`.
+    pub fn follow(&mut self, xcb: &XCBObjects, state: &RulerState) {
+        if self.visible && self.at != Some((state.from, state.to)) {
+            self.at = Some((state.from, state.to));
+            xcb.conn.send_request(&ClearArea { exposures: true, window: self.window, x: 0, y: 0, width: 0, height: 0 });
+        }
+    }
+
+    /// Forgets what was painted, so the next `follow` paints again.
+    pub fn invalidate(&mut self) {
+        self.at = None;
+    }
+
+    /// Paints the lines once the last of a series of Expose events for them
+    /// arrives, since they are drawn in full.
+    pub fn expose(&self, ev: &ExposeEvent, render: &Render, state: &RulerState, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        if ev.count() > 0 {
+            return Ok(());
+        }
+        render.clear()?;
+        paint(&render.ctx, state, settings)
+    }
+
+    /// Draws the lines onto `ctx` in screen coordinates, if they are shown.
+    pub fn export(&self, ctx: &Context, state: &RulerState, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        if self.visible {
+            paint(ctx, state, settings)?;
+        }
+        Ok(())
+    }
+}
+
+/// Each line as a point it goes through and its direction.
+fn lines(state: &RulerState) -> Vec<(DVec2, DVec2)> {
+    let Some(along) = (state.to - state.from).try_normalize() else { return Vec::new() };
+    let across = along.perp();
+    let mirrored = DVec2::new(along.x, -along.y);
+    vec![(state.from, across), (state.to, across), (state.from, mirrored)]
+}
+
+fn paint(ctx: &Context, state: &RulerState, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let accent = settings.theme.accent;
+    let reach = state.screen_size.length();
+
+    ctx.set_source_rgba(accent.r, accent.g, accent.b, settings.theme.opacity.max(0.6));
+    ctx.set_line_width(1.0);
+    ctx.set_dash(&DASH, 0.0);
+    for (through, direction) in lines(state) {
+        let (start, end) = (through - direction * reach, through + direction * reach);
+        ctx.move_to(start.x, start.y);
+        ctx.line_to(end.x, end.y);
+    }
+    ctx.stroke()?;
+    ctx.set_dash(&[], 0.0);
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::{FromStr, SplitWhitespace};
+use std::sync::mpsc::Sender;
+
+use clap::ValueEnum;
+use glam::DVec2;
+
+use ruler::unit::Unit;
+
+/// A request in the line-based control protocol, shared by every channel
+/// that lets other programs drive the ruler.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Request {
+    /// Reply with the current state
+    Get,
+    /// Move either or both endpoints
+    Set { from: Option<DVec2>, to: Option<DVec2> },
+    /// Turn the ruler around `from` by this many degrees counterclockwise
+    Rotate(f64),
+    /// Switch the scale and readout to another unit
+    Unit(Unit),
+    /// Receive the state every time it changes
+    Subscribe,
+    /// Add a ruler beside the last one
+    NewRuler,
+    Toggle,
+    Quit,
+}
+
+/// A request together with the way back to the client that sent it. Replies
+/// are single lines, and subscribers keep `reply` to receive updates.
+pub struct Message {
+    pub request: Request,
+    pub reply: Sender<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidRequestError {
+    line: String,
+    message: &'static str,
+}
+
+impl Display for InvalidRequestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid request '{}': {}", self.line, self.message)
+    }
+}
+
+impl Error for InvalidRequestError {}
+
+/// The next word as a finite number, so `nan` and `inf` are refused too.
+fn coordinate(words: &mut SplitWhitespace) -> Option<f64> {
+    words.next()?.parse().ok().filter(|number: &f64| number.is_finite())
+}
+
+impl FromStr for Request {
+    type Err = InvalidRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |message| InvalidRequestError { line: s.to_string(), message };
+        let mut words = s.split_whitespace();
+
+        let request = match words.next() {
+            Some("get") => Request::Get,
+            Some("subscribe") => Request::Subscribe,
+            Some("new") => Request::NewRuler,
+            Some("toggle") => Request::Toggle,
+            Some("quit") => Request::Quit,
+            Some("rotate") => Request::Rotate(coordinate(&mut words).ok_or_else(|| error("expected an angle in degrees"))?),
+            Some("unit") => {
+                let unit = words.next().and_then(|name| Unit::from_str(name, true).ok());
+                Request::Unit(unit.ok_or_else(|| error("expected px, mm, cm or in"))?)
+            }
+            Some("set") => {
+                let (mut from, mut to) = (None, None);
+                while let Some(endpoint) = words.next() {
+                    let point = match (coordinate(&mut words), coordinate(&mut words)) {
+                        (Some(x), Some(y)) => DVec2::new(x, y),
+                        _ => return Err(error("expected two coordinates after the endpoint")),
+                    };
+                    match endpoint {
+                        "from" => from = Some(point),
+                        "to" => to = Some(point),
+                        _ => return Err(error("expected 'from' or 'to'")),
+                    }
+                }
+                if from.is_none() && to.is_none() {
+                    return Err(error("nothing to set"));
+                }
+                return Ok(Request::Set { from, to });
+            }
+            _ => return Err(error("unknown command")),
+        };
+
+        match words.next() {
+            Some(_) => Err(error("unexpected arguments")),
+            None => Ok(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Request, &'static str> {
+        line.parse::<Request>().map_err(|e| e.message)
+    }
+
+    #[test]
+    fn parses_every_verb() {
+        assert_eq!(parse("get"), Ok(Request::Get));
+        assert_eq!(parse("subscribe"), Ok(Request::Subscribe));
+        assert_eq!(parse("new"), Ok(Request::NewRuler));
+        assert_eq!(parse("toggle"), Ok(Request::Toggle));
+        assert_eq!(parse("quit"), Ok(Request::Quit));
+        assert_eq!(parse("rotate -22.5"), Ok(Request::Rotate(-22.5)));
+        assert_eq!(parse("unit mm"), Ok(Request::Unit(Unit::Mm)));
+        assert_eq!(parse("unit IN"), Ok(Request::Unit(Unit::In)));
+        assert_eq!(parse("set from 10 20"), Ok(Request::Set { from: Some(DVec2::new(10.0, 20.0)), to: None }));
+        assert_eq!(parse("set to 1.5 -2"), Ok(Request::Set { from: None, to: Some(DVec2::new(1.5, -2.0)) }));
+        assert_eq!(
+            parse("set to 30 40 from 10 20"),
+            Ok(Request::Set { from: Some(DVec2::new(10.0, 20.0)), to: Some(DVec2::new(30.0, 40.0)) })
+        );
+    }
+
+    #[test]
+    fn refuses_unknown_commands() {
+        assert_eq!(parse(""), Err("unknown command"));
+        assert_eq!(parse("GET"), Err("unknown command"));
+        assert_eq!(parse("move 1 2"), Err("unknown command"));
+        assert_eq!(parse("unit furlong"), Err("expected px, mm, cm or in"));
+        assert_eq!(parse("set middle 1 2"), Err("expected 'from' or 'to'"));
+    }
+
+    #[test]
+    fn refuses_the_wrong_number_of_arguments() {
+        assert_eq!(parse("get now"), Err("unexpected arguments"));
+        assert_eq!(parse("quit 0"), Err("unexpected arguments"));
+        assert_eq!(parse("rotate"), Err("expected an angle in degrees"));
+        assert_eq!(parse("rotate 10 20"), Err("unexpected arguments"));
+        assert_eq!(parse("unit"), Err("expected px, mm, cm or in"));
+        assert_eq!(parse("unit mm cm"), Err("unexpected arguments"));
+        assert_eq!(parse("set"), Err("nothing to set"));
+        assert_eq!(parse("set from 10"), Err("expected two coordinates after the endpoint"));
+        assert_eq!(parse("set from 10 20 30"), Err("expected two coordinates after the endpoint"));
+    }
+
+    #[test]
+    fn refuses_what_is_not_a_number() {
+        assert_eq!(parse("rotate ten"), Err("expected an angle in degrees"));
+        assert_eq!(parse("rotate nan"), Err("expected an angle in degrees"));
+        assert_eq!(parse("set from 10 inf"), Err("expected two coordinates after the endpoint"));
+        assert_eq!(parse("set to 1,5 2"), Err("expected two coordinates after the endpoint"));
+    }
+
+    #[test]
+    fn ignores_surrounding_whitespace() {
+        assert_eq!(parse("  get\r\n"), Ok(Request::Get));
+        assert_eq!(parse("\tset  from 10\t20  "), Ok(Request::Set { from: Some(DVec2::new(10.0, 20.0)), to: None }));
+        assert_eq!(parse("rotate 45 \n"), Ok(Request::Rotate(45.0)));
+    }
+
+    #[test]
+    fn errors_quote_the_line() {
+        let error = "rotate ten".parse::<Request>().unwrap_err();
+        assert_eq!(error.to_string(), "Invalid request 'rotate ten': expected an angle in degrees");
+    }
+}
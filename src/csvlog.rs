@@ -0,0 +1,36 @@
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ruler::output::Report;
+
+const HEADER: &str = "timestamp,from_x,from_y,to_x,to_y,length_px,length_mm,length_cm,length_in,angle";
+
+/// Appends finished measurements to a CSV file for `--log`. The header is
+/// written when the file is new, so several sessions can share one log.
+pub struct CsvLog {
+    file: File,
+}
+
+impl CsvLog {
+    pub fn open(path: &Path) -> Result<CsvLog, Box<dyn Error>> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", HEADER)?;
+        }
+        Ok(CsvLog { file })
+    }
+
+    pub fn record(&mut self, report: &Report) -> Result<(), Box<dyn Error>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        let Report { from, to, length, angle } = report;
+        writeln!(
+            self.file,
+            "{},{},{},{},{},{:.2},{:.2},{:.3},{:.3},{:.2}",
+            timestamp, from.x, from.y, to.x, to.y, length.px, length.mm, length.cm, length.inches, angle
+        )?;
+        Ok(())
+    }
+}
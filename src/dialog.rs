@@ -0,0 +1,198 @@
+use std::error::Error;
+
+use cairo::{FontSlant, FontWeight, Operator};
+use clap::ValueEnum;
+use toml::Value;
+use xcb::x::{MapWindow, UnmapWindow, Window};
+
+use ruler::unit::Unit;
+
+use crate::backend::{Render, XCBObjects};
+use crate::config::{Color, Settings};
+
+pub const WIDTH: u16 = 320;
+const ROW_HEIGHT: f64 = 32.0;
+const PADDING: f64 = 16.0;
+
+/// The themes the settings cycle through, by name, background and accent.
+const THEMES: [(&str, Color, Color); 3] = [
+    ("light", Color::gray(1.0), Color::gray(0.7)),
+    ("dark", Color::gray(0.15), Color::gray(0.8)),
+    ("blueprint", Color { r: 0.1, g: 0.25, b: 0.5 }, Color::gray(1.0)),
+];
+const OPACITIES: [f64; 5] = [0.2, 0.4, 0.6, 0.8, 1.0];
+const SNAP_ANGLES: [f64; 4] = [0.0, 15.0, 45.0, 90.0];
+const TICK_SPACINGS: [f64; 4] = [0.0, 4.0, 8.0, 16.0];
+
+/// A setting the window shows, a row each.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Row {
+    Unit,
+    Theme,
+    Opacity,
+    SnapAngle,
+    TickSpacing,
+    Clamp,
+    Body,
+}
+
+const ROWS: [Row; 7] = [Row::Unit, Row::Theme, Row::Opacity, Row::SnapAngle, Row::TickSpacing, Row::Clamp, Row::Body];
+
+impl Row {
+    fn label(self) -> &'static str {
+        match self {
+            Row::Unit => "Unit",
+            Row::Theme => "Theme",
+            Row::Opacity => "Opacity",
+            Row::SnapAngle => "Snap angle to",
+            Row::TickSpacing => "Ticks apart by",
+            Row::Clamp => "Keep on the screen",
+            Row::Body => "Drag by the bar",
+        }
+    }
+
+    fn value(self, settings: &Settings) -> String {
+        let yes_no = |on: bool| if on { "yes" } else { "no" }.to_string();
+        match self {
+            Row::Unit => settings.unit.suffix().to_string(),
+            Row::Theme => theme(settings).map_or("custom", |i| THEMES[i].0).to_string(),
+            Row::Opacity => format!("{:.0}%", settings.theme.opacity * 100.0),
+            Row::SnapAngle if settings.drag.snap_angle == 0.0 => "off".to_string(),
+            Row::SnapAngle => format!("{}°", settings.drag.snap_angle),
+            Row::TickSpacing if settings.sizes.tick_spacing == 0.0 => "any".to_string(),
+            Row::TickSpacing => format!("{} px", settings.sizes.tick_spacing),
+            Row::Clamp => yes_no(settings.drag.clamp_to_screen),
+            Row::Body => yes_no(settings.drag.body),
+        }
+    }
+
+    /// The settings to save for the next value after the current one, or
+    /// the one before it.
+    pub fn step(self, settings: &Settings, forward: bool) -> Vec<(&'static str, Value)> {
+        match self {
+            Row::Unit => {
+                let units = Unit::value_variants();
+                let i = units.iter().position(|unit| *unit == settings.unit).unwrap_or(0);
+                let unit = units[cycle(i, units.len(), forward)];
+                vec![("unit", Value::String(unit.suffix().to_string()))]
+            }
+            Row::Theme => {
+                let (_, background, accent) = THEMES[theme(settings).map_or(0, |i| cycle(i, THEMES.len(), forward))];
+                vec![("theme.background", Value::String(background.to_string())), ("theme.accent", Value::String(accent.to_string()))]
+            }
+            Row::Opacity => vec![("theme.opacity", Value::Float(step_through(&OPACITIES, settings.theme.opacity, forward)))],
+            Row::SnapAngle => vec![("drag.snap_angle", Value::Float(step_through(&SNAP_ANGLES, settings.drag.snap_angle, forward)))],
+            Row::TickSpacing => vec![("sizes.tick_spacing", Value::Float(step_through(&TICK_SPACINGS, settings.sizes.tick_spacing, forward)))],
+            Row::Clamp => vec![("drag.clamp_to_screen", Value::Boolean(!settings.drag.clamp_to_screen))],
+            Row::Body => vec![("drag.body", Value::Boolean(!settings.drag.body))],
+        }
+    }
+}
+
+/// Which of the themes the settings have, compared as written since the
+/// file only keeps colors to 8 bits.
+fn theme(settings: &Settings) -> Option<usize> {
+    let written = (settings.theme.background.to_string(), settings.theme.accent.to_string());
+    THEMES.iter().position(|(_, background, accent)| (background.to_string(), accent.to_string()) == written)
+}
+
+fn cycle(i: usize, len: usize, forward: bool) -> usize {
+    if forward { (i + 1) % len } else { (i + len - 1) % len }
+}
+
+/// The next of `options` above `current`, or below it going back, wrapping
+/// around at either end.
+fn step_through(options: &[f64], current: f64, forward: bool) -> f64 {
+    let next = if forward {
+        options.iter().find(|&&option| option > current + 1e-9).or(options.first())
+    } else {
+        options.iter().rev().find(|&&option| option < current - 1e-9).or(options.last())
+    };
+    *next.expect("there are options")
+}
+
+/// A window of the settings most often changed, for those who would rather
+/// not edit the config file. Clicking a row moves its setting to the next
+/// value, and the right button to the one before, saving it to the file.
+pub struct Dialog {
+    pub window: Window,
+    shown: bool,
+    /// Why the last change could not be saved, shown until the next.
+    pub error: Option<String>,
+}
+
+impl Dialog {
+    pub fn height() -> u16 {
+        (PADDING * 2.0 + ROW_HEIGHT * (ROWS.len() + 1) as f64) as u16
+    }
+
+    pub fn create(xcb: &XCBObjects) -> Result<Dialog, Box<dyn Error>> {
+        let window = xcb.create_dialog(WIDTH, Dialog::height(), "Ruler settings")?;
+        Ok(Dialog { window, shown: false, error: None })
+    }
+
+    pub fn toggle(&mut self, xcb: &XCBObjects) {
+        if self.shown {
+            self.hide(xcb);
+        } else {
+            xcb.conn.send_request(&MapWindow { window: self.window });
+            self.shown = true;
+        }
+    }
+
+    pub fn hide(&mut self, xcb: &XCBObjects) {
+        if self.shown {
+            xcb.conn.send_request(&UnmapWindow { window: self.window });
+            self.shown = false;
+        }
+    }
+
+    /// The row at `y` pixels down the window.
+    pub fn row_at(&self, y: f64) -> Option<Row> {
+        let i = ((y - PADDING) / ROW_HEIGHT).floor();
+        (i >= 0.0).then(|| ROWS.get(i as usize).copied()).flatten()
+    }
+
+    pub fn paint(&self, render: &Render, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        let theme = &settings.theme;
+        let ctx = &render.ctx;
+        // Opaque and in black or white on the background, to stay readable
+        // whatever the ruler's own opacity and accent.
+        let background = theme.background;
+        let luminance = background.r * 0.2126 + background.g * 0.7152 + background.b * 0.0722;
+        let ink = if luminance > 0.5 { Color::gray(0.1) } else { Color::gray(0.95) };
+
+        ctx.save()?;
+        ctx.set_operator(Operator::Source);
+        ctx.set_source_rgba(background.r, background.g, background.b, 1.0);
+        ctx.paint()?;
+        ctx.set_operator(Operator::Over);
+        ctx.select_font_face(&theme.font, FontSlant::Normal, FontWeight::Normal);
+        ctx.set_font_size(theme.font_size);
+
+        let baseline = |i: usize| PADDING + ROW_HEIGHT * (i as f64 + 0.5) + theme.font_size * 0.35;
+        for (i, row) in ROWS.iter().enumerate() {
+            ctx.set_source_rgb(ink.r, ink.g, ink.b);
+            ctx.move_to(PADDING, baseline(i));
+            ctx.show_text(row.label())?;
+
+            let value = row.value(settings);
+            let extents = ctx.text_extents(&value)?;
+            ctx.set_source_rgb(theme.accent.r, theme.accent.g, theme.accent.b);
+            ctx.rectangle(WIDTH as f64 - PADDING - extents.width() - 12.0, PADDING + ROW_HEIGHT * i as f64 + 4.0, extents.width() + 12.0, ROW_HEIGHT - 8.0);
+            ctx.set_line_width(1.0);
+            ctx.stroke()?;
+            ctx.set_source_rgb(ink.r, ink.g, ink.b);
+            ctx.move_to(WIDTH as f64 - PADDING - 6.0 - extents.width() - extents.x_bearing(), baseline(i));
+            ctx.show_text(&value)?;
+        }
+
+        let note = self.error.as_deref().unwrap_or("Click to change, right click to go back");
+        ctx.set_font_size(theme.font_size * 0.75);
+        ctx.set_source_rgba(ink.r, ink.g, ink.b, 0.7);
+        ctx.move_to(PADDING, baseline(ROWS.len()));
+        ctx.show_text(note)?;
+        ctx.restore()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,952 @@
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::{Entry, HashMap};
+use std::error::Error;
+use std::f64::consts::PI;
+#[cfg(feature = "scripting")]
+use std::rc::Rc;
+
+use cairo::{Context, FillRule, Filter, FontSlant, FontWeight, ImageSurface, Operator};
+use glam::DVec2;
+
+use ruler::color::{contrast_ratio, steepest_change, Rgb, AA, AAA};
+use ruler::edges::{crossings, spacing};
+use ruler::geom::Rect;
+use ruler::scale::{Scale, TickKind};
+use ruler::state::{Handle, Mode, RulerState};
+
+use crate::config::{Color, Edge, Settings, Theme};
+use crate::loupe::REACH;
+use crate::stats::FrameStats;
+#[cfg(feature = "scripting")]
+use crate::overlay::{Overlay, State as OverlayState};
+
+// Side of the holes left at the endpoints while sampling, odd so the
+// endpoint's pixel is in the middle.
+const SAMPLE_HOLE: f64 = 3.0;
+
+// Width of the slit left along the line while its luminance is plotted,
+// enough to uncover every pixel the line passes through.
+const PROFILE_SLIT: f64 = 3.0;
+
+// Rulers shorter than this show a compact readout instead of the protractor.
+const PROTRACTOR_LENGTH: f64 = 150.0;
+
+// Room kept between the compact readout and the ends of the bar.
+const SHORT_PADDING: f64 = 2.0;
+
+// Ratios within this of one between small whole numbers are also given as
+// that, up to the largest term.
+const RATIO_TOLERANCE: f64 = 0.005;
+const RATIO_TERMS: u32 = 10;
+
+/// How the ruler is drawn beyond what the settings say.
+#[derive(Clone)]
+pub struct View {
+    pub dock: Option<Edge>,
+    pub px_per_mm: DVec2,
+    #[cfg(feature = "scripting")]
+    pub overlay: Option<Rc<Overlay>>,
+    pub labels: RefCell<Labels>,
+    pub stats: RefCell<FrameStats>,
+    /// Whether the loupe shows up while an endpoint is dragged.
+    pub loupe: bool,
+    /// How far the dragged endpoint looks for an edge to snap to, while it does.
+    pub edge_snap: Option<usize>,
+    /// Whether the colors under the endpoints are sampled and shown.
+    pub sampling: bool,
+    /// The colors last sampled under `from` and `to`, `None` off the screen.
+    pub samples: Cell<[Option<Rgb>; 2]>,
+    /// Whether the luminance along the line is plotted.
+    pub luminance: bool,
+    /// The luminance last read under each pixel along the line from `from`,
+    /// `None` off the screen.
+    pub profile: RefCell<Vec<Option<f64>>>,
+    /// The gap between two windows the ruler was last laid across.
+    pub gap: Option<Gap>,
+    /// Whether the ruler is being laid along the calibration's reference
+    /// object, to press `calibrate` again once it is.
+    pub calibrating: bool,
+    /// Whether the bar shows the screen under the start of the line magnified.
+    pub zoom_strip: bool,
+    /// The last capture for the zoomed strip.
+    pub strip: Option<Strip>,
+    /// What the change in length is read out against.
+    pub committed: Committed,
+    /// The length in millimeters stored with `store_reference`, which the
+    /// ruler's length is read out as a ratio of.
+    pub reference: Option<f64>,
+    /// The direction stored with `store_reference_angle`, as an angle from
+    /// the screen's x-axis like the ruler's, which the angle is read out from.
+    pub reference_angle: Option<f64>,
+    /// Whether the ticks and their labels are along the +y edge, with the
+    /// readouts moving to the −y edge out of their way.
+    pub flip_ticks: bool,
+    /// Whether lengths read out to hundredths of a pixel, along with the
+    /// endpoints, for keyboard nudges of a fraction of one.
+    pub precise: bool,
+}
+
+impl View {
+    pub fn docked(&self) -> bool {
+        self.dock.is_some()
+    }
+}
+
+/// The distance between the centers of two windows whose gap the ruler
+/// measures, shown until it is moved.
+#[derive(Debug, Copy, Clone)]
+pub struct Gap {
+    /// Where the ruler's endpoints were put across the gap.
+    pub endpoints: (DVec2, DVec2),
+    /// In pixels.
+    pub centers: f64,
+}
+
+/// The lengths in pixels of the last two measurements the ruler was left
+/// at, for how much it changed since.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Committed {
+    latest: Option<f64>,
+    before: Option<f64>,
+}
+
+impl Committed {
+    pub fn commit(&mut self, length: f64) {
+        if self.latest != Some(length) {
+            (self.latest, self.before) = (Some(length), self.latest);
+        }
+    }
+
+    /// How much longer `length` is than the last measurement, or than the
+    /// one before while it is still the last.
+    pub fn delta(&self, length: f64) -> Option<f64> {
+        let reference = if self.latest == Some(length) { self.before } else { self.latest };
+        reference.map(|reference| length - reference)
+    }
+}
+
+/// The screen under the start of the line, captured with the ruler hidden
+/// to be shown magnified in the bar until the ruler moves.
+#[derive(Clone)]
+pub struct Strip {
+    pub image: ImageSurface,
+    /// Where the image's top left pixel is on the screen.
+    pub origin: DVec2,
+    /// Where the ruler's endpoints were when it was captured.
+    pub endpoints: (DVec2, DVec2),
+    pub zoom: f64,
+}
+
+impl Strip {
+    /// The box around what the bar of a ruler from `from` to `to` shows
+    /// magnified `zoom` times: the first `1 / zoom` of the line and as
+    /// little of the bar either side.
+    pub fn area(from: DVec2, to: DVec2, half_width: f64, zoom: f64) -> Rect {
+        let along = (to - from) / zoom;
+        let across = along.try_normalize().unwrap_or(DVec2::X).perp() * half_width / zoom;
+        let corners = [from + across, from - across, from + along + across, from + along - across];
+        let (min, max) = corners.iter().fold((corners[0], corners[0]), |(min, max), corner| (min.min(*corner), max.max(*corner)));
+        Rect { min: min.floor(), max: max.ceil() }
+    }
+}
+
+/// Tick labels and their widths, kept between frames since the same few come
+/// up on every redraw. Changing the font starts over.
+#[derive(Clone, Default)]
+pub struct Labels {
+    font: (String, u64),
+    labels: HashMap<i64, (String, f64)>,
+}
+
+impl Labels {
+    /// The text and width of the label for `value` in the context's current font, `font` at `size`.
+    fn get(&mut self, ctx: &Context, value: i64, font: &str, size: f64) -> Result<&(String, f64), cairo::Error> {
+        if self.font.0 != font || self.font.1 != size.to_bits() {
+            self.font = (font.to_string(), size.to_bits());
+            self.labels.clear();
+        }
+        Ok(match self.labels.entry(value) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let text = value.to_string();
+                let width = ctx.text_extents(&text)?.width();
+                entry.insert((text, width))
+            }
+        })
+    }
+}
+
+fn set_source(ctx: &Context, color: Color, alpha: f64) {
+    ctx.set_source_rgba(color.r, color.g, color.b, alpha);
+}
+
+/// Draws the ruler with the window's top left corner at `origin` on the screen.
+pub fn draw(ctx: &Context, state: &RulerState, origin: DVec2, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    let (from, to) = (state.from - origin, state.to - origin);
+    let theme = &settings.theme;
+    let opacity = theme.opacity;
+    let bg = theme.background;
+    let accent = theme.accent;
+    let half_width = settings.sizes.half_width;
+    let control_radius = state.handle_radius(settings.sizes.control_radius);
+
+    ctx.set_operator(Operator::Source);
+    ctx.set_source_rgba(0.0, 0.0, 0.0, 0.0);
+    ctx.paint()?;
+
+    ctx.save()?;
+
+    // Holes for the loupe, edge snapping and the sampler to see the screen
+    // through rather than the ruler. Those around the dragged endpoint have
+    // room to spare for the compositor lagging a frame behind.
+    let mut holes = vec![];
+    if let Some(endpoint) = state.dragged_endpoint().filter(|_| view.loupe) {
+        holes.push((endpoint, (2 * REACH + 1) as f64 * 2.0));
+    }
+    if let (Some(endpoint), Some(reach)) = (state.dragged_endpoint(), view.edge_snap) {
+        holes.push((endpoint, (2 * reach + 1) as f64 * 2.0));
+    }
+    if view.sampling {
+        holes.extend([(state.from, SAMPLE_HOLE), (state.to, SAMPLE_HOLE)]);
+    }
+    for (point, side) in holes {
+        // One clip for each, since holes that overlap would cancel out within one.
+        let corner = (point - origin).floor() - ((side - 1.0) / 2.0).floor();
+        let (x1, y1, x2, y2) = ctx.clip_extents()?;
+        ctx.set_fill_rule(FillRule::EvenOdd);
+        ctx.rectangle(x1, y1, x2 - x1, y2 - y1);
+        ctx.rectangle(corner.x, corner.y, side, side);
+        ctx.clip();
+        ctx.set_fill_rule(FillRule::Winding);
+    }
+    if view.luminance {
+        let along = (to - from).try_normalize().unwrap_or(DVec2::X) * PROFILE_SLIT / 2.0;
+        let (start, end, across) = (from - along, to + along, along.perp());
+        let (x1, y1, x2, y2) = ctx.clip_extents()?;
+        ctx.set_fill_rule(FillRule::EvenOdd);
+        ctx.rectangle(x1, y1, x2 - x1, y2 - y1);
+        for corner in [start + across, end + across, end - across, start - across] {
+            ctx.line_to(corner.x, corner.y);
+        }
+        ctx.close_path();
+        ctx.clip();
+        ctx.set_fill_rule(FillRule::Winding);
+    }
+
+    if state.mode == Mode::LShape {
+        let drawn = draw_l_shape(ctx, state, origin, settings, view);
+        ctx.restore()?;
+        return drawn;
+    }
+
+    ctx.set_line_width(2.0);
+
+    ctx.translate(from.x, from.y);
+    let angle = (to - from).try_normalize().map_or(0.0, |dir| DVec2::X.angle_between(dir));
+    ctx.rotate(angle);
+
+    let length = from.distance(to);
+    let unit = state.unit_or(settings.unit);
+    let px_per_unit = unit.px_per_unit(to - from, view.px_per_mm);
+    let format_length = |value: f64| if view.precise { unit.format_precise(value) } else { unit.format(value) };
+    // While the ruler stays where it was captured.
+    let strip = view.strip.as_ref().filter(|strip| view.zoom_strip && !state.is_dragging() && strip.endpoints == (state.from, state.to));
+    // Spread out over the magnified pixels, labelled with the distances on the screen.
+    let ticks = Scale::for_unit(unit).ticks(length, px_per_unit * strip.map_or(1.0, |strip| strip.zoom), settings.sizes.tick_spacing);
+
+    // The edge the ticks are along, 1 for −y and -1 for +y.
+    let side = if view.flip_ticks { -1.0 } else { 1.0 };
+    // The baseline of text `height` tall kept `inset` from the edge opposite
+    // the ticks, where the readouts go.
+    let from_bottom = |inset: f64, height: f64| if view.flip_ticks { -half_width + inset + height } else { half_width - inset };
+
+    // The part under the pointer, or being dragged, is tinted.
+    let fill = |handle| if !view.docked() && state.active_handle() == Some(handle) { bg.lerp(accent, 0.4) } else { bg };
+
+    ctx.rectangle(0.0, -half_width, length, half_width * 2.0);
+    set_source(ctx, fill(Handle::Body), opacity);
+    ctx.fill()?;
+
+    if let Some(strip) = strip {
+        // Scaled about `from` on the screen, which the rotation cancels out of.
+        ctx.save()?;
+        ctx.rectangle(0.0, -half_width, length, half_width * 2.0);
+        ctx.clip();
+        ctx.scale(strip.zoom, strip.zoom);
+        ctx.rotate(-angle);
+        let offset = strip.origin - state.from;
+        ctx.set_source_surface(&strip.image, offset.x, offset.y)?;
+        ctx.source().set_filter(Filter::Nearest);
+        ctx.paint()?;
+        ctx.restore()?;
+    }
+
+    ctx.rectangle(0.0, -half_width, length, half_width * 2.0);
+    set_source(ctx, accent, opacity);
+    ctx.stroke()?;
+
+    if !view.docked() {
+        set_source(ctx, fill(Handle::From), opacity);
+        ctx.arc(0.0, 0.0, control_radius, 0.0, PI * 2.0);
+        ctx.fill()?;
+
+        set_source(ctx, fill(Handle::To), opacity);
+        ctx.arc(length, 0.0, control_radius, 0.0, PI * 2.0);
+        ctx.fill()?;
+
+        set_source(ctx, accent, opacity);
+
+        ctx.arc(0.0, 0.0, control_radius, PI * 0.5, PI * 1.5);
+        ctx.stroke()?;
+
+        ctx.arc(length, 0.0, control_radius, PI * 1.5, PI * 0.5);
+        ctx.stroke()?;
+    }
+
+    set_source(ctx, accent, opacity);
+
+    ctx.select_font_face(&theme.font, FontSlant::Normal, FontWeight::Normal);
+    ctx.set_font_size(theme.font_size);
+
+    for tick in ticks.clone() {
+        let inner_width = half_width - match tick.kind {
+            TickKind::Major => 17.0,
+            TickKind::Mid => 12.0,
+            TickKind::Minor => 7.0,
+        };
+
+        ctx.line_to(tick.position, -inner_width * side);
+        ctx.line_to(tick.position, -half_width * side);
+        ctx.stroke()?;
+    }
+
+    let readouts = view.sampling && !view.docked() && length >= PROTRACTOR_LENGTH;
+
+    // From the reference axis if one is stored, within half a turn either way.
+    let relative = view.reference_angle.map_or(angle, |reference| (angle - reference + PI).rem_euclid(PI * 2.0) - PI);
+    let display_angle = if relative > 0.0 { PI * 2.0 - relative } else { relative.abs() } * 180.0 / PI;
+    let angle_string = if view.reference_angle.is_some() { format!("{:.2}° to ref", display_angle) } else { format!("{:.2}°", display_angle) };
+
+    ctx.save()?;
+    if view.docked() {
+        // A docked ruler never changes length or angle, so there is nothing to read out.
+    } else if view.luminance && length >= PROTRACTOR_LENGTH {
+        // Its labels would run into the color readouts.
+        if view.flip_ticks {
+            ctx.translate(0.0, -half_width);
+        }
+        draw_profile(ctx, &view.profile.borrow(), length, half_width, !readouts, |distance| format_length(distance / px_per_unit))?;
+    } else if length >= PROTRACTOR_LENGTH {
+        ctx.translate(30.0, from_bottom(30.0, 0.0));
+
+        ctx.line_to(0.0, 0.0);
+        ctx.line_to(30.0, 0.0);
+        ctx.stroke()?;
+
+        // The axis the angle is read from, the screen's x-axis or the reference.
+        ctx.line_to(0.0, 0.0);
+        let axis = DVec2::from_angle(relative) * 30.0;
+        ctx.line_to(axis.x, -axis.y);
+        ctx.stroke()?;
+
+        ctx.arc(0.0, 0.0, 16.0, 0.0, -relative);
+        ctx.stroke()?;
+
+        // A T-square's length is not otherwise spelled out, as its arm's is.
+        let text = if state.mode == Mode::TSquare { format!("{}  {}", format_length(length / px_per_unit), angle_string) } else { angle_string };
+        let extents = ctx.text_extents(&text)?;
+        ctx.translate(35.0, extents.height());
+        ctx.text_path(&text);
+        ctx.fill()?;
+    } else {
+        // Short rulers end before the first tick labels, so they get their length spelled out.
+        let lines = [format_length(length / px_per_unit), angle_string];
+        let mut widest = 0.0f64;
+        for line in &lines {
+            widest = widest.max(ctx.text_extents(line)?.width());
+        }
+        if widest <= length - SHORT_PADDING * 2.0 {
+            let (line_height, top) = (half_width / 2.0, if view.flip_ticks { -half_width } else { 0.0 });
+            for (i, line) in lines.iter().enumerate() {
+                let extents = ctx.text_extents(line)?;
+                ctx.move_to((length - extents.width()) / 2.0 - extents.x_bearing(), top + line_height * (i as f64 + 0.5) + extents.height() / 2.0);
+                ctx.text_path(line);
+            }
+        } else {
+            // Too short for the lines to fit along it, so the length alone runs
+            // across the bar below the longest ticks, smaller if it has to be.
+            let extents = ctx.text_extents(&lines[0])?;
+            let room = half_width * 2.0 - 17.0 - SHORT_PADDING * 2.0;
+            let scale = (room / extents.width()).min((length - SHORT_PADDING * 2.0).max(0.0) / extents.height()).min(1.0);
+            ctx.translate(length / 2.0, 17.0 / 2.0 * side);
+            ctx.rotate(-PI / 2.0);
+            ctx.scale(scale, scale);
+            ctx.move_to(-extents.width() / 2.0 - extents.x_bearing(), -extents.height() / 2.0 - extents.y_bearing());
+            ctx.text_path(&lines[0]);
+        }
+        ctx.fill()?;
+    }
+    ctx.restore()?;
+
+    let mut labels = view.labels.borrow_mut();
+    let label_baseline = if view.flip_ticks { 7.0 + ctx.text_extents("0")?.height() } else { -7.0 };
+    for (x, value) in ticks.filter_map(|tick| Some((tick.position, tick.label_value()?))) {
+        let (label, width) = labels.get(ctx, value, &theme.font, theme.font_size)?;
+        ctx.move_to(x - width / 2.0, label_baseline);
+        ctx.text_path(label);
+        let visibility = ((length - x) / 50.0).min(1.0);
+        set_source(ctx, bg.lerp(accent, visibility), opacity);
+        ctx.fill()?;
+    }
+
+    if let Some((foot, end)) = state.arm_points() {
+        draw_arm(ctx, state, foot, end, length, settings, view)?;
+    }
+
+    #[cfg(feature = "scripting")]
+    if let Some(overlay) = &view.overlay {
+        set_source(ctx, accent, opacity);
+        overlay.draw(ctx, OverlayState { length, angle: display_angle, half_width, px_per_unit })?;
+    }
+
+    ctx.set_font_size(theme.font_size * 0.75);
+    if readouts {
+        // Along the bottom edge, each next to the handle it was sampled under,
+        // with the contrast between them in the room left in the middle.
+        let size = theme.font_size * 0.75;
+        let baseline = from_bottom(8.0, size);
+        let (mut left, mut right) = (control_radius * 2.0 + 8.0, length - control_radius * 2.0 - 8.0);
+        let samples = view.samples.get();
+        for (i, sample) in samples.iter().enumerate() {
+            let Some(sample) = sample else { continue };
+            let text = format!("{} {}", sample, sample.channels());
+            let extents = ctx.text_extents(&text)?;
+            let width = size + 4.0 + extents.width();
+            let x = if i == 0 { left } else { right - width };
+
+            ctx.rectangle(x, baseline - size, size, size);
+            ctx.set_source_rgb(sample.r as f64 / 255.0, sample.g as f64 / 255.0, sample.b as f64 / 255.0);
+            ctx.fill_preserve()?;
+            set_source(ctx, accent, opacity);
+            ctx.set_line_width(1.0);
+            ctx.stroke()?;
+
+            ctx.move_to(x + size + 4.0 - extents.x_bearing(), baseline);
+            ctx.text_path(&text);
+            ctx.fill()?;
+            if i == 0 {
+                left = x + width;
+            } else {
+                right = x;
+            }
+        }
+
+        if let [Some(a), Some(b)] = samples {
+            draw_contrast(ctx, contrast_ratio(a, b), (left + right) / 2.0, baseline, right - left - 16.0, size, theme)?;
+        }
+    }
+
+    // Along the bottom edge, in the middle where the protractor and frame stats leave room.
+    let note = if view.calibrating {
+        Some(format!("lay along {:.2} mm and press calibrate again", settings.calibration.reference_mm))
+    } else {
+        let gap = view.gap.filter(|gap| gap.endpoints == (state.from, state.to)).map(|gap| format!("centers {}", format_length(gap.centers / px_per_unit)));
+        // Less than half a pixel is no change the eye could make.
+        let delta = view.committed.delta(length).filter(|delta| delta.abs() >= 0.5);
+        let delta = gap.or_else(|| delta.map(|delta| format!("{}{}", if delta > 0.0 { "+" } else { "" }, format_length(delta / px_per_unit))));
+        let ratio = view.reference.map(|reference| ratio_text(((state.to - state.from) / view.px_per_mm).length() / reference));
+        let endpoints = view.precise.then(|| format!("{:.2}, {:.2} → {:.2}, {:.2}", state.from.x, state.from.y, state.to.x, state.to.y));
+        let parts: Vec<String> = [endpoints, delta, ratio].into_iter().flatten().collect();
+        (!parts.is_empty()).then(|| parts.join("   "))
+    };
+    if let Some(text) = note.filter(|_| !readouts && !view.luminance && length >= PROTRACTOR_LENGTH) {
+        let extents = ctx.text_extents(&text)?;
+        ctx.move_to((length - extents.width()) / 2.0 - extents.x_bearing(), from_bottom(8.0, theme.font_size * 0.75));
+        ctx.text_path(&text);
+        set_source(ctx, accent, opacity);
+        ctx.fill()?;
+    }
+
+    let stats = view.stats.borrow();
+    if let Some(timings) = stats.last().filter(|_| stats.visible) {
+        // In the bottom right corner, clear of the handle and the protractor.
+        let line = timings.line();
+        let extents = ctx.text_extents(&line)?;
+        let x = if view.docked() { length - 8.0 } else { length - control_radius * 2.0 - 8.0 };
+        // A line up when the color readouts take the bottom one.
+        let size = theme.font_size * 0.75;
+        let baseline = if readouts { from_bottom(12.0 + size, size) } else { from_bottom(8.0, size) };
+        ctx.move_to(x - extents.width() - extents.x_bearing(), baseline);
+        ctx.text_path(&line);
+        set_source(ctx, accent, opacity);
+        ctx.fill()?;
+    }
+
+    ctx.restore()?;
+
+    Ok(())
+}
+/// The two bars of an L from the corner at `from`, along each axis as far as
+/// `to` is from it, with dashed lines from `to` square onto their ends.
+fn draw_l_shape(ctx: &Context, state: &RulerState, origin: DVec2, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    let theme = &settings.theme;
+    let (half_width, control_radius) = (settings.sizes.half_width, settings.sizes.control_radius);
+    let unit = state.unit_or(settings.unit);
+    let fill = |handle| if !view.docked() && state.active_handle() == Some(handle) { theme.background.lerp(theme.accent, 0.4) } else { theme.background };
+    let (corner, point) = (state.from - origin, state.to - origin);
+
+    ctx.set_line_width(2.0);
+    ctx.select_font_face(&theme.font, FontSlant::Normal, FontWeight::Normal);
+    let mut labels = view.labels.borrow_mut();
+    for ((start, end), axis) in state.bars().into_iter().zip(["x", "y"]) {
+        let (start, end) = (start - origin, end - origin);
+        let length = start.distance(end);
+        let px_per_unit = unit.px_per_unit(end - start, view.px_per_mm);
+
+        ctx.save()?;
+        ctx.translate(start.x, start.y);
+        ctx.rotate((end - start).try_normalize().map_or(0.0, |dir| DVec2::X.angle_between(dir)));
+        ctx.rectangle(0.0, -half_width, length, half_width * 2.0);
+        set_source(ctx, fill(Handle::Body), theme.opacity);
+        ctx.fill_preserve()?;
+        set_source(ctx, theme.accent, theme.opacity);
+        ctx.stroke()?;
+
+        ctx.set_font_size(theme.font_size);
+        for tick in Scale::for_unit(unit).ticks(length, px_per_unit, settings.sizes.tick_spacing) {
+            let inner_width = half_width - match tick.kind {
+                TickKind::Major => 17.0,
+                TickKind::Mid => 12.0,
+                TickKind::Minor => 7.0,
+            };
+            ctx.line_to(tick.position, -inner_width);
+            ctx.line_to(tick.position, -half_width);
+            set_source(ctx, theme.accent, theme.opacity);
+            ctx.stroke()?;
+            if let Some(value) = tick.label_value() {
+                let (label, width) = labels.get(ctx, value, &theme.font, theme.font_size)?;
+                ctx.move_to(tick.position - width / 2.0, -7.0);
+                ctx.text_path(label);
+                set_source(ctx, theme.background.lerp(theme.accent, ((length - tick.position) / 50.0).min(1.0)), theme.opacity);
+                ctx.fill()?;
+            }
+        }
+
+        // Along the bottom edge, past where the other bar crosses this one.
+        ctx.set_font_size(theme.font_size * 0.75);
+        let text = format!("{} {}", axis, unit.format(length / px_per_unit));
+        let extents = ctx.text_extents(&text)?;
+        ctx.move_to(half_width.max(control_radius) + 8.0 - extents.x_bearing(), half_width - 8.0);
+        ctx.text_path(&text);
+        set_source(ctx, theme.accent, theme.opacity);
+        ctx.fill()?;
+        ctx.restore()?;
+    }
+
+    ctx.save()?;
+    ctx.set_line_width(1.0);
+    ctx.set_dash(&[4.0, 3.0], 0.0);
+    set_source(ctx, theme.accent, theme.opacity);
+    for foot in [DVec2::new(point.x, corner.y), DVec2::new(corner.x, point.y)] {
+        ctx.move_to(point.x, point.y);
+        ctx.line_to(foot.x, foot.y);
+        ctx.stroke()?;
+    }
+    ctx.restore()?;
+
+    if !view.docked() {
+        for (center, handle) in [(corner, Handle::From), (point, Handle::To)] {
+            ctx.arc(center.x, center.y, control_radius, 0.0, PI * 2.0);
+            set_source(ctx, fill(handle), theme.opacity);
+            ctx.fill_preserve()?;
+            set_source(ctx, theme.accent, theme.opacity);
+            ctx.stroke()?;
+        }
+    }
+    Ok(())
+}
+
+/// A T-square's arm from `foot` to `end` on the screen, as a bar with ticks
+/// of its own and its length spelled out, drawn in the ruler's frame.
+fn draw_arm(ctx: &Context, state: &RulerState, foot: DVec2, end: DVec2, length: f64, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    let theme = &settings.theme;
+    let (half_width, control_radius) = (settings.sizes.half_width, settings.sizes.control_radius);
+    let unit = state.unit_or(settings.unit);
+    let arm_length = foot.distance(end);
+    let px_per_unit = unit.px_per_unit(end - foot, view.px_per_mm);
+    let fill = |handle| if !view.docked() && state.active_handle() == Some(handle) { theme.background.lerp(theme.accent, 0.4) } else { theme.background };
+
+    ctx.save()?;
+    // Turned a quarter from the ruler, towards its ticks for a positive length.
+    ctx.translate(state.arm.at * length, 0.0);
+    ctx.rotate(if state.arm.length >= 0.0 { -PI / 2.0 } else { PI / 2.0 });
+
+    // Its bar starts at the ruler's edge, leaving the ruler's ticks in view,
+    // while its own are still counted from the ruler's line.
+    let start = half_width.min(arm_length);
+    ctx.rectangle(start, -half_width, arm_length - start, half_width * 2.0);
+    set_source(ctx, fill(Handle::Body), theme.opacity);
+    ctx.fill_preserve()?;
+    set_source(ctx, theme.accent, theme.opacity);
+    ctx.stroke()?;
+
+    for tick in Scale::for_unit(unit).ticks(arm_length, px_per_unit, settings.sizes.tick_spacing).filter(|tick| tick.position >= start) {
+        let inner_width = half_width - match tick.kind {
+            TickKind::Major => 17.0,
+            TickKind::Mid => 12.0,
+            TickKind::Minor => 7.0,
+        };
+        ctx.line_to(tick.position, -inner_width);
+        ctx.line_to(tick.position, -half_width);
+        ctx.stroke()?;
+    }
+
+    if !view.docked() {
+        set_source(ctx, fill(Handle::ArmEnd), theme.opacity);
+        ctx.arc(arm_length, 0.0, control_radius, 0.0, PI * 2.0);
+        ctx.fill()?;
+        set_source(ctx, theme.accent, theme.opacity);
+        ctx.arc(arm_length, 0.0, control_radius, PI * 1.5, PI * 0.5);
+        ctx.stroke()?;
+
+        // A smaller knob on the ruler's line, clear of its readouts.
+        ctx.arc(0.0, 0.0, control_radius / 3.0, 0.0, PI * 2.0);
+        set_source(ctx, fill(Handle::ArmFoot), theme.opacity);
+        ctx.fill_preserve()?;
+        set_source(ctx, theme.accent, theme.opacity);
+        ctx.stroke()?;
+    }
+
+    // Along the bottom edge, between the handles.
+    ctx.select_font_face(&theme.font, FontSlant::Normal, FontWeight::Normal);
+    ctx.set_font_size(theme.font_size * 0.75);
+    let text = unit.format(arm_length / px_per_unit);
+    let extents = ctx.text_extents(&text)?;
+    ctx.move_to((start + arm_length - extents.width()) / 2.0 - extents.x_bearing(), half_width - 8.0);
+    ctx.text_path(&text);
+    ctx.fill()?;
+    ctx.restore()?;
+    Ok(())
+}
+
+/// A ratio as a factor, followed by the small whole numbers it is close to
+/// being between if there are any, such as "×1.50 (3:2)".
+fn ratio_text(ratio: f64) -> String {
+    let terms = (1..=RATIO_TERMS).find_map(|q| {
+        let p = (ratio * q as f64).round();
+        let close = p >= 1.0 && p <= RATIO_TERMS as f64 && (p / q as f64 / ratio - 1.0).abs() <= RATIO_TOLERANCE;
+        close.then_some((p as u32, q))
+    });
+    match terms {
+        Some((p, q)) => format!("×{:.2} ({}:{})", ratio, p, q),
+        None => format!("×{:.2}", ratio),
+    }
+}
+
+/// The luminance along the line as a plot below it, brighter higher up,
+/// with a tick at every edge it crosses and the sharpest marked. The labels
+/// give the sharpest edge's distance from `from`, and how many edges there
+/// are and how far apart.
+fn draw_profile(ctx: &Context, profile: &[Option<f64>], length: f64, half_width: f64, labels: bool, format: impl Fn(f64) -> String) -> Result<(), Box<dyn Error>> {
+    let (top, height) = (PROFILE_SLIT, (half_width - 8.0) / 2.0);
+    let step = length / (profile.len().max(2) - 1) as f64;
+    let y = |value: f64| top + height * (1.0 - value);
+
+    ctx.set_line_width(1.0);
+    // Broken where pixels could not be read.
+    for (i, value) in profile.iter().enumerate() {
+        match value {
+            Some(value) if i > 0 && profile[i - 1].is_some() => ctx.line_to(i as f64 * step, y(*value)),
+            Some(value) => ctx.move_to(i as f64 * step, y(*value)),
+            None => {}
+        }
+    }
+    ctx.stroke()?;
+
+    let crossings = crossings(profile);
+    for crossing in &crossings {
+        let x = crossing.index as f64 * step;
+        ctx.move_to(x, top + height * 0.75);
+        ctx.line_to(x, top + height);
+    }
+    ctx.stroke()?;
+
+    let Some(edge) = steepest_change(profile) else { return Ok(()) };
+    let x = edge as f64 * step;
+    ctx.move_to(x, top);
+    ctx.line_to(x, top + height);
+    ctx.stroke()?;
+    if !labels {
+        return Ok(());
+    }
+
+    let label = format(x);
+    let extents = ctx.text_extents(&label)?;
+    let baseline = top + height + 4.0 + extents.height();
+    let label_x = (x - extents.width() / 2.0).clamp(0.0, (length - extents.width()).max(0.0));
+    let label_end = label_x + extents.width();
+    ctx.move_to(label_x - extents.x_bearing(), baseline);
+    ctx.text_path(&label);
+    ctx.fill()?;
+
+    // At the other end from the sharpest edge's label, out of its way.
+    let count = if crossings.len() == 1 { "1 edge".to_string() } else { format!("{} edges", crossings.len()) };
+    let summary = match spacing(&crossings) {
+        Some((min, max)) if min == max => format!("{}, every {}", count, format(min as f64 * step)),
+        Some((min, max)) => format!("{}, {}–{} apart", count, format(min as f64 * step), format(max as f64 * step)),
+        None => count,
+    };
+    let extents = ctx.text_extents(&summary)?;
+    let room = if x < length / 2.0 { length - label_end } else { label_x };
+    if extents.width() + 16.0 <= room {
+        let summary_x = if x < length / 2.0 { length - 8.0 - extents.width() } else { 8.0 };
+        ctx.move_to(summary_x - extents.x_bearing(), baseline);
+        ctx.text_path(&summary);
+        ctx.fill()?;
+    }
+    Ok(())
+}
+
+/// The contrast ratio centered on `x` with a badge for each WCAG level,
+/// filled when it passes. Left out if it does not fit in `room`.
+fn draw_contrast(ctx: &Context, ratio: f64, x: f64, baseline: f64, room: f64, size: f64, theme: &Theme) -> Result<(), Box<dyn Error>> {
+    let text = format!("{:.2}:1", ratio);
+    let badges = [("AA", ratio >= AA), ("AAA", ratio >= AAA)];
+    let extents = ctx.text_extents(&text)?;
+    let badge_widths = badges.iter().map(|(name, _)| ctx.text_extents(name).map(|extents| extents.width() + 6.0)).collect::<Result<Vec<_>, _>>()?;
+    let width = extents.width() + badge_widths.iter().map(|width| width + 4.0).sum::<f64>();
+    if width > room {
+        return Ok(());
+    }
+
+    set_source(ctx, theme.accent, theme.opacity);
+    let mut x = x - width / 2.0;
+    ctx.move_to(x - extents.x_bearing(), baseline);
+    ctx.text_path(&text);
+    ctx.fill()?;
+    x += extents.width() + 4.0;
+
+    ctx.set_line_width(1.0);
+    for ((name, passes), badge_width) in badges.iter().zip(badge_widths) {
+        ctx.rectangle(x, baseline - size - 1.0, badge_width, size + 3.0);
+        if *passes {
+            ctx.fill()?;
+            // Lettered in the background color on a badge that passes.
+            set_source(ctx, theme.background, theme.opacity);
+        } else {
+            ctx.stroke()?;
+        }
+        ctx.move_to(x + 3.0 - ctx.text_extents(name)?.x_bearing(), baseline);
+        ctx.text_path(name);
+        ctx.fill()?;
+        set_source(ctx, theme.accent, theme.opacity);
+        x += badge_width + 4.0;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+
+    use cairo::{Context, Format, ImageSurface};
+    use glam::DVec2;
+
+    use ruler::state::{Handle, Interaction, Mode, RulerState};
+    use ruler::unit::Unit;
+
+    use super::{draw, ratio_text, Committed, Strip, View};
+    use crate::config::Settings;
+
+    // Font rendering differs a little between machines, so a few pixels may
+    // be off by more than a rounding error before a reference counts as broken.
+    const CHANNEL_TOLERANCE: u8 = 24;
+    const MAX_DIFFERING: f64 = 0.005;
+
+    fn settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.theme.font = "DejaVu Sans".to_string();
+        settings
+    }
+
+    fn view() -> View {
+        View {
+            dock: None,
+            px_per_mm: DVec2::splat(96.0 / 25.4),
+            #[cfg(feature = "scripting")]
+            overlay: None,
+            labels: Default::default(),
+            stats: Default::default(),
+            loupe: false,
+            edge_snap: None,
+            sampling: false,
+            samples: Default::default(),
+            luminance: false,
+            profile: Default::default(),
+            gap: None,
+            calibrating: false,
+            zoom_strip: false,
+            strip: None,
+            committed: Default::default(),
+            reference: None,
+            reference_angle: None,
+            flip_ticks: false,
+            precise: false,
+        }
+    }
+
+    /// A ruler of `length` pixels at `angle` degrees counterclockwise, in a surface just large enough to hold it.
+    fn ruler(length: f64, angle: f64) -> (RulerState, ImageSurface) {
+        let half_width = settings().sizes.half_width;
+        let direction = DVec2::from_angle(-angle.to_radians()) * length;
+        let min = direction.min(DVec2::ZERO) - half_width;
+        let size = direction.abs() + half_width * 2.0;
+        let state = RulerState::new(-min, direction - min, Mode::Line, size);
+        let surface = ImageSurface::create(Format::ARgb32, size.x.ceil() as i32, size.y.ceil() as i32).unwrap();
+        (state, surface)
+    }
+
+    /// Renders `state` and compares it with `tests/golden/{name}.png`, or
+    /// replaces the reference with `UPDATE_GOLDEN=1`. A failing render is left
+    /// in `target/golden` for a look.
+    fn check(name: &str, state: &RulerState, surface: ImageSurface, settings: &Settings) {
+        check_view(name, state, surface, settings, &view());
+    }
+
+    fn check_view(name: &str, state: &RulerState, mut surface: ImageSurface, settings: &Settings, view: &View) {
+        {
+            let ctx = Context::new(&surface).unwrap();
+            draw(&ctx, state, DVec2::ZERO, settings, view).unwrap();
+        }
+        surface.flush();
+
+        let reference_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name));
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+            surface.write_to_png(&mut File::create(&reference_path).unwrap()).unwrap();
+            return;
+        }
+
+        let mut reference = ImageSurface::create_from_png(&mut File::open(&reference_path).unwrap()).unwrap();
+        assert_eq!((reference.width(), reference.height()), (surface.width(), surface.height()), "{} changed size", name);
+        let total = (surface.width() * surface.height()) as f64;
+        let differing = {
+            let (actual, expected) = (surface.data().unwrap(), reference.data().unwrap());
+            actual.chunks(4).zip(expected.chunks(4)).filter(|(a, e)| a.iter().zip(e.iter()).any(|(a, e)| a.abs_diff(*e) > CHANNEL_TOLERANCE)).count()
+        };
+        if differing as f64 > total * MAX_DIFFERING {
+            let failed = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden");
+            fs::create_dir_all(&failed).unwrap();
+            surface.write_to_png(&mut File::create(failed.join(format!("{}.png", name))).unwrap()).unwrap();
+            panic!("{} differs from its reference in {} of {} pixels", name, differing, total);
+        }
+    }
+
+    #[test]
+    fn horizontal_in_pixels() {
+        let (state, surface) = ruler(400.0, 0.0);
+        check("horizontal_px", &state, surface, &settings());
+    }
+
+    #[test]
+    fn angled_in_millimeters() {
+        let (mut state, surface) = ruler(300.0, 30.0);
+        state.unit = Some(Unit::Mm);
+        check("angled_mm", &state, surface, &settings());
+    }
+
+    #[test]
+    fn short_readout() {
+        let (state, surface) = ruler(100.0, 0.0);
+        check("short", &state, surface, &settings());
+    }
+
+    #[test]
+    fn shortest_readout() {
+        let settings = settings();
+        let (state, surface) = ruler(settings.sizes.min_length, 0.0);
+        check("shortest", &state, surface, &settings);
+    }
+
+    #[test]
+    fn steep_in_inches() {
+        let (mut state, surface) = ruler(250.0, 120.0);
+        state.unit = Some(Unit::In);
+        check("steep_in", &state, surface, &settings());
+    }
+
+    #[test]
+    fn flipped_ticks() {
+        let (state, surface) = ruler(300.0, 0.0);
+        check_view("flipped", &state, surface, &settings(), &View { flip_ticks: true, ..view() });
+    }
+
+    #[test]
+    fn angle_from_a_reference() {
+        let (state, surface) = ruler(300.0, 30.0);
+        // Stored along a ruler at 20 degrees counterclockwise.
+        let reference = DVec2::X.angle_between(DVec2::from_angle(-20f64.to_radians()));
+        check_view("reference_angle", &state, surface, &settings(), &View { reference_angle: Some(reference), ..view() });
+    }
+
+    #[test]
+    fn t_square_arm() {
+        let half_width = settings().sizes.half_width;
+        let surface = ImageSurface::create(Format::ARgb32, (300.0 + half_width * 2.0) as i32, (120.0 + half_width * 2.0) as i32).unwrap();
+        let from = DVec2::new(half_width, 120.0 + half_width);
+        let mut state = RulerState::new(from, from + DVec2::new(300.0, 0.0), Mode::TSquare, DVec2::new(1920.0, 1080.0));
+        state.arm.at = 0.25;
+        state.arm.length = 120.0;
+        check("t_square", &state, surface, &settings());
+    }
+
+    #[test]
+    fn l_shape_offsets() {
+        let half_width = settings().sizes.half_width;
+        let surface = ImageSurface::create(Format::ARgb32, (260.0 + half_width * 2.0) as i32, (160.0 + half_width * 2.0) as i32).unwrap();
+        let from = DVec2::splat(half_width);
+        let state = RulerState::new(from, from + DVec2::new(260.0, 160.0), Mode::LShape, DVec2::new(1920.0, 1080.0));
+        check("l_shape", &state, surface, &settings());
+    }
+
+    #[test]
+    fn hovered_handle() {
+        let (mut state, surface) = ruler(200.0, 0.0);
+        state.interaction = Interaction::Hovering(Handle::To);
+        check("hovered", &state, surface, &settings());
+    }
+
+    #[test]
+    fn strip_area_is_the_start_of_the_line() {
+        let area = Strip::area(DVec2::new(100.0, 50.0), DVec2::new(500.0, 50.0), 40.0, 4.0);
+        assert_eq!((area.min, area.max), (DVec2::new(100.0, 40.0), DVec2::new(200.0, 60.0)));
+        // Upright, up the screen.
+        let area = Strip::area(DVec2::new(100.0, 100.0), DVec2::new(100.0, 20.0), 8.0, 4.0);
+        assert_eq!((area.min, area.max), (DVec2::new(98.0, 80.0), DVec2::new(102.0, 100.0)));
+    }
+
+    #[test]
+    fn delta_from_the_previous_measurement() {
+        let mut committed = Committed::default();
+        assert_eq!(committed.delta(100.0), None);
+        committed.commit(100.0);
+        assert_eq!(committed.delta(112.0), Some(12.0));
+        committed.commit(112.0);
+        // Left where it was, it still reads the change from the one before.
+        assert_eq!(committed.delta(112.0), Some(12.0));
+        assert_eq!(committed.delta(90.0), Some(-22.0));
+        committed.commit(112.0);
+        assert_eq!(committed.delta(112.0), Some(12.0));
+    }
+
+    #[test]
+    fn ratio_to_the_reference() {
+        assert_eq!(ratio_text(1.5), "×1.50 (3:2)");
+        assert_eq!(ratio_text(2.0 / 3.0), "×0.67 (2:3)");
+        assert_eq!(ratio_text(1.0), "×1.00 (1:1)");
+        assert_eq!(ratio_text(1.618), "×1.62");
+        assert_eq!(ratio_text(25.0), "×25.00");
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::Write;
+
+use toml::{Table, Value};
+
+use crate::config::Settings;
+
+/// Comments for the generated config, keyed by setting path. The values
+/// themselves always come from `Settings::default()`.
+const COMMENTS: &[(&str, &str)] = &[
+    ("format", "How measurements are printed, overridden by --format"),
+    ("history", "Append finished measurements to the history file"),
+    ("notify", "Show a desktop notification for every finished drag, or --notify"),
+    ("sync", "Share the unit picked at runtime and the profile with other rulers that sync"),
+    ("auto_hide_minutes", "Hide the ruler after this many minutes without use, where it was, until `ruler toggle` shows it again; 0 to never hide"),
+    ("unit", "\"px\", \"mm\", \"cm\" or \"in\" for the scale and readout, overridden by --unit"),
+    ("startup", "Initial placement"),
+    ("startup.mode", "Measurement mode, overridden by --mode"),
+    ("startup.orientation", "\"horizontal\", \"vertical\" or an angle in degrees, overridden by --orientation"),
+    ("startup.anchor", "\"center\" or \"cursor\", overridden by --anchor"),
+    ("startup.single_instance", "Forward the command line to a running ruler, or --single-instance"),
+    ("startup.tray", "Put an icon with a menu in the system tray, or --tray"),
+    ("theme", "Colors are #rrggbb or rgb:r/g/b"),
+    ("theme.opacity", "0 is fully transparent, 1 opaque"),
+    ("sizes", "Sizes in pixels"),
+    ("sizes.min_length", "0 to disable"),
+    ("keymap", "Keys such as \"q\", \"Escape\" or \"ctrl+shift+q\" for each action"),
+    ("drag", "Modifiers that constrain a drag while held, \"\" to only use the sticky toggles"),
+    ("drag.symmetric", "Move the other endpoint the mirrored way, keeping the middle in place, or the toggle_symmetric key"),
+    ("drag.clamp_to_screen", "Allow endpoints off-screen with false or the toggle_clamp key"),
+    ("drag.ignore_limits", "Let the endpoints off the screen and closer than sizes.min_length while held"),
+    ("drag.confine_to", "\"desktop\", \"monitor\" for the one the fixed endpoint is on, or [x, y, width, height]"),
+    ("drag.snap_angle", "Degrees the angle snaps to multiples of, such as 15, 0 to not snap"),
+    ("drag.body", "Drag the bar between the endpoints to move the whole ruler"),
+    ("drag.snap_to_pixels", "\"centers\" or \"boundaries\" to land dragged endpoints on whole pixels, \"off\" to leave them"),
+    ("drag.snap_to_edges", "Snap the dragged endpoint to the strongest edge on the screen nearby, over fix_distance and fix_angle"),
+    ("drag.snap_to_windows", "Snap the dragged endpoint to the nearest border or corner of a window, frame included"),
+    ("drag.snap_to_center", "Snap to the monitor's center lines and diagonals as well while snapping to windows"),
+    ("drag.edge_reach", "Pixels from the pointer to look for an edge or border, up to 32"),
+    ("performance.max_fps", "0 for uncapped, overridden by --max-fps"),
+    ("performance.frame_stats", "Log average frame timings on exit, see the toggle_frame_stats key for a live view"),
+    ("loupe", "A magnified view of the pixels around the dragged endpoint"),
+    ("loupe.enabled", "Flipped at runtime by the toggle_loupe key"),
+    ("loupe.zoom", "4 to 8, for the zoomed strip as well"),
+    ("grid", "A layout grid across the screen, shown with the toggle_grid key"),
+    ("grid.cell", "Pixels between lines, at least 2"),
+    ("grid.major_every", "Every how many lines one is drawn stronger, 0 for none"),
+    ("grid.origin", "\"from\" or \"to\", the endpoint lines run through"),
+    ("baseline", "Baselines across the screen from the start of the ruler, shown with the toggle_baseline key"),
+    ("baseline.line_height", "Pixels between baselines, at least 2, changed while shown with the baseline_taller and baseline_shorter keys"),
+    ("calibration", "The calibrate key works out the screen's pixels per millimeter from the ruler laid along an object of known length"),
+    ("calibration.reference_mm", "The long edge of a bank card by default"),
+];
+
+/// Settings without a default value, shown commented out.
+const EXAMPLES: &[(&str, &str)] = &[
+    ("overlay_script", "\"/home/me/.config/ruler/overlay.rhai\" # needs the scripting feature"),
+    ("px_per_mm", "3.78 # over what the calibrate key saved and the X server's figure"),
+    ("screenshot_dir", "\"/home/me/Pictures/ruler\" # for screenshots and SVG exports, defaults to $XDG_PICTURES_DIR or ~/Pictures"),
+    ("startup.dock", "\"bottom\" # \"top\", \"left\" or \"right\", or --dock"),
+];
+
+const FOOTER: &str = "
+# Profiles are layered over the settings above and only need to list what
+# they change. Pick one with --profile or a top-level `profile = \"name\"`.
+# [profiles.presentation.theme]
+# font_size = 20.0
+
+# The physical size of a monitor, by the output name xrandr lists, for one
+# whose EDID gets it wrong. Below px_per_mm and above the calibration.
+# [outputs.DP-1]
+# size_mm = [597.0, 336.0]
+";
+
+fn comment(out: &mut String, path: &str) {
+    if let Some((_, text)) = COMMENTS.iter().find(|&&(p, _)| p == path) {
+        let _ = writeln!(out, "# {}", text);
+    }
+}
+
+fn emit(out: &mut String, table: &Table, prefix: &str) {
+    let path = |key: &str| if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+
+    for (key, value) in table.iter().filter(|(_, value)| !value.is_table()) {
+        comment(out, &path(key));
+        let _ = writeln!(out, "{} = {}", key, value);
+    }
+    for (example_path, example) in EXAMPLES {
+        let key = match prefix {
+            "" => (!example_path.contains('.')).then_some(*example_path),
+            _ => example_path.strip_prefix(prefix).and_then(|rest| rest.strip_prefix('.')),
+        };
+        if let Some(key) = key {
+            let _ = writeln!(out, "# {} = {}", key, example);
+        }
+    }
+    for (key, value) in table {
+        if let Value::Table(inner) = value {
+            out.push('\n');
+            comment(out, &path(key));
+            let _ = writeln!(out, "[{}]", path(key));
+            emit(out, inner, &path(key));
+        }
+    }
+}
+
+/// A complete, commented config file holding every default.
+pub fn default_config() -> String {
+    let defaults = Table::try_from(Settings::default()).unwrap_or_default();
+    let mut out = String::new();
+    emit(&mut out, &defaults, "");
+    out.push_str(FOOTER);
+    out
+}
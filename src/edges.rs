@@ -0,0 +1,200 @@
+//! Finding edges in what is on the screen, for endpoints to snap to.
+
+use glam::DVec2;
+
+use crate::color::EDGE_THRESHOLD;
+
+/// The luminance of a square of pixels around a point, row by row, `None`
+/// for pixels that could not be read.
+pub struct Patch {
+    /// The top left pixel.
+    origin: DVec2,
+    side: usize,
+    luminance: Vec<Option<f64>>,
+}
+
+impl Patch {
+    /// The pixels reaching `reach` either side of the one under `point`, row
+    /// by row, as `new` takes their luminance.
+    pub fn pixels(point: DVec2, reach: usize) -> Vec<DVec2> {
+        let (origin, side) = (point.floor() - reach as f64, 2 * reach + 1);
+        (0..side * side).map(|i| origin + DVec2::new((i % side) as f64, (i / side) as f64)).collect()
+    }
+
+    /// The patch around `point` from the luminance of what `pixels` gave.
+    pub fn new(point: DVec2, reach: usize, luminance: Vec<Option<f64>>) -> Patch {
+        let side = 2 * reach + 1;
+        assert_eq!(luminance.len(), side * side, "a luminance for every pixel of the patch");
+        Patch { origin: point.floor() - reach as f64, side, luminance }
+    }
+
+    fn at(&self, x: usize, y: usize) -> Option<f64> {
+        self.luminance[y * self.side + x]
+    }
+
+    /// Where `point` lands on the strongest edge within `reach` of it. The
+    /// edges lie on the boundaries between pixels, and a corner, where a
+    /// boundary across and one along meet, moves both coordinates. `None` if
+    /// nothing changes past `EDGE_THRESHOLD` that close.
+    pub fn snap(&self, point: DVec2, reach: f64) -> Option<DVec2> {
+        let mut best: Option<(f64, f64, DVec2)> = None;
+        for y in 1..self.side {
+            for x in 1..self.side {
+                let Some(here) = self.at(x, y) else { continue };
+                // A change from the pixel to the left is an edge at the boundary
+                // between the two, and from the one above likewise.
+                let across = self.at(x - 1, y).map_or(0.0, |left| (here - left).abs());
+                let along = self.at(x, y - 1).map_or(0.0, |above| (here - above).abs());
+                let boundary = self.origin + DVec2::new(x as f64, y as f64);
+                let snapped = DVec2::new(
+                    if across > EDGE_THRESHOLD { boundary.x } else { point.x },
+                    if along > EDGE_THRESHOLD { boundary.y } else { point.y },
+                );
+                let strength = [across, along].into_iter().filter(|change| *change > EDGE_THRESHOLD).sum::<f64>();
+                let distance = snapped.distance(point);
+                if strength == 0.0 || distance > reach {
+                    continue;
+                }
+                // The strongest, and the closest of those as strong.
+                match best {
+                    Some((best_strength, best_distance, _)) if best_strength > strength || (best_strength == strength && best_distance <= distance) => {}
+                    _ => best = Some((strength, distance, snapped)),
+                }
+            }
+        }
+        best.map(|(_, _, snapped)| snapped)
+    }
+}
+
+/// An edge the line crosses, from a profile of the luminance along it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Crossing {
+    /// The first pixel past where the luminance changes most.
+    pub index: usize,
+    /// Whether it gets brighter past the edge.
+    pub rising: bool,
+}
+
+/// The edges the line crosses in a profile of the luminance along it.
+/// Changes past `EDGE_THRESHOLD` the same way next to each other are one
+/// edge spread over a few pixels, as antialiasing or a soft shadow leaves it.
+pub fn crossings(profile: &[Option<f64>]) -> Vec<Crossing> {
+    let mut crossings = vec![];
+    // The strongest change of the edge under way, if any.
+    let mut edge: Option<(Crossing, f64)> = None;
+    for i in 1..profile.len() {
+        let change = profile[i].zip(profile[i - 1]).map(|(here, before)| here - before).filter(|change| change.abs() > EDGE_THRESHOLD);
+        let Some(change) = change else {
+            crossings.extend(edge.take().map(|(crossing, _)| crossing));
+            continue;
+        };
+        let crossing = Crossing { index: i, rising: change > 0.0 };
+        match edge {
+            Some((under_way, strongest)) if under_way.rising == crossing.rising => {
+                if change.abs() > strongest {
+                    edge = Some((crossing, change.abs()));
+                }
+            }
+            _ => {
+                crossings.extend(edge.take().map(|(crossing, _)| crossing));
+                edge = Some((crossing, change.abs()));
+            }
+        }
+    }
+    crossings.extend(edge.map(|(crossing, _)| crossing));
+    crossings
+}
+
+/// The smallest and largest distance in pixels between crossings that go
+/// the same way, such as the top edges of the rows of a list, of whichever
+/// way more of them go. `None` with fewer than two that way.
+pub fn spacing(crossings: &[Crossing]) -> Option<(usize, usize)> {
+    let rising = crossings.iter().filter(|crossing| crossing.rising).count();
+    let way = rising * 2 >= crossings.len();
+    let indices: Vec<usize> = crossings.iter().filter(|crossing| crossing.rising == way).map(|crossing| crossing.index).collect();
+    let gaps = indices.windows(2).map(|pair| pair[1] - pair[0]);
+    gaps.fold(None, |range, gap| Some(range.map_or((gap, gap), |(min, max): (usize, usize)| (min.min(gap), max.max(gap)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A patch around `point` lit by `lit` for each pixel's screen position.
+    fn patch(point: DVec2, reach: usize, lit: impl Fn(DVec2) -> bool) -> Patch {
+        let luminance = Patch::pixels(point, reach).into_iter().map(|pixel| Some(if lit(pixel) { 1.0 } else { 0.0 })).collect();
+        Patch::new(point, reach, luminance)
+    }
+
+    fn indices(crossings: &[Crossing]) -> Vec<usize> {
+        crossings.iter().map(|crossing| crossing.index).collect()
+    }
+
+    #[test]
+    fn crossings_of_evenly_spaced_rows() {
+        // A line one pixel high every 10, as between the rows of a list.
+        let profile: Vec<_> = (0..50).map(|i| Some(if i % 10 == 9 { 0.2 } else { 1.0 })).collect();
+        let crossings = crossings(&profile);
+        assert_eq!(indices(&crossings), vec![9, 10, 19, 20, 29, 30, 39, 40, 49]);
+        assert!(!crossings[0].rising && crossings[1].rising);
+        // Five darkening against four brightening, 10 apart either way.
+        assert_eq!(spacing(&crossings), Some((10, 10)));
+    }
+
+    #[test]
+    fn soft_edge_is_one_crossing() {
+        let profile = [Some(0.0), Some(0.0), Some(0.2), Some(0.7), Some(0.9), Some(0.9)];
+        assert_eq!(crossings(&profile), vec![Crossing { index: 3, rising: true }]);
+        assert_eq!(spacing(&crossings(&profile)), None);
+    }
+
+    #[test]
+    fn crossings_stop_at_unread_pixels() {
+        assert_eq!(indices(&crossings(&[Some(0.0), Some(1.0), None, Some(0.0), Some(1.0)])), vec![1, 4]);
+        assert_eq!(crossings(&[Some(0.5), Some(0.5)]), vec![]);
+    }
+
+    #[test]
+    fn uneven_spacing() {
+        let crossing = |index| Crossing { index, rising: true };
+        assert_eq!(spacing(&[crossing(2), crossing(12), crossing(25)]), Some((10, 13)));
+    }
+
+    #[test]
+    fn pixels_are_row_by_row() {
+        let pixels = Patch::pixels(DVec2::new(10.5, 20.5), 1);
+        assert_eq!(pixels.len(), 9);
+        assert_eq!(pixels[0], DVec2::new(9.0, 19.0));
+        assert_eq!(pixels[1], DVec2::new(10.0, 19.0));
+        assert_eq!(pixels[3], DVec2::new(9.0, 20.0));
+    }
+
+    #[test]
+    fn snaps_across_to_a_vertical_edge() {
+        let point = DVec2::new(100.5, 50.5);
+        let patch = patch(point, 8, |pixel| pixel.x >= 103.0);
+        assert_eq!(patch.snap(point, 8.0), Some(DVec2::new(103.0, 50.5)));
+    }
+
+    #[test]
+    fn snaps_both_ways_to_a_corner() {
+        let point = DVec2::new(100.5, 50.5);
+        let patch = patch(point, 8, |pixel| pixel.x >= 98.0 && pixel.y >= 52.0);
+        assert_eq!(patch.snap(point, 8.0), Some(DVec2::new(98.0, 52.0)));
+    }
+
+    #[test]
+    fn prefers_the_stronger_edge() {
+        let point = DVec2::new(100.5, 50.5);
+        let luminance = Patch::pixels(point, 8).into_iter().map(|pixel| Some(if pixel.x >= 104.0 { 1.0 } else if pixel.x >= 101.0 { 0.3 } else { 0.2 })).collect();
+        assert_eq!(Patch::new(point, 8, luminance).snap(point, 8.0), Some(DVec2::new(104.0, 50.5)));
+    }
+
+    #[test]
+    fn nothing_to_snap_to() {
+        let point = DVec2::new(100.5, 50.5);
+        assert_eq!(patch(point, 8, |_| false).snap(point, 8.0), None);
+        // An edge in the patch, but further than the reach.
+        assert_eq!(patch(point, 8, |pixel| pixel.x >= 107.0).snap(point, 4.0), None);
+    }
+}
@@ -0,0 +1,74 @@
+use glam::DVec2;
+
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const BLOCK: usize = 128;
+// The first detailed timing descriptor, the monitor's preferred mode.
+const PREFERRED_TIMING: usize = 54;
+
+/// The physical size of the picture in millimeters from a monitor's EDID.
+/// The preferred mode's detailed timing gives it to the millimeter, the base
+/// block only to the centimeter, so that is the fallback. `None` if the EDID
+/// is malformed or gives no size, as projectors do.
+pub fn image_size_mm(edid: &[u8]) -> Option<DVec2> {
+    let base = edid.get(..BLOCK).filter(|base| base[..HEADER.len()] == HEADER)?;
+    if base.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+        return None;
+    }
+    let timing = &base[PREFERRED_TIMING..PREFERRED_TIMING + 18];
+    // A pixel clock of zero marks a display descriptor rather than a timing.
+    let is_timing = timing[0] != 0 || timing[1] != 0;
+    let width = (timing[12] as u16) | ((timing[14] as u16 & 0xf0) << 4);
+    let height = (timing[13] as u16) | ((timing[14] as u16 & 0x0f) << 8);
+    if is_timing && width > 0 && height > 0 {
+        return Some(DVec2::new(width as f64, height as f64));
+    }
+    // Zero in either means the size is unknown or variable.
+    let (width_cm, height_cm) = (base[21], base[22]);
+    (width_cm > 0 && height_cm > 0).then(|| DVec2::new(width_cm as f64, height_cm as f64) * 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A base block for a 51 by 29 cm monitor with a preferred mode 598 by
+    /// 336 mm, or no size in the timing if `timing_mm` is `None`.
+    fn edid(timing_mm: Option<(u16, u16)>) -> Vec<u8> {
+        let mut edid = vec![0; BLOCK];
+        edid[..8].copy_from_slice(&HEADER);
+        edid[21] = 51;
+        edid[22] = 29;
+        // 148.5 MHz, in 10 kHz units.
+        edid[54..56].copy_from_slice(&14850u16.to_le_bytes());
+        if let Some((width, height)) = timing_mm {
+            edid[66] = width as u8;
+            edid[67] = height as u8;
+            edid[68] = ((width >> 8) << 4 | (height >> 8)) as u8;
+        }
+        let sum = edid.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        edid[127] = 0u8.wrapping_sub(sum);
+        edid
+    }
+
+    #[test]
+    fn size_from_the_preferred_timing() {
+        assert_eq!(image_size_mm(&edid(Some((598, 336)))), Some(DVec2::new(598.0, 336.0)));
+    }
+
+    #[test]
+    fn falls_back_to_centimeters() {
+        assert_eq!(image_size_mm(&edid(None)), Some(DVec2::new(510.0, 290.0)));
+    }
+
+    #[test]
+    fn rejects_malformed_edid() {
+        let mut corrupt = edid(Some((598, 336)));
+        corrupt[66] ^= 1;
+        assert_eq!(image_size_mm(&corrupt), None);
+        assert_eq!(image_size_mm(&edid(Some((598, 336)))[..100]), None);
+        let mut headless = edid(None);
+        headless[0] = 1;
+        headless[127] = headless[127].wrapping_sub(1);
+        assert_eq!(image_size_mm(&headless), None);
+    }
+}
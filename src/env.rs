@@ -0,0 +1,82 @@
+use std::env;
+
+use toml::{Table, Value};
+use tracing::warn;
+
+const PREFIX: &str = "RULER_";
+pub const PROFILE_VAR: &str = "RULER_PROFILE";
+
+fn parse_like(template: &Value, raw: &str) -> Option<Value> {
+    match template {
+        Value::String(_) => Some(Value::String(raw.to_string())),
+        Value::Integer(_) => raw.parse().ok().map(Value::Integer),
+        Value::Float(_) => raw.parse().ok().map(Value::Float),
+        Value::Boolean(_) => raw.parse().ok().map(Value::Boolean),
+        Value::Array(_) => Some(Value::Array(raw.split(',').map(|item| Value::String(item.trim().to_string())).collect())),
+        _ => None,
+    }
+}
+
+/// Finds the setting a variable name such as `theme_font_size` refers to,
+/// returning the path of keys and the default value.
+fn lookup<'a>(table: &'a Table, name: &str) -> Option<(Vec<&'a str>, &'a Value)> {
+    table.iter().find_map(|(key, value)| {
+        if key == name && !value.is_table() {
+            Some((vec![key.as_str()], value))
+        } else if let (Some(rest), Value::Table(inner)) = (name.strip_prefix(key.as_str()).and_then(|rest| rest.strip_prefix('_')), value) {
+            let (mut path, value) = lookup(inner, rest)?;
+            path.insert(0, key);
+            Some((path, value))
+        } else {
+            None
+        }
+    })
+}
+
+fn insert(table: &mut Table, path: &[&str], value: Value) {
+    match path {
+        [key] => {
+            table.insert(key.to_string(), value);
+        }
+        [key, rest @ ..] => {
+            let inner = table.entry(key.to_string()).or_insert_with(|| Value::Table(Table::new()));
+            if let Value::Table(inner) = inner {
+                insert(inner, rest, value);
+            }
+        }
+        [] => {}
+    }
+}
+
+/// Builds a config overlay from `RULER_*` variables, using `defaults` to find
+/// out which setting each one names and what type it has. Variables that
+/// `check` rejects are skipped with a warning.
+pub fn overlay(defaults: &Table, check: impl Fn(&Table) -> Result<(), String>) -> Table {
+    let mut overlay = Table::new();
+    for (var, raw) in env::vars() {
+        let Some(name) = var.strip_prefix(PREFIX) else { continue };
+        if var == PROFILE_VAR {
+            continue;
+        }
+        let name = name.to_lowercase();
+        match lookup(defaults, &name) {
+            Some((path, template)) => match parse_like(template, &raw) {
+                Some(value) => {
+                    let mut single = Table::new();
+                    insert(&mut single, &path, value.clone());
+                    match check(&single) {
+                        Ok(()) => insert(&mut overlay, &path, value),
+                        Err(message) => warn!("Ignoring {}: {}", var, message),
+                    }
+                }
+                None => warn!("Ignoring {}: invalid value '{}'", var, raw),
+            },
+            None => warn!("Ignoring {}: no such setting", var),
+        }
+    }
+    overlay
+}
+
+pub fn profile() -> Option<String> {
+    env::var(PROFILE_VAR).ok()
+}
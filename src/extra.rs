@@ -0,0 +1,77 @@
+use glam::DVec2;
+use xcb::x::Window;
+
+use ruler::geom::Rect;
+use ruler::state::{Mode, RulerState};
+
+use crate::config::Color;
+
+/// Accents the added rulers take in turn, set apart from each other and
+/// from the default gray of the first.
+const ACCENTS: [Color; 6] = [
+    Color { r: 0.90, g: 0.35, b: 0.20 },
+    Color { r: 0.20, g: 0.55, b: 0.90 },
+    Color { r: 0.25, g: 0.70, b: 0.35 },
+    Color { r: 0.75, g: 0.35, b: 0.80 },
+    Color { r: 0.90, g: 0.70, b: 0.15 },
+    Color { r: 0.15, g: 0.70, b: 0.70 },
+];
+/// Gap left between a ruler and one added beside it.
+const GAP: f64 = 8.0;
+
+/// A ruler added beside the first with `new_ruler`, with a window, endpoints
+/// and accent of its own. It is dragged like the first, while the keys keep
+/// acting on the first, but for `quit` closing it.
+pub struct Extra {
+    pub window: Window,
+    pub state: RulerState,
+    pub accent: Color,
+    /// The density of the monitor it was last dropped on.
+    pub px_per_mm: DVec2,
+}
+
+/// The rulers added beside the first, in the order they were added.
+#[derive(Default)]
+pub struct Extras {
+    pub rulers: Vec<Extra>,
+    /// How many were ever added, for the next one's accent.
+    added: usize,
+}
+
+impl Extras {
+    /// Adds a ruler in `window` beside the last one added, or beside `first`
+    /// if there are none, with the next accent.
+    pub fn add(&mut self, window: Window, first: &RulerState, half_width: f64, px_per_mm: DVec2) -> &mut Extra {
+        let like = self.rulers.last().map_or(first, |extra| &extra.state);
+        let (from, to) = beside(like, half_width);
+        let mut state = RulerState::new(from, to, Mode::Line, like.screen_size);
+        state.unit = like.unit;
+        let accent = ACCENTS[self.added % ACCENTS.len()];
+        self.added += 1;
+        self.rulers.push(Extra { window, state, accent, px_per_mm });
+        self.rulers.last_mut().expect("just pushed")
+    }
+
+    pub fn get_mut(&mut self, window: Window) -> Option<&mut Extra> {
+        self.rulers.iter_mut().find(|extra| extra.window == window)
+    }
+
+    pub fn contains(&self, window: Window) -> bool {
+        self.rulers.iter().any(|extra| extra.window == window)
+    }
+
+    pub fn remove(&mut self, window: Window) -> Option<Extra> {
+        let index = self.rulers.iter().position(|extra| extra.window == window)?;
+        Some(self.rulers.remove(index))
+    }
+}
+
+/// Where a ruler beside `like` goes: parallel to it, clear of its bar, on
+/// the side that keeps both endpoints on the screen if only one does.
+fn beside(like: &RulerState, half_width: f64) -> (DVec2, DVec2) {
+    let across = (like.to - like.from).try_normalize().unwrap_or(DVec2::X).perp() * (half_width * 2.0 + GAP);
+    let screen = Rect::from_size(like.screen_size);
+    let fits = |offset: DVec2| screen.contains(like.from + offset) && screen.contains(like.to + offset);
+    let offset = if fits(across) || !fits(-across) { across } else { -across };
+    (like.from + offset, like.to + offset)
+}
@@ -0,0 +1,234 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::measurement::Measurement;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Field {
+    LenPx,
+    LenMm,
+    LenCm,
+    LenIn,
+    Angle,
+    FromX,
+    FromY,
+    ToX,
+    ToY,
+    Dx,
+    Dy,
+}
+
+const FIELDS: &[(&str, Field, usize)] = &[
+    ("len_px", Field::LenPx, 0),
+    ("len_mm", Field::LenMm, 1),
+    ("len_cm", Field::LenCm, 2),
+    ("len_in", Field::LenIn, 2),
+    ("angle", Field::Angle, 2),
+    ("from_x", Field::FromX, 0),
+    ("from_y", Field::FromY, 0),
+    ("to_x", Field::ToX, 0),
+    ("to_y", Field::ToY, 0),
+    ("dx", Field::Dx, 0),
+    ("dy", Field::Dy, 0),
+];
+
+impl Field {
+    fn value(self, m: &Measurement) -> f64 {
+        match self {
+            Field::LenPx => m.length_px(),
+            Field::LenMm => m.length_mm(),
+            Field::LenCm => m.length_mm() / 10.0,
+            Field::LenIn => m.length_mm() / 25.4,
+            Field::Angle => m.angle(),
+            Field::FromX => m.from.x,
+            Field::FromY => m.from.y,
+            Field::ToX => m.to.x,
+            Field::ToY => m.to.y,
+            Field::Dx => m.to.x - m.from.x,
+            Field::Dy => m.to.y - m.from.y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Field { field: Field, precision: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct TemplateError {
+    template: String,
+    message: String,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid format '{}': {}", self.template, self.message)
+    }
+}
+
+impl Error for TemplateError {}
+
+/// A measurement output template such as `{len_px} px at {angle:.1}°`.
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn render(&self, m: &Measurement) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Field { field, precision } => out.push_str(&format!("{:.*}", precision, field.value(m))),
+            }
+        }
+        out
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        "{len_px} px, {angle}°".parse().unwrap()
+    }
+}
+
+fn parse_field(spec: &str) -> Result<Segment, String> {
+    let (name, precision) = match spec.split_once(':') {
+        Some((name, precision)) => {
+            let digits = precision.strip_prefix('.').ok_or_else(|| format!("expected '.N' after ':' in '{{{}}}'", spec))?;
+            (name, Some(digits.parse::<usize>().map_err(|_| format!("invalid precision in '{{{}}}'", spec))?))
+        }
+        None => (spec, None),
+    };
+    let &(_, field, default_precision) = FIELDS.iter().find(|(n, _, _)| *n == name)
+        .ok_or_else(|| format!("unknown placeholder '{{{}}}'", name))?;
+    Ok(Segment::Field { field, precision: precision.unwrap_or(default_precision) })
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |message: String| TemplateError { template: s.to_string(), message };
+
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| error("unclosed '{'".to_string()))?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(parse_field(&rest[..end]).map_err(error)?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err(error("unmatched '}', use '}}' for a literal brace".to_string())),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Template { source: s.to_string(), segments })
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Serialize for Template {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Template {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+
+    use super::*;
+
+    /// 500 px long at 2 px per millimeter, up and to the right.
+    const M: Measurement = Measurement { from: DVec2::new(100.0, 500.0), to: DVec2::new(400.0, 100.0), px_per_mm: DVec2::splat(2.0) };
+
+    fn render(template: &str) -> String {
+        template.parse::<Template>().unwrap().render(&M)
+    }
+
+    fn error(template: &str) -> String {
+        template.parse::<Template>().unwrap_err().message
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        assert_eq!(render("{len_px} px, {len_mm} mm, {len_cm} cm"), "500 px, 250.0 mm, 25.00 cm");
+        assert_eq!(render("{from_x},{from_y} to {to_x},{to_y} by {dx},{dy}"), "100,500 to 400,100 by 300,-400");
+        assert_eq!(render("{angle}°"), "53.13°");
+        assert_eq!(render("plain text"), "plain text");
+        assert_eq!(render(""), "");
+    }
+
+    #[test]
+    fn takes_a_precision() {
+        assert_eq!(render("{len_px:.2}"), "500.00");
+        assert_eq!(render("{len_in:.0} in at {angle:.1}"), "10 in at 53.1");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{len_px}}"), "{len_px}");
+        assert_eq!(render("{{{len_px}}}"), "{500}");
+        assert_eq!(render("}}{{"), "}{");
+    }
+
+    #[test]
+    fn refuses_unknown_placeholders() {
+        assert_eq!(error("{length}"), "unknown placeholder '{length}'");
+        assert_eq!(error("{LEN_PX}"), "unknown placeholder '{LEN_PX}'");
+        assert_eq!(error("{}"), "unknown placeholder '{}'");
+    }
+
+    #[test]
+    fn refuses_malformed_templates() {
+        assert_eq!(error("{len_px"), "unclosed '{'");
+        assert_eq!(error("len_px}"), "unmatched '}', use '}}' for a literal brace");
+        assert_eq!(error("{len_px:2}"), "expected '.N' after ':' in '{len_px:2}'");
+        assert_eq!(error("{len_px:.x}"), "invalid precision in '{len_px:.x}'");
+        let error = "{nope}".parse::<Template>().unwrap_err();
+        assert_eq!(error.to_string(), "Invalid format '{nope}': unknown placeholder '{nope}'");
+    }
+
+    #[test]
+    fn displays_as_written() {
+        assert_eq!("{len_px:.1} {{px}}".parse::<Template>().unwrap().to_string(), "{len_px:.1} {{px}}");
+    }
+}
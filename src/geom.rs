@@ -1,48 +1,80 @@
 use glam::DVec2;
 
-pub fn closest_point_below_line_on_circle(center: DVec2, radius: f64, start: DVec2, dir: DVec2, point: DVec2) -> DVec2 {
-    let (a, b, c) = {
-        let a = dir.y;
-        let b = -dir.x;
-        let c = start.y * dir.x - start.x * dir.y;
-        let coefficient = -(a * a + b * b).sqrt() * c.signum();
-        (a * coefficient, b * coefficient, c * coefficient)
-    };
-
-    let (a, b, c) = if a * center.x + b * center.y + c < 0.0 {
-        (-a, -b, -c)
-    } else {
-        (a, b, c)
-    };
+/// An axis-aligned rectangle from `min` to `max`, edges included.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub min: DVec2,
+    pub max: DVec2,
+}
 
-    if a * point.x + b * point.y + c > 0.0 {
-        point
-    } else {
-        match circle_intersect(center, radius, start, dir) {
-            Some((p1, p2)) => {
-                if p1.distance_squared(point) < p2.distance_squared(point) {
-                    p1
-                } else {
-                    p2
-                }
-            }
-            None => {
-                println!("This shouldn't really happen");
-                point
-            }
-        }
+impl Rect {
+    /// The rectangle from the origin to `size`, such as the screen.
+    pub fn from_size(size: DVec2) -> Rect {
+        Rect { min: DVec2::ZERO, max: size }
+    }
+
+    pub fn contains(&self, point: DVec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn clamp(&self, point: DVec2) -> DVec2 {
+        point.clamp(self.min, self.max)
     }
 }
 
-fn circle_intersect(center: DVec2, radius: f64, start: DVec2, dir: DVec2) -> Option<(DVec2, DVec2)> {
-    let a = dir.length_squared();
-    let b = 2.0 * dir.dot(start - center);
-    let c = start.distance_squared(center) - radius.powi(2);
+/// Turns `vec` onto the nearest multiple of `increment` degrees if it is
+/// within `tolerance` degrees of it, keeping its length. Returns the result
+/// and whether it snapped. A zero `vec` or `increment` is left as it is.
+pub fn snap_angle(vec: DVec2, increment: f64, tolerance: f64) -> (DVec2, bool) {
+    if increment <= 0.0 || vec == DVec2::ZERO {
+        return (vec, false);
+    }
+    let angle = vec.y.atan2(vec.x).to_degrees();
+    let snapped = (angle / increment).round() * increment;
+    if (angle - snapped).abs() > tolerance {
+        return (vec, false);
+    }
+    (DVec2::from_angle(snapped.to_radians()) * vec.length(), true)
+}
 
-    solve_quadratic(a, b, c).map(|solutions| (start + dir * solutions.0, start + dir * solutions.1))
+/// The point of the circle around `center` that lies within the rectangle
+/// from `min` to `max` and is closest to `point`, which is taken to be on the
+/// circle. `None` if no part of the circle is within the rectangle.
+///
+/// Any point of the circle outside of the rectangle is closest to one of the
+/// ends of the arcs inside it, which are where the circle crosses its edges.
+pub fn closest_point_on_circle_in_rect(center: DVec2, radius: f64, min: DVec2, max: DVec2, point: DVec2) -> Option<DVec2> {
+    if point.cmpge(min).all() && point.cmple(max).all() {
+        return Some(point);
+    }
+    let corners = [min, DVec2::new(max.x, min.y), max, DVec2::new(min.x, max.y)];
+    (0..4)
+        .flat_map(|i| circle_segment_intersect(center, radius, corners[i], corners[(i + 1) % 4]))
+        .min_by(|a, b| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
 }
 
+/// Where the circle crosses the segment from `a` to `b`, ends included.
+fn circle_segment_intersect(center: DVec2, radius: f64, a: DVec2, b: DVec2) -> impl Iterator<Item = DVec2> {
+    let dir = b - a;
+    let roots = solve_quadratic(dir.length_squared(), 2.0 * dir.dot(a - center), a.distance_squared(center) - radius.powi(2));
+    roots
+        .into_iter()
+        .flat_map(|(t1, t2)| [t1, t2])
+        .filter(|t| (0.0..=1.0).contains(t))
+        .map(move |t| a + dir * t)
+}
+
+/// Real roots of `a t² + b t + c`, smaller first. A zero `a`, as from a
+/// zero-length direction, leaves a linear equation with at most one root,
+/// and zero `a`, `b` and `c` give 0 for an equation any `t` solves.
 fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0.0 {
+        return match (b, c) {
+            (0.0, 0.0) => Some((0.0, 0.0)),
+            (0.0, _) => None,
+            _ => Some((-c / b, -c / b)),
+        };
+    }
     let d = b * b - 4.0 * a * c;
     if d < 0.0 {
         None
@@ -53,3 +85,496 @@ fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
         Some((t1, t2))
     }
 }
+
+/// The point on the line through `start` along `dir` closest to `point`,
+/// `start` itself for a zero `dir`.
+pub fn project_onto_line(point: DVec2, start: DVec2, dir: DVec2) -> DVec2 {
+    let length_squared = dir.length_squared();
+    if length_squared == 0.0 {
+        return start;
+    }
+    start + dir * (point - start).dot(dir) / length_squared
+}
+
+/// The point on the segment from `a` to `b` closest to `point`.
+pub fn closest_point_on_segment(point: DVec2, a: DVec2, b: DVec2) -> DVec2 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared == 0.0 {
+        return a;
+    }
+    a + ab * ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0)
+}
+
+pub fn distance_to_segment(point: DVec2, a: DVec2, b: DVec2) -> f64 {
+    point.distance(closest_point_on_segment(point, a, b))
+}
+
+/// Where the segments `a1`-`a2` and `b1`-`b2` cross, ends included. Parallel
+/// segments have no single crossing point, so they give `None` even when
+/// they overlap.
+pub fn segment_intersection(a1: DVec2, a2: DVec2, b1: DVec2, b2: DVec2) -> Option<DVec2> {
+    let (a, b) = (a2 - a1, b2 - b1);
+    let denominator = a.perp_dot(b);
+    if denominator == 0.0 {
+        return None;
+    }
+    let offset = b1 - a1;
+    let t = offset.perp_dot(b) / denominator;
+    let u = offset.perp_dot(a) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then(|| a1 + a * t)
+}
+
+/// Axis-aligned strips, at most `step` wide, that together cover the bar
+/// reaching `half_width` either side of the segment from `from` to `to`. The
+/// strips run across the axis the bar spans more of, and overshoot its edges
+/// by less than a strip's width.
+pub fn tessellate_bar(from: DVec2, to: DVec2, half_width: f64, step: f64) -> Vec<Rect> {
+    let across = (to - from).try_normalize().unwrap_or(DVec2::X).perp() * half_width;
+    // Steep bars are sliced along y, by working on them with the axes swapped.
+    let steep = (to.y - from.y).abs() > (to.x - from.x).abs();
+    let swap = |point: DVec2| if steep { DVec2::new(point.y, point.x) } else { point };
+    let corners = [from + across, to + across, to - across, from - across].map(swap);
+
+    let start = corners.iter().map(|corner| corner.x).fold(f64::INFINITY, f64::min);
+    let end = corners.iter().map(|corner| corner.x).fold(f64::NEG_INFINITY, f64::max);
+    let count = ((end - start) / step).ceil().max(1.0) as usize;
+    let width = (end - start) / count as f64;
+
+    (0..count)
+        .map(|i| {
+            let (x0, x1) = (start + width * i as f64, start + width * (i + 1) as f64);
+            // The bar is convex, so within the strip it spans from its lowest to
+            // its highest corner or crossing of the strip's sides.
+            let inside = corners.iter().filter(|corner| (x0..=x1).contains(&corner.x)).map(|corner| corner.y);
+            let crossings = (0..4).flat_map(|edge| {
+                let (a, b) = (corners[edge], corners[(edge + 1) % 4]);
+                [x0, x1].into_iter().filter_map(move |x| {
+                    let t = (x - a.x) / (b.x - a.x);
+                    (0.0..=1.0).contains(&t).then_some(a.y + (b.y - a.y) * t)
+                })
+            });
+            let (low, high) = inside.chain(crossings).fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), y| (low.min(y), high.max(y)));
+            Rect { min: swap(DVec2::new(x0, low)), max: swap(DVec2::new(x1, high)) }
+        })
+        .collect()
+}
+
+/// Where `point` lands on the nearest edge or corner of any of `rects`
+/// within `reach` of it, the rectangles from the bottom of a stack up. An
+/// edge only counts where it lies out from under the rectangles above it.
+pub fn snap_to_rects(point: DVec2, rects: &[Rect], reach: f64) -> Option<DVec2> {
+    let covered = |point: DVec2, above: &[Rect]| above.iter().any(|rect| point.cmpgt(rect.min).all() && point.cmplt(rect.max).all());
+    let nearest = |value: f64, edges: [f64; 2]| edges.into_iter().filter(|edge| (edge - value).abs() <= reach).min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()));
+    rects
+        .iter()
+        .enumerate()
+        .filter_map(|(i, rect)| {
+            // Near enough the rectangle for its edges to be in reach along them too.
+            let near = point.cmpge(rect.min - reach).all() && point.cmple(rect.max + reach).all();
+            let x = nearest(point.x, [rect.min.x, rect.max.x]).filter(|_| near);
+            let y = nearest(point.y, [rect.min.y, rect.max.y]).filter(|_| near);
+            if x.is_none() && y.is_none() {
+                return None;
+            }
+            // Along the edge, but no further than its ends.
+            let snapped = rect.clamp(DVec2::new(x.unwrap_or(point.x), y.unwrap_or(point.y)));
+            (snapped.distance(point) <= reach && !covered(snapped, &rects[i + 1..])).then_some(snapped)
+        })
+        .min_by(|a, b| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
+}
+
+/// The nearest point within `reach` of `point` on the lines through the
+/// middle of `rect` across and down, or on its diagonals, the middle itself
+/// if that is in reach.
+pub fn snap_to_center_lines(point: DVec2, rect: &Rect, reach: f64) -> Option<DVec2> {
+    let center = (rect.min + rect.max) / 2.0;
+    if point.distance(center) <= reach {
+        return Some(center);
+    }
+    let size = rect.max - rect.min;
+    let lines = [(center, DVec2::X), (center, DVec2::Y), (rect.min, size), (DVec2::new(rect.min.x, rect.max.y), DVec2::new(size.x, -size.y))];
+    lines
+        .into_iter()
+        .filter(|(_, dir)| *dir != DVec2::ZERO)
+        .map(|(start, dir)| project_onto_line(point, start, dir))
+        .filter(|snapped| rect.contains(*snapped) && snapped.distance(point) <= reach)
+        .min_by(|a, b| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
+}
+
+/// The shortest segment between two rectangles that do not overlap, edge to
+/// edge through the middle of the stretch they face each other along, or
+/// corner to corner if they face each other along neither axis. `None` if
+/// they overlap.
+pub fn gap_between(a: &Rect, b: &Rect) -> Option<(DVec2, DVec2)> {
+    let mut ends = (DVec2::ZERO, DVec2::ZERO);
+    let mut apart = false;
+    for axis in 0..2 {
+        (ends.0[axis], ends.1[axis]) = if a.max[axis] <= b.min[axis] {
+            apart = true;
+            (a.max[axis], b.min[axis])
+        } else if b.max[axis] <= a.min[axis] {
+            apart = true;
+            (a.min[axis], b.max[axis])
+        } else {
+            let middle = (a.min[axis].max(b.min[axis]) + a.max[axis].min(b.max[axis])) / 2.0;
+            (middle, middle)
+        };
+    }
+    apart.then_some(ends)
+}
+
+/// The pixel under every point a pixel apart along the segment from `from`
+/// to `to`, both ends included, numbered by their distance from `from`.
+pub fn line_pixels(from: DVec2, to: DVec2) -> Vec<DVec2> {
+    let count = from.distance(to).round().max(1.0) as usize;
+    (0..=count).map(|i| from.lerp(to, i as f64 / count as f64).floor()).collect()
+}
+
+/// The point nearest `point` on the grid of whole pixels shifted by
+/// `offset`, such as 0.5 for the centers of pixels, and inside `within` if
+/// given, whose edges are taken to be whole pixels.
+pub fn round_to_grid(point: DVec2, offset: f64, within: Option<&Rect>) -> DVec2 {
+    let rounded = (point - offset).round() + offset;
+    match within {
+        Some(rect) => rounded.clamp((rect.min - offset).ceil() + offset, ((rect.max - offset).floor() + offset).max((rect.min - offset).ceil() + offset)),
+        None => rounded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec2;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const EPSILON: f64 = 1e-6;
+
+    /// Which side of the line through `start` along `dir` a point is on.
+    fn side(start: DVec2, dir: DVec2, point: DVec2) -> f64 {
+        dir.perp_dot(point - start)
+    }
+
+    #[test]
+    fn quadratic_with_two_roots() {
+        assert_eq!(solve_quadratic(1.0, -3.0, 2.0), Some((1.0, 2.0)));
+    }
+
+    #[test]
+    fn quadratic_with_a_double_root() {
+        assert_eq!(solve_quadratic(1.0, -2.0, 1.0), Some((1.0, 1.0)));
+    }
+
+    #[test]
+    fn quadratic_without_real_roots() {
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), None);
+    }
+
+    #[test]
+    fn linear_equation() {
+        assert_eq!(solve_quadratic(0.0, 2.0, -4.0), Some((2.0, 2.0)));
+        assert_eq!(solve_quadratic(0.0, 0.0, 1.0), None);
+        assert_eq!(solve_quadratic(0.0, 0.0, 0.0), Some((0.0, 0.0)));
+    }
+
+    #[test]
+    fn projection_onto_a_line() {
+        let projected = project_onto_line(DVec2::new(3.0, 7.0), DVec2::new(0.0, 1.0), DVec2::new(2.0, 0.0));
+        assert!(projected.distance(DVec2::new(3.0, 1.0)) < EPSILON);
+        assert_eq!(project_onto_line(DVec2::new(3.0, 7.0), DVec2::new(0.0, 1.0), DVec2::ZERO), DVec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn closest_point_inside_and_past_a_segment() {
+        let (a, b) = (DVec2::ZERO, DVec2::new(10.0, 0.0));
+        assert_eq!(closest_point_on_segment(DVec2::new(4.0, 3.0), a, b), DVec2::new(4.0, 0.0));
+        assert_eq!(closest_point_on_segment(DVec2::new(-4.0, 3.0), a, b), a);
+        assert_eq!(closest_point_on_segment(DVec2::new(14.0, -3.0), a, b), b);
+        assert_eq!(distance_to_segment(DVec2::new(13.0, 4.0), a, b), 5.0);
+    }
+
+    #[test]
+    fn degenerate_segment() {
+        let a = DVec2::new(1.0, 1.0);
+        assert_eq!(closest_point_on_segment(DVec2::new(4.0, 5.0), a, a), a);
+        assert_eq!(distance_to_segment(DVec2::new(4.0, 5.0), a, a), 5.0);
+    }
+
+    #[test]
+    fn crossing_segments() {
+        let crossing = segment_intersection(DVec2::ZERO, DVec2::new(4.0, 4.0), DVec2::new(0.0, 4.0), DVec2::new(4.0, 0.0)).unwrap();
+        assert!(crossing.distance(DVec2::new(2.0, 2.0)) < EPSILON);
+    }
+
+    #[test]
+    fn segments_touching_at_an_end() {
+        let touching = segment_intersection(DVec2::ZERO, DVec2::new(2.0, 0.0), DVec2::new(2.0, -1.0), DVec2::new(2.0, 1.0)).unwrap();
+        assert!(touching.distance(DVec2::new(2.0, 0.0)) < EPSILON);
+    }
+
+    #[test]
+    fn segments_that_do_not_cross() {
+        // The lines cross at (5, 5), past the end of both.
+        assert_eq!(segment_intersection(DVec2::ZERO, DVec2::new(4.0, 4.0), DVec2::new(10.0, 0.0), DVec2::new(6.0, 4.0)), None);
+        assert_eq!(segment_intersection(DVec2::ZERO, DVec2::new(4.0, 0.0), DVec2::new(0.0, 1.0), DVec2::new(4.0, 1.0)), None);
+        assert_eq!(segment_intersection(DVec2::ZERO, DVec2::new(4.0, 0.0), DVec2::new(2.0, 0.0), DVec2::new(6.0, 0.0)), None);
+    }
+
+    #[test]
+    fn angles_near_an_increment_snap() {
+        let (snapped, did) = snap_angle(DVec2::from_angle(44f64.to_radians()) * 10.0, 15.0, 2.0);
+        assert!(did);
+        assert!(snapped.distance(DVec2::from_angle(45f64.to_radians()) * 10.0) < EPSILON);
+    }
+
+    #[test]
+    fn angles_between_increments_are_kept() {
+        let vec = DVec2::from_angle(-52f64.to_radians()) * 10.0;
+        assert_eq!(snap_angle(vec, 15.0, 2.0), (vec, false));
+    }
+
+    #[test]
+    fn snapping_across_the_wraparound() {
+        let (snapped, did) = snap_angle(DVec2::new(-10.0, -0.1), 90.0, 2.0);
+        assert!(did);
+        assert!(snapped.distance(DVec2::new(-10.0005, 0.0)) < 1e-3);
+    }
+
+    #[test]
+    fn nothing_to_snap() {
+        assert_eq!(snap_angle(DVec2::ZERO, 15.0, 2.0), (DVec2::ZERO, false));
+        assert_eq!(snap_angle(DVec2::new(3.0, 1.0), 0.0, 2.0), (DVec2::new(3.0, 1.0), false));
+    }
+
+    #[test]
+    fn point_in_the_rectangle_is_kept() {
+        let point = DVec2::new(60.0, 50.0);
+        assert_eq!(closest_point_on_circle_in_rect(DVec2::new(50.0, 50.0), 10.0, DVec2::ZERO, DVec2::splat(100.0), point), Some(point));
+    }
+
+    #[test]
+    fn point_past_an_edge_goes_to_where_the_circle_crosses_it() {
+        // The circle around (0, 3) crosses the top edge at (4, 0).
+        let result = closest_point_on_circle_in_rect(DVec2::new(0.0, 3.0), 5.0, DVec2::new(-100.0, 0.0), DVec2::splat(100.0), DVec2::new(3.0, -2.0)).unwrap();
+        assert!(result.distance(DVec2::new(4.0, 0.0)) < EPSILON);
+    }
+
+    #[test]
+    fn point_past_a_corner_stays_on_the_circle() {
+        // Clamping to one edge and then the other would leave the circle here.
+        let center = DVec2::new(3.0, 3.0);
+        let result = closest_point_on_circle_in_rect(center, 5.0, DVec2::ZERO, DVec2::splat(100.0), center + DVec2::splat(-5.0 / 2f64.sqrt())).unwrap();
+        assert!((result.distance(center) - 5.0).abs() < EPSILON);
+        assert!(result.cmpge(DVec2::splat(-EPSILON)).all());
+    }
+
+    #[test]
+    fn point_goes_to_the_nearer_of_two_crossings() {
+        // The circle around (0, -3) crosses the top edge at (-4, 0) and (4, 0).
+        let result = closest_point_on_circle_in_rect(DVec2::new(0.0, -3.0), 5.0, DVec2::new(-100.0, 0.0), DVec2::splat(100.0), DVec2::new(5.0, -3.0)).unwrap();
+        assert!(result.distance(DVec2::new(4.0, 0.0)) < EPSILON);
+    }
+
+    #[test]
+    fn circle_touching_an_edge() {
+        // Tangent to the top edge at (0, 0), the only point of it in the rectangle.
+        let result = closest_point_on_circle_in_rect(DVec2::new(0.0, -5.0), 5.0, DVec2::new(-100.0, 0.0), DVec2::splat(100.0), DVec2::new(5.0, -5.0)).unwrap();
+        assert!(result.distance(DVec2::ZERO) < EPSILON);
+    }
+
+    #[test]
+    fn circle_outside_the_rectangle() {
+        assert_eq!(closest_point_on_circle_in_rect(DVec2::splat(200.0), 5.0, DVec2::ZERO, DVec2::splat(100.0), DVec2::new(205.0, 200.0)), None);
+        // Just short of the top edge.
+        assert_eq!(closest_point_on_circle_in_rect(DVec2::new(0.0, -6.0), 5.0, DVec2::new(-100.0, 0.0), DVec2::splat(100.0), DVec2::new(0.0, -1.0)), None);
+    }
+
+    #[test]
+    fn circle_around_the_rectangle() {
+        // No part of the circle is inside, though the rectangle is inside it.
+        assert_eq!(closest_point_on_circle_in_rect(DVec2::splat(50.0), 500.0, DVec2::ZERO, DVec2::splat(100.0), DVec2::new(550.0, 50.0)), None);
+    }
+
+    #[test]
+    fn degenerate_circles_and_rectangles() {
+        // A circle of no radius is only in the rectangle if its center is.
+        let center = DVec2::new(50.0, 50.0);
+        assert_eq!(closest_point_on_circle_in_rect(center, 0.0, DVec2::ZERO, DVec2::splat(100.0), center), Some(center));
+        assert_eq!(closest_point_on_circle_in_rect(DVec2::splat(-1.0), 0.0, DVec2::ZERO, DVec2::splat(100.0), DVec2::splat(-1.0)), None);
+
+        // A rectangle of no width is where the circle crosses the line it is.
+        let result = closest_point_on_circle_in_rect(DVec2::ZERO, 5.0, DVec2::new(4.0, -100.0), DVec2::new(4.0, 100.0), DVec2::new(5.0, 0.0)).unwrap();
+        assert!(result.distance(DVec2::new(4.0, 3.0)) < EPSILON || result.distance(DVec2::new(4.0, -3.0)) < EPSILON);
+
+        // A rectangle shrunk to a point is on the circle or not.
+        let point = DVec2::new(3.0, 4.0);
+        assert_eq!(closest_point_on_circle_in_rect(DVec2::ZERO, 5.0, point, point, DVec2::new(-5.0, 0.0)), Some(point));
+        assert_eq!(closest_point_on_circle_in_rect(DVec2::ZERO, 5.0, DVec2::splat(1.0), DVec2::splat(1.0), DVec2::new(-5.0, 0.0)), None);
+    }
+
+    #[test]
+    fn level_bar_is_covered_exactly() {
+        let strips = tessellate_bar(DVec2::new(10.0, 50.0), DVec2::new(110.0, 50.0), 20.0, 25.0);
+        assert_eq!(strips.len(), 4);
+        for (i, strip) in strips.iter().enumerate() {
+            assert_eq!(strip.min, DVec2::new(10.0 + 25.0 * i as f64, 30.0));
+            assert_eq!(strip.max, DVec2::new(35.0 + 25.0 * i as f64, 70.0));
+        }
+    }
+
+    #[test]
+    fn steep_bar_is_sliced_along_y() {
+        let strips = tessellate_bar(DVec2::new(50.0, 0.0), DVec2::new(50.0, 100.0), 10.0, 50.0);
+        assert_eq!(strips.len(), 2);
+        assert_eq!(strips[0], Rect { min: DVec2::new(40.0, 0.0), max: DVec2::new(60.0, 50.0) });
+    }
+
+    #[test]
+    fn zero_length_bar() {
+        let strips = tessellate_bar(DVec2::splat(5.0), DVec2::splat(5.0), 3.0, 4.0);
+        assert_eq!(strips, vec![Rect { min: DVec2::new(5.0, 2.0), max: DVec2::new(5.0, 8.0) }]);
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_window_edge() {
+        let window = Rect { min: DVec2::new(100.0, 100.0), max: DVec2::new(300.0, 200.0) };
+        assert_eq!(snap_to_rects(DVec2::new(104.0, 150.0), &[window], 8.0), Some(DVec2::new(100.0, 150.0)));
+        assert_eq!(snap_to_rects(DVec2::new(295.0, 150.0), &[window], 8.0), Some(DVec2::new(300.0, 150.0)));
+        assert_eq!(snap_to_rects(DVec2::new(200.0, 150.0), &[window], 8.0), None);
+    }
+
+    #[test]
+    fn snaps_to_a_window_corner() {
+        let window = Rect { min: DVec2::new(100.0, 100.0), max: DVec2::new(300.0, 200.0) };
+        assert_eq!(snap_to_rects(DVec2::new(304.0, 195.0), &[window], 8.0), Some(DVec2::new(300.0, 200.0)));
+        // Past the corner along the edge, but out of reach of the window.
+        assert_eq!(snap_to_rects(DVec2::new(304.0, 220.0), &[window], 8.0), None);
+    }
+
+    #[test]
+    fn edges_under_a_window_above_do_not_count() {
+        let below = Rect { min: DVec2::new(100.0, 100.0), max: DVec2::new(300.0, 200.0) };
+        let above = Rect { min: DVec2::new(50.0, 50.0), max: DVec2::new(200.0, 250.0) };
+        assert_eq!(snap_to_rects(DVec2::new(104.0, 150.0), &[below, above], 8.0), None);
+        assert_eq!(snap_to_rects(DVec2::new(104.0, 150.0), &[above, below], 8.0), Some(DVec2::new(100.0, 150.0)));
+    }
+
+    #[test]
+    fn gap_between_windows_side_by_side() {
+        let left = Rect { min: DVec2::new(0.0, 0.0), max: DVec2::new(100.0, 100.0) };
+        let right = Rect { min: DVec2::new(120.0, 50.0), max: DVec2::new(200.0, 300.0) };
+        assert_eq!(gap_between(&left, &right), Some((DVec2::new(100.0, 75.0), DVec2::new(120.0, 75.0))));
+        assert_eq!(gap_between(&right, &left), Some((DVec2::new(120.0, 75.0), DVec2::new(100.0, 75.0))));
+    }
+
+    #[test]
+    fn gap_between_windows_apart_diagonally() {
+        let a = Rect { min: DVec2::new(0.0, 0.0), max: DVec2::new(100.0, 100.0) };
+        let b = Rect { min: DVec2::new(130.0, 140.0), max: DVec2::new(200.0, 200.0) };
+        assert_eq!(gap_between(&a, &b), Some((DVec2::new(100.0, 100.0), DVec2::new(130.0, 140.0))));
+    }
+
+    #[test]
+    fn no_gap_between_overlapping_windows() {
+        let a = Rect { min: DVec2::new(0.0, 0.0), max: DVec2::new(100.0, 100.0) };
+        let b = Rect { min: DVec2::new(50.0, 50.0), max: DVec2::new(200.0, 200.0) };
+        assert_eq!(gap_between(&a, &b), None);
+    }
+
+    #[test]
+    fn snaps_to_the_center_lines_and_diagonals() {
+        let screen = Rect::from_size(DVec2::new(1920.0, 1080.0));
+        assert_eq!(snap_to_center_lines(DVec2::new(963.0, 200.0), &screen, 8.0), Some(DVec2::new(960.0, 200.0)));
+        assert_eq!(snap_to_center_lines(DVec2::new(300.0, 545.0), &screen, 8.0), Some(DVec2::new(300.0, 540.0)));
+        assert_eq!(snap_to_center_lines(DVec2::new(964.0, 536.0), &screen, 8.0), Some(DVec2::new(960.0, 540.0)));
+        let diagonal = snap_to_center_lines(DVec2::new(480.0, 275.0), &screen, 8.0).unwrap();
+        assert!((diagonal.y - diagonal.x * 1080.0 / 1920.0).abs() < EPSILON);
+        assert_eq!(snap_to_center_lines(DVec2::new(300.0, 600.0), &screen, 8.0), None);
+    }
+
+    #[test]
+    fn pixels_along_a_row() {
+        let pixels = line_pixels(DVec2::new(3.5, 0.5), DVec2::new(0.5, 0.5));
+        assert_eq!(pixels, vec![DVec2::new(3.0, 0.0), DVec2::new(2.0, 0.0), DVec2::new(1.0, 0.0), DVec2::new(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn pixels_of_a_point() {
+        assert_eq!(line_pixels(DVec2::splat(2.5), DVec2::splat(2.5)), vec![DVec2::splat(2.0); 2]);
+    }
+
+    #[test]
+    fn rounds_to_pixel_centers_and_boundaries() {
+        assert_eq!(round_to_grid(DVec2::new(10.2, 7.9), 0.5, None), DVec2::new(10.5, 7.5));
+        assert_eq!(round_to_grid(DVec2::new(10.2, 7.9), 0.0, None), DVec2::new(10.0, 8.0));
+    }
+
+    #[test]
+    fn rounds_to_the_grid_inside_a_rectangle() {
+        let screen = Rect::from_size(DVec2::new(1920.0, 1080.0));
+        assert_eq!(round_to_grid(DVec2::new(1920.0, 0.0), 0.5, Some(&screen)), DVec2::new(1919.5, 0.5));
+        assert_eq!(round_to_grid(DVec2::new(1920.0, 0.0), 0.0, Some(&screen)), DVec2::new(1920.0, 0.0));
+    }
+
+    fn vector(range: f64) -> impl Strategy<Value = DVec2> {
+        (-range..range, -range..range).prop_map(|(x, y)| DVec2::new(x, y))
+    }
+
+    proptest! {
+        #[test]
+        fn projection_is_perpendicular(point in vector(1000.0), start in vector(1000.0), dir in vector(10.0)) {
+            prop_assume!(dir.length() > EPSILON);
+            let projected = project_onto_line(point, start, dir);
+            prop_assert!(side(start, dir.normalize(), projected).abs() < EPSILON * 1000.0);
+            prop_assert!((point - projected).dot(dir.normalize()).abs() < EPSILON * 1000.0);
+        }
+
+        #[test]
+        fn segment_distance_bounds(point in vector(1000.0), a in vector(1000.0), b in vector(1000.0)) {
+            let distance = distance_to_segment(point, a, b);
+            prop_assert!(distance <= point.distance(a) + EPSILON && distance <= point.distance(b) + EPSILON);
+            let line = project_onto_line(point, a, b - a);
+            prop_assert!(distance >= point.distance(line) - EPSILON * 1000.0);
+        }
+
+        #[test]
+        fn crossings_lie_on_both_segments(a1 in vector(1000.0), a2 in vector(1000.0), b1 in vector(1000.0), b2 in vector(1000.0)) {
+            if let Some(crossing) = segment_intersection(a1, a2, b1, b2) {
+                prop_assert!(distance_to_segment(crossing, a1, a2) < EPSILON * 1000.0);
+                prop_assert!(distance_to_segment(crossing, b1, b2) < EPSILON * 1000.0);
+            }
+        }
+
+        #[test]
+        fn confined_point_stays_on_the_circle_and_in_the_rectangle(
+            center in vector(100.0).prop_map(|v| v + 100.0),
+            radius in 1.0..400.0,
+            angle in 0.0..std::f64::consts::TAU,
+        ) {
+            let (min, max) = (DVec2::ZERO, DVec2::splat(200.0));
+            let point = center + DVec2::from_angle(angle) * radius;
+            if let Some(result) = closest_point_on_circle_in_rect(center, radius, min, max, point) {
+                prop_assert!((result.distance(center) - radius).abs() < EPSILON * radius);
+                prop_assert!(result.cmpge(min - EPSILON * 1000.0).all() && result.cmple(max + EPSILON * 1000.0).all());
+            }
+        }
+
+        #[test]
+        fn strips_cover_the_bar_and_stay_near_it(
+            from in vector(1000.0),
+            to in vector(1000.0),
+            half_width in 1.0..50.0,
+            (along, offset) in (0.001..0.999, -0.999..0.999),
+        ) {
+            let strips = tessellate_bar(from, to, half_width, 4.0);
+            let direction = (to - from).try_normalize().unwrap_or(DVec2::X);
+            let point = from.lerp(to, along) + direction.perp() * half_width * offset;
+            prop_assert!(strips.iter().any(|strip| strip.contains(point)));
+            for strip in &strips {
+                let center = (strip.min + strip.max) / 2.0;
+                prop_assert!(distance_to_segment(center, from, to) <= half_width + 4.0 * std::f64::consts::SQRT_2);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::error::Error;
+
+use cairo::Context;
+use glam::DVec2;
+use xcb::x::{ClearArea, ExposeEvent, MapWindow, UnmapWindow, Window};
+
+use ruler::state::RulerState;
+
+use crate::backend::{Render, XCBObjects};
+use crate::config::{GridOrigin, Settings};
+
+/// Lines every `grid.cell` pixels across the whole screen, running through
+/// an endpoint, in a window of its own that the pointer passes through.
+pub struct Grid {
+    pub window: Window,
+    visible: bool,
+    /// Where the origin was within one period of the grid when it was last
+    /// painted. A move by whole periods leaves the grid as it was.
+    phase: Option<DVec2>,
+}
+
+impl Grid {
+    pub fn create(xcb: &XCBObjects, screen_size: DVec2) -> Result<Grid, Box<dyn Error>> {
+        let window = xcb.create_overlay(screen_size.x as u16, screen_size.y as u16)?;
+        Ok(Grid { window, visible: false, phase: None })
+    }
+
+    pub fn shown(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self, xcb: &XCBObjects, state: &RulerState, settings: &Settings) {
+        self.visible = !self.visible;
+        if self.visible {
+            xcb.conn.send_request(&MapWindow { window: self.window });
+            self.follow(xcb, state, settings);
+        } else {
+            xcb.conn.send_request(&UnmapWindow { window: self.window });
+            self.phase = None;
+        }
+    }
+
+    /// Has the grid painted again if the origin moved it, or anyway after
+    /// `invalidate`.
+    pub fn follow(&mut self, xcb: &XCBObjects, state: &RulerState, settings: &Settings) {
+        let phase = origin(state, settings).rem_euclid(DVec2::splat(period(settings)));
+        if self.visible && self.phase != Some(phase) {
+            self.phase = Some(phase);
+            xcb.conn.send_request(&ClearArea { exposures: true, window: self.window, x: 0, y: 0, width: 0, height: 0 });
+        }
+    }
+
+    /// Forgets what was painted, so the next `follow` paints again.
+    pub fn invalidate(&mut self) {
+        self.phase = None;
+    }
+
+    /// Paints the grid once the last of a series of Expose events for it
+    /// arrives, since it is drawn in full.
+    pub fn expose(&self, ev: &ExposeEvent, render: &Render, state: &RulerState, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        if ev.count() > 0 {
+            return Ok(());
+        }
+        render.clear()?;
+        paint(&render.ctx, origin(state, settings), state.screen_size, settings)
+    }
+
+    /// Draws the grid onto `ctx` as on the screen, if it is shown.
+    pub fn export(&self, ctx: &Context, state: &RulerState, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        if self.visible {
+            paint(ctx, origin(state, settings), state.screen_size, settings)?;
+        }
+        Ok(())
+    }
+}
+
+fn origin(state: &RulerState, settings: &Settings) -> DVec2 {
+    let origin = match settings.grid.origin {
+        GridOrigin::From => state.from,
+        GridOrigin::To => state.to,
+    };
+    origin.floor()
+}
+
+/// The distance after which the grid repeats, major lines included.
+fn period(settings: &Settings) -> f64 {
+    settings.grid.cell() * settings.grid.major_every.max(1) as f64
+}
+
+fn paint(ctx: &Context, origin: DVec2, screen_size: DVec2, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let (cell, major_every) = (settings.grid.cell(), settings.grid.major_every as i64);
+    let accent = settings.theme.accent;
+
+    ctx.set_line_width(1.0);
+
+    for major in [false, true] {
+        let alpha = if major { settings.theme.opacity } else { settings.theme.opacity * 0.35 };
+        ctx.set_source_rgba(accent.r, accent.g, accent.b, alpha);
+        for axis in [0, 1] {
+            let (start, extent) = (origin[axis], screen_size[axis]);
+            // The line numbers that land on the screen, counted from the origin.
+            let first = (-start / cell).ceil() as i64;
+            let last = ((extent - start) / cell).floor() as i64;
+            for line in (first..=last).filter(|line| (major_every > 0 && line % major_every == 0) == major) {
+                // Half a pixel in, so a line covers one column of pixels exactly.
+                let position = start + line as f64 * cell + 0.5;
+                if axis == 0 {
+                    ctx.move_to(position, 0.0);
+                    ctx.line_to(position, screen_size.y);
+                } else {
+                    ctx.move_to(0.0, position);
+                    ctx.line_to(screen_size.x, position);
+                }
+            }
+        }
+        ctx.stroke()?;
+    }
+    Ok(())
+}
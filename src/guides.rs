@@ -0,0 +1,108 @@
+use std::error::Error;
+
+use cairo::Context;
+use glam::DVec2;
+use xcb::x::{ChangeWindowAttributes, ClearArea, ConfigWindow, ConfigureWindow, Cw, MapWindow, StackMode, UnmapWindow, Window};
+use xcb::Xid;
+
+use ruler::state::RulerState;
+
+use crate::backend::{coordinate, XCBObjects};
+use crate::config::{Color, Theme};
+
+/// Thickness of a guide in pixels.
+const THICKNESS: u16 = 1;
+
+/// Lines across the whole screen through each endpoint, horizontal and
+/// vertical. Each is a window of its own painted in the accent color by the
+/// server, which the pointer passes through.
+pub struct Guides {
+    /// The horizontal and vertical guide through `from`, then through `to`.
+    windows: [Window; 4],
+    visible: bool,
+    /// The endpoints the guides were last moved to.
+    at: Option<(DVec2, DVec2)>,
+}
+
+impl Guides {
+    pub fn create(xcb: &XCBObjects, theme: &Theme) -> Result<Guides, Box<dyn Error>> {
+        let mut windows = [Window::none(); 4];
+        for window in &mut windows {
+            *window = xcb.create_overlay(THICKNESS, THICKNESS)?;
+        }
+        let guides = Guides { windows, visible: false, at: None };
+        guides.set_color(xcb, theme);
+        Ok(guides)
+    }
+
+    pub fn set_color(&self, xcb: &XCBObjects, theme: &Theme) {
+        let pixel = pixel(theme.accent, theme.opacity);
+        for window in self.windows {
+            xcb.conn.send_request(&ChangeWindowAttributes { window, value_list: &[Cw::BackPixel(pixel)] });
+            xcb.conn.send_request(&ClearArea { exposures: false, window, x: 0, y: 0, width: 0, height: 0 });
+        }
+    }
+
+    /// The windows of the guides while they are shown.
+    pub fn shown_windows(&self) -> &[Window] {
+        if self.visible { &self.windows } else { &[] }
+    }
+
+    pub fn toggle(&mut self, xcb: &XCBObjects, state: &RulerState) {
+        self.visible = !self.visible;
+        if self.visible {
+            self.follow(xcb, state);
+            for window in self.windows {
+                xcb.conn.send_request(&MapWindow { window });
+            }
+        } else {
+            for window in self.windows {
+                xcb.conn.send_request(&UnmapWindow { window });
+            }
+        }
+    }
+
+    /// Moves the guides to the endpoints while they are shown.
+    pub fn follow(&mut self, xcb: &XCBObjects, state: &RulerState) {
+        if !self.visible || self.at == Some((state.from, state.to)) {
+            return;
+        }
+        self.at = Some((state.from, state.to));
+        let (width, height) = (state.screen_size.x as u32, state.screen_size.y as u32);
+        for (windows, point) in self.windows.chunks(2).zip([state.from, state.to]) {
+            let (x, y) = (coordinate(point.x.floor(), "guide x") as i32, coordinate(point.y.floor(), "guide y") as i32);
+            let thickness = THICKNESS as u32;
+            xcb.conn.send_request(&ConfigureWindow {
+                window: windows[0],
+                value_list: &[ConfigWindow::X(0), ConfigWindow::Y(y), ConfigWindow::Width(width), ConfigWindow::Height(thickness), ConfigWindow::StackMode(StackMode::Above)],
+            });
+            xcb.conn.send_request(&ConfigureWindow {
+                window: windows[1],
+                value_list: &[ConfigWindow::X(x), ConfigWindow::Y(0), ConfigWindow::Width(thickness), ConfigWindow::Height(height), ConfigWindow::StackMode(StackMode::Above)],
+            });
+        }
+    }
+
+    /// Draws the guides onto `ctx` where their windows are, if they are shown.
+    pub fn export(&self, ctx: &Context, state: &RulerState, theme: &Theme) -> Result<(), Box<dyn Error>> {
+        if !self.visible {
+            return Ok(());
+        }
+        ctx.set_source_rgba(theme.accent.r, theme.accent.g, theme.accent.b, theme.opacity);
+        for point in [state.from, state.to] {
+            let (x, y) = (point.x.floor(), point.y.floor());
+            ctx.rectangle(0.0, y, state.screen_size.x, THICKNESS as f64);
+            ctx.rectangle(x, 0.0, THICKNESS as f64, state.screen_size.y);
+        }
+        ctx.fill()?;
+        Ok(())
+    }
+}
+
+/// `color` at `alpha` as a pixel of the ruler's 32-bit visual, which takes
+/// premultiplied alpha.
+fn pixel(color: Color, alpha: f64) -> u32 {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let channel = |value: f64| ((value * alpha).clamp(0.0, 1.0) * 255.0).round() as u32;
+    (channel(1.0) << 24) | (channel(color.r) << 16) | (channel(color.g) << 8) | channel(color.b)
+}
@@ -0,0 +1,95 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::warn;
+
+use ruler::measurement::Measurement;
+use ruler::output::{Lengths, Point, Report};
+
+use crate::xdg;
+
+const HISTORY_FILE: &str = "history.tsv";
+
+pub fn path() -> Option<PathBuf> {
+    xdg::data_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+/// One tab-separated line per measurement: unix time, both endpoints, the
+/// length in pixels and millimeters and the angle.
+fn entry(measurement: &Measurement, time: SystemTime) -> String {
+    let timestamp = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let Measurement { from, to, .. } = measurement;
+    format!(
+        "{}\t{},{}\t{},{}\t{:.0} px\t{:.1} mm\t{:.2}°",
+        timestamp, from.x, from.y, to.x, to.y, measurement.length_px(), measurement.length_mm(), measurement.angle()
+    )
+}
+
+/// A history line as printed by `history list --json`.
+#[derive(Debug, Serialize)]
+struct Entry {
+    timestamp: u64,
+    #[serde(flatten)]
+    report: Report,
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let point = |field: &str| {
+        let (x, y) = field.split_once(',')?;
+        Some(Point { x: x.parse().ok()?, y: y.parse().ok()? })
+    };
+    let number = |field: &str, suffix: &str| field.strip_suffix(suffix)?.trim_end().parse::<f64>().ok();
+
+    let [timestamp, from, to, px, mm, angle] = line.split('\t').collect::<Vec<_>>()[..] else { return None };
+    let mm = number(mm, "mm")?;
+    Some(Entry {
+        timestamp: timestamp.parse().ok()?,
+        report: Report {
+            from: point(from)?,
+            to: point(to)?,
+            length: Lengths { px: number(px, "px")?, mm, cm: mm / 10.0, inches: mm / 25.4 },
+            angle: number(angle, "°")?,
+        },
+    })
+}
+
+pub fn append(measurement: &Measurement) -> Result<(), Box<dyn Error>> {
+    let Some(path) = path() else { return Ok(()) };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", entry(measurement, SystemTime::now()))?;
+    Ok(())
+}
+
+/// Prints the history as it is stored, or as one JSON object per line.
+pub fn list(json: bool) -> Result<(), Box<dyn Error>> {
+    let Some(path) = path() else { return Ok(()) };
+    match fs::read_to_string(&path) {
+        Ok(contents) if json => {
+            for line in contents.lines() {
+                match parse_entry(line) {
+                    Some(entry) => println!("{}", serde_json::to_string(&entry)?),
+                    None => warn!("Skipping malformed history line: {}", line),
+                }
+            }
+        }
+        Ok(contents) => print!("{}", contents),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+pub fn clear() -> Result<(), Box<dyn Error>> {
+    let Some(path) = path() else { return Ok(()) };
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
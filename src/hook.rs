@@ -0,0 +1,48 @@
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use tracing::{debug, warn};
+
+use ruler::output::Report;
+
+use crate::backend::unblock_shutdown_signals;
+
+// Not RULER_, so a ruler started by the hook does not take these for settings.
+const PREFIX: &str = "MEASURE_";
+
+/// Runs `command` with `sh -c` for `--on-measure`, passing the measurement in
+/// `MEASURE_*` variables. The command runs in the background, the ruler does
+/// not wait for it.
+pub fn run(command: &str, report: &Report, text: &str) {
+    let Report { from, to, length, angle } = report;
+    let vars = [
+        ("FROM_X", from.x.to_string()),
+        ("FROM_Y", from.y.to_string()),
+        ("TO_X", to.x.to_string()),
+        ("TO_Y", to.y.to_string()),
+        ("PX", format!("{:.2}", length.px)),
+        ("MM", format!("{:.2}", length.mm)),
+        ("CM", format!("{:.3}", length.cm)),
+        ("IN", format!("{:.3}", length.inches)),
+        ("ANGLE", format!("{:.2}", angle)),
+        ("TEXT", text.to_string()),
+    ];
+    let mut child = Command::new("sh");
+    child.arg("-c").arg(command).envs(vars.iter().map(|(name, value)| (format!("{}{}", PREFIX, name), value))).stdin(Stdio::null());
+    // The ruler reads its shutdown signals from a signalfd, but the command should be interruptible as usual.
+    unsafe { child.pre_exec(unblock_shutdown_signals) };
+    let child = child.spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to run the --on-measure command: {}", e);
+            return;
+        }
+    };
+    thread::spawn(move || match child.wait() {
+        Ok(status) if status.success() => debug!("--on-measure command finished"),
+        Ok(status) => warn!("The --on-measure command failed with {}", status),
+        Err(e) => warn!("Failed to wait for the --on-measure command: {}", e),
+    });
+}
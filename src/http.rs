@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tracing::{debug, info, warn};
+
+use ruler::output::{Report, StreamFormat};
+
+/// Serves the current measurement as JSON on a localhost port, for tools
+/// that can poll over HTTP but not speak the control socket. Only `GET` is
+/// answered, with the same JSON as `--stream json`.
+pub struct Endpoint {
+    state: Arc<Mutex<String>>,
+}
+
+impl Endpoint {
+    pub fn serve(port: u16) -> Result<Endpoint, Box<dyn Error>> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        info!(address = %listener.local_addr()?, "serving the measurement over HTTP");
+        let state = Arc::new(Mutex::new(String::new()));
+
+        let shared = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let state = shared.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = respond(stream, &state) {
+                                debug!("HTTP connection closed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept an HTTP connection: {}", e),
+                }
+            }
+        });
+
+        Ok(Endpoint { state })
+    }
+
+    pub fn update(&self, report: &Report) {
+        if let Ok(mut state) = self.state.lock() {
+            *state = report.line(StreamFormat::Json);
+        }
+    }
+}
+
+fn respond(stream: TcpStream, state: &Mutex<String>) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are of no interest, but are read so closing the connection
+    // does not reset it before the client has the response.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut words = request_line.split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/" | "/measurement")) => match state.lock().map(|state| state.clone()) {
+            Ok(body) if !body.is_empty() => ("200 OK", body),
+            _ => ("503 Service Unavailable", r#"{"error":"no measurement yet"}"#.to_string()),
+        },
+        (Some("GET"), _) => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+        _ => ("405 Method Not Allowed", r#"{"error":"only GET is supported"}"#.to_string()),
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::error::Error;
+
+use cairo::{FontSlant, FontWeight, Operator};
+use glam::DVec2;
+use xcb::x::{ConfigWindow, ConfigureWindow, MapWindow, StackMode, UnmapWindow, Window};
+
+use ruler::geom::project_onto_line;
+use ruler::state::RulerState;
+use ruler::unit::Unit;
+
+use crate::backend::{Render, XCBObjects};
+use crate::config::Settings;
+
+/// Gap between the pointer and the label, clear of the cursor's arrow.
+const OFFSET: f64 = 16.0;
+const PADDING: f64 = 6.0;
+
+/// A label beside the pointer giving how far it is from the ruler's line,
+/// in a window of its own that the pointer passes through.
+pub struct Hud {
+    pub window: Window,
+    pub enabled: bool,
+    /// The text shown and the window's size for it, while it is shown.
+    shown: Option<(String, (u16, u16))>,
+}
+
+impl Hud {
+    pub fn create(xcb: &XCBObjects) -> Result<Hud, Box<dyn Error>> {
+        let window = xcb.create_overlay(1, 1)?;
+        Ok(Hud { window, enabled: false, shown: None })
+    }
+
+    /// Shows the distance from `pointer` to the line through the ruler,
+    /// across it, beside the pointer on whichever side stays on the screen.
+    pub fn follow(&mut self, xcb: &XCBObjects, render: &Render, pointer: DVec2, state: &RulerState, px_per_mm: DVec2, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        let unit = state.unit_or(settings.unit);
+        let text = distance_text(pointer, state, unit, px_per_mm);
+        let ctx = &render.ctx;
+        ctx.select_font_face(&settings.theme.font, FontSlant::Normal, FontWeight::Normal);
+        ctx.set_font_size(settings.theme.font_size * 0.75);
+        let extents = ctx.text_extents(&text)?;
+        let size = ((extents.width() + PADDING * 2.0).ceil() as u16, (settings.theme.font_size * 0.75 + PADDING * 2.0).ceil() as u16);
+
+        let (width, height) = (size.0 as f64, size.1 as f64);
+        let x = if pointer.x + OFFSET + width <= state.screen_size.x { pointer.x + OFFSET } else { pointer.x - OFFSET - width };
+        let y = if pointer.y + OFFSET + height <= state.screen_size.y { pointer.y + OFFSET } else { pointer.y - OFFSET - height };
+        let mut value_list = vec![ConfigWindow::X(x as i32), ConfigWindow::Y(y as i32)];
+        let resized = self.shown.as_ref().is_none_or(|(_, shown)| *shown != size);
+        if resized {
+            render.resize(size.0 as i32, size.1 as i32)?;
+            value_list.extend([ConfigWindow::Width(size.0 as u32), ConfigWindow::Height(size.1 as u32)]);
+        }
+        value_list.push(ConfigWindow::StackMode(StackMode::Above));
+        xcb.conn.send_request(&ConfigureWindow { window: self.window, value_list: &value_list });
+        if self.shown.is_none() {
+            xcb.conn.send_request(&MapWindow { window: self.window });
+        }
+
+        self.shown = Some((text, size));
+        self.paint(render, settings)
+    }
+
+    pub fn hide(&mut self, xcb: &XCBObjects) {
+        if self.shown.take().is_some() {
+            xcb.conn.send_request(&UnmapWindow { window: self.window });
+        }
+    }
+
+    pub fn paint(&self, render: &Render, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        let Some((text, (width, height))) = &self.shown else { return Ok(()) };
+        let (width, height) = (*width as f64, *height as f64);
+        let theme = &settings.theme;
+        let ctx = &render.ctx;
+
+        ctx.save()?;
+        ctx.set_operator(Operator::Source);
+        ctx.set_source_rgba(theme.background.r, theme.background.g, theme.background.b, theme.opacity);
+        ctx.paint()?;
+        ctx.set_source_rgba(theme.accent.r, theme.accent.g, theme.accent.b, theme.opacity);
+        ctx.set_line_width(1.0);
+        ctx.rectangle(0.5, 0.5, width - 1.0, height - 1.0);
+        ctx.stroke()?;
+
+        ctx.select_font_face(&theme.font, FontSlant::Normal, FontWeight::Normal);
+        ctx.set_font_size(theme.font_size * 0.75);
+        let extents = ctx.text_extents(text)?;
+        ctx.move_to(PADDING - extents.x_bearing(), (height + extents.height()) / 2.0 - (extents.height() + extents.y_bearing()));
+        ctx.text_path(text);
+        ctx.fill()?;
+        ctx.restore()?;
+        Ok(())
+    }
+}
+
+/// How far `point` is from the line through the ruler, measured along the
+/// perpendicular so millimeters follow the screen's density that way.
+pub fn distance_text(point: DVec2, state: &RulerState, unit: Unit, px_per_mm: DVec2) -> String {
+    let across = point - project_onto_line(point, state.from, state.to - state.from);
+    unit.format(across.length() / unit.px_per_unit(across, px_per_mm))
+}
@@ -0,0 +1,153 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use xcb::Connection;
+use xcb::x::{Atom, ClientMessageData, ClientMessageEvent, CURRENT_TIME, EventMask, GetSelectionOwner, SendEvent, SendEventDest, SetSelectionOwner, Window};
+use xcb::Xid;
+
+use crate::backend::{checked, round_trip};
+use crate::config::{Anchor, Orientation};
+
+const PLACE: u32 = 1;
+const TOGGLE: u32 = 2;
+const QUIT: u32 = 3;
+const SET: u32 = 4;
+
+/// Bits of the second word saying which of the optional values that follow
+/// are there, so any value, NaN included, can be told apart from none.
+const FIRST: u32 = 1;
+const SECOND: u32 = 2;
+
+/// A request sent from a new invocation to the running instance. These
+/// travel in a single client message, so they are limited to five words.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Command {
+    Place { orientation: Option<Orientation>, anchor: Option<Anchor> },
+    /// Length in pixels and counterclockwise angle in degrees, either of which may be left as is.
+    Set { length: Option<f64>, angle: Option<f64> },
+    Toggle,
+    Quit,
+}
+
+impl Command {
+    fn encode(self) -> [u32; 5] {
+        match self {
+            Command::Place { orientation, anchor } => {
+                let degrees = orientation.map_or(0.0, |o| o.degrees() as f32);
+                let anchor = match anchor {
+                    None => 0,
+                    Some(Anchor::Center) => 1,
+                    Some(Anchor::Cursor) => 2,
+                };
+                [PLACE, present(orientation.is_some(), false), degrees.to_bits(), anchor, 0]
+            }
+            Command::Set { length, angle } => {
+                let present = present(length.is_some(), angle.is_some());
+                let length = length.map_or(0.0, |length| length as f32);
+                let angle = angle.map_or(0.0, |angle| angle as f32);
+                [SET, present, length.to_bits(), angle.to_bits(), 0]
+            }
+            Command::Toggle => [TOGGLE, 0, 0, 0, 0],
+            Command::Quit => [QUIT, 0, 0, 0, 0],
+        }
+    }
+
+    pub fn decode(data: [u32; 5]) -> Option<Command> {
+        let value = |present: u32, bit: u32, bits: u32| (present & bit != 0).then(|| f32::from_bits(bits) as f64);
+        match data {
+            [PLACE, present, degrees, anchor, _] => {
+                let orientation = value(present, FIRST, degrees).map(Orientation::Angle);
+                let anchor = match anchor {
+                    1 => Some(Anchor::Center),
+                    2 => Some(Anchor::Cursor),
+                    _ => None,
+                };
+                Some(Command::Place { orientation, anchor })
+            }
+            [SET, present, length, angle, _] => Some(Command::Set { length: value(present, FIRST, length), angle: value(present, SECOND, angle) }),
+            [TOGGLE, ..] => Some(Command::Toggle),
+            [QUIT, ..] => Some(Command::Quit),
+            _ => None,
+        }
+    }
+}
+
+fn present(first: bool, second: bool) -> u32 {
+    (if first { FIRST } else { 0 }) | (if second { SECOND } else { 0 })
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct NoInstanceError;
+
+impl Display for NoInstanceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No running ruler instance found")
+    }
+}
+
+impl Error for NoInstanceError {}
+
+/// Returns the window of the instance owning `selection`, if any.
+pub fn running(conn: &Connection, selection: Atom) -> Result<Option<Window>, Box<dyn Error>> {
+    let owner = round_trip(conn, &GetSelectionOwner { selection })?.owner();
+    Ok((!owner.is_none()).then_some(owner))
+}
+
+/// Registers `window` as the running instance. The selection is released by
+/// the server when the process exits, however it exits.
+pub fn claim(conn: &Connection, selection: Atom, window: Window) -> Result<(), Box<dyn Error>> {
+    checked(conn, &SetSelectionOwner { owner: window, selection, time: CURRENT_TIME })?;
+    Ok(())
+}
+
+pub fn forward(conn: &Connection, owner: Window, message_type: Atom, command: Command) -> Result<(), Box<dyn Error>> {
+    checked(conn, &SendEvent {
+        propagate: false,
+        destination: SendEventDest::Window(owner),
+        event_mask: EventMask::NO_EVENT,
+        event: &ClientMessageEvent::new(owner, message_type, ClientMessageData::Data32(command.encode())),
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(command: Command) -> Option<Command> {
+        Command::decode(command.encode())
+    }
+
+    #[test]
+    fn set_fields_survive_the_trip() {
+        let place = Command::Place { orientation: Some(Orientation::Angle(-22.5)), anchor: Some(Anchor::Cursor) };
+        assert_eq!(round_trip(place), Some(place));
+        let set = Command::Set { length: Some(250.5), angle: Some(90.0) };
+        assert_eq!(round_trip(set), Some(set));
+        assert_eq!(round_trip(Command::Toggle), Some(Command::Toggle));
+        assert_eq!(round_trip(Command::Quit), Some(Command::Quit));
+    }
+
+    #[test]
+    fn unset_fields_survive_the_trip() {
+        let place = Command::Place { orientation: None, anchor: None };
+        assert_eq!(round_trip(place), Some(place));
+        for set in [Command::Set { length: None, angle: None }, Command::Set { length: Some(0.0), angle: None }, Command::Set { length: None, angle: Some(0.0) }] {
+            assert_eq!(round_trip(set), Some(set));
+        }
+    }
+
+    #[test]
+    fn nan_is_not_taken_for_unset() {
+        let Some(Command::Set { length: Some(length), angle: None }) = round_trip(Command::Set { length: Some(f64::NAN), angle: None }) else { panic!() };
+        assert!(length.is_nan());
+        let Some(Command::Place { orientation: Some(orientation), .. }) = round_trip(Command::Place { orientation: Some(Orientation::Angle(f64::NAN)), anchor: None }) else { panic!() };
+        assert!(orientation.degrees().is_nan());
+    }
+
+    #[test]
+    fn unknown_messages_are_ignored() {
+        assert_eq!(Command::decode([0, 0, 0, 0, 0]), None);
+        assert_eq!(Command::decode([99, 1, 2, 3, 4]), None);
+    }
+}
@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use tracing::{debug, warn};
+
+use crate::backend::Waker;
+use crate::control::Message;
+use crate::xdg;
+
+const SOCKET_FILE: &str = "ruler.sock";
+
+/// Listens on a socket in `$XDG_RUNTIME_DIR` and hands incoming requests to
+/// the main loop. The socket is removed again when the server is dropped.
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Server {
+    /// Starts listening, unless there is no runtime directory. Only the
+    /// registered instance listens, so a socket left behind is stale.
+    pub fn listen(waker: Waker, sender: Sender<Message>) -> Result<Option<Server>, Box<dyn Error>> {
+        let Some(path) = xdg::runtime_dir().map(|dir| dir.join(SOCKET_FILE)) else { return Ok(None) };
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let (sender, waker) = (sender.clone(), waker.clone());
+                        thread::spawn(move || {
+                            if let Err(e) = serve(stream, sender, waker) {
+                                debug!("Control connection closed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept a control connection: {}", e),
+                }
+            }
+        });
+
+        Ok(Some(Server { path }))
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn serve(stream: UnixStream, sender: Sender<Message>, waker: Waker) -> Result<(), Box<dyn Error>> {
+    let (reply, replies) = mpsc::channel::<String>();
+    let mut writer = stream.try_clone()?;
+    thread::spawn(move || {
+        for line in replies {
+            if writeln!(writer, "{}", line).is_err() {
+                break;
+            }
+        }
+    });
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.parse() {
+            Ok(request) => {
+                sender.send(Message { request, reply: reply.clone() })?;
+                waker.wake()?;
+            }
+            Err(e) => reply.send(format!("error {}", e))?,
+        }
+    }
+    Ok(())
+}
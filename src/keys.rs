@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use xcb::Connection;
+use xcb::x::{GetKeyboardMapping, KeyButMask, Keycode, Keysym, ModMask};
+
+use crate::backend::round_trip;
+use crate::suggest;
+
+const KEYSYMS: &[(&str, Keysym)] = &[
+    ("space", 0x0020),
+    ("apostrophe", 0x0027),
+    ("plus", 0x002b),
+    ("comma", 0x002c),
+    ("minus", 0x002d),
+    ("period", 0x002e),
+    ("slash", 0x002f),
+    ("semicolon", 0x003b),
+    ("equal", 0x003d),
+    ("question", 0x003f),
+    ("bracketleft", 0x005b),
+    ("backslash", 0x005c),
+    ("bracketright", 0x005d),
+    ("grave", 0x0060),
+    ("BackSpace", 0xff08),
+    ("Tab", 0xff09),
+    ("Return", 0xff0d),
+    ("Escape", 0xff1b),
+    ("Home", 0xff50),
+    ("Left", 0xff51),
+    ("Up", 0xff52),
+    ("Right", 0xff53),
+    ("Down", 0xff54),
+    ("Page_Up", 0xff55),
+    ("Page_Down", 0xff56),
+    ("End", 0xff57),
+    ("Insert", 0xff63),
+    ("KP_Add", 0xffab),
+    ("KP_Subtract", 0xffad),
+    ("F1", 0xffbe),
+    ("F2", 0xffbf),
+    ("F3", 0xffc0),
+    ("F4", 0xffc1),
+    ("F5", 0xffc2),
+    ("F6", 0xffc3),
+    ("F7", 0xffc4),
+    ("F8", 0xffc5),
+    ("F9", 0xffc6),
+    ("F10", 0xffc7),
+    ("F11", 0xffc8),
+    ("F12", 0xffc9),
+    ("Delete", 0xffff),
+];
+
+const MODIFIERS: &[(&str, KeyButMask)] = &[
+    ("shift", KeyButMask::SHIFT),
+    ("ctrl", KeyButMask::CONTROL),
+    ("control", KeyButMask::CONTROL),
+    ("alt", KeyButMask::MOD1),
+    ("mod1", KeyButMask::MOD1),
+    ("super", KeyButMask::MOD4),
+    ("mod4", KeyButMask::MOD4),
+    ("altgr", KeyButMask::MOD5),
+    ("mod5", KeyButMask::MOD5),
+];
+
+const MODIFIER_MASK: KeyButMask = KeyButMask::SHIFT.union(KeyButMask::CONTROL).union(KeyButMask::MOD1).union(KeyButMask::MOD4).union(KeyButMask::MOD5);
+
+/// Caps Lock and Num Lock in every combination, which bindings ignore but
+/// grabs tell apart.
+const LOCKS: [KeyButMask; 4] = [KeyButMask::empty(), KeyButMask::LOCK, KeyButMask::MOD2, KeyButMask::LOCK.union(KeyButMask::MOD2)];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    CancelDrag,
+    NextProfile,
+    ToggleFixDistance,
+    ToggleFixAngle,
+    ToggleClamp,
+    Copy,
+    Paste,
+    Screenshot,
+    ExportSvg,
+    ToggleFrameStats,
+    ToggleLoupe,
+    ToggleGuides,
+    ToggleGrid,
+    ToggleConstruction,
+    ToggleBaseline,
+    BaselineTaller,
+    BaselineShorter,
+    ToggleSampler,
+    CopyColor,
+    ToggleLuminance,
+    MeasureWindow,
+    ToggleFollowFocus,
+    MeasureGap,
+    Calibrate,
+    ToggleZoomStrip,
+    ToggleCursorDistance,
+    DropMarker,
+    NewRuler,
+    StoreReference,
+    StoreReferenceAngle,
+    ToggleSettings,
+    FlipTicks,
+    TogglePrecise,
+    NudgeLeft,
+    NudgeRight,
+    NudgeUp,
+    NudgeDown,
+    ToggleSymmetric,
+    Recenter,
+    Recall,
+}
+
+pub type Keymap = BTreeMap<Action, Vec<KeyBinding>>;
+
+pub fn default_keymap() -> Keymap {
+    let binding = |s: &str| s.parse::<KeyBinding>().unwrap();
+    BTreeMap::from([
+        (Action::Quit, vec![binding("q")]),
+        (Action::CancelDrag, vec![binding("Escape")]),
+        (Action::NextProfile, vec![binding("p")]),
+        (Action::ToggleFixDistance, vec![binding("d")]),
+        (Action::ToggleFixAngle, vec![binding("a")]),
+        (Action::ToggleSymmetric, vec![binding("e")]),
+        (Action::ToggleClamp, vec![binding("c")]),
+        (Action::Copy, vec![binding("ctrl+c")]),
+        (Action::Paste, vec![binding("ctrl+v")]),
+        (Action::Screenshot, vec![binding("ctrl+s")]),
+        (Action::ExportSvg, vec![binding("ctrl+e")]),
+        (Action::ToggleFrameStats, vec![binding("F12")]),
+        (Action::ToggleLoupe, vec![binding("m")]),
+        (Action::ToggleGuides, vec![binding("g")]),
+        (Action::ToggleGrid, vec![binding("shift+g")]),
+        (Action::ToggleConstruction, vec![binding("t")]),
+        (Action::ToggleBaseline, vec![binding("b")]),
+        (Action::BaselineTaller, vec![binding("bracketright")]),
+        (Action::BaselineShorter, vec![binding("bracketleft")]),
+        (Action::ToggleSampler, vec![binding("o")]),
+        (Action::CopyColor, vec![binding("ctrl+shift+c")]),
+        (Action::ToggleLuminance, vec![binding("l")]),
+        (Action::MeasureWindow, vec![binding("w")]),
+        (Action::ToggleFollowFocus, vec![binding("f")]),
+        (Action::MeasureGap, vec![binding("shift+w")]),
+        (Action::Calibrate, vec![binding("k")]),
+        (Action::ToggleZoomStrip, vec![binding("z")]),
+        (Action::ToggleCursorDistance, vec![binding("h")]),
+        (Action::DropMarker, vec![binding("x")]),
+        (Action::NewRuler, vec![binding("n")]),
+        (Action::StoreReference, vec![binding("r")]),
+        (Action::StoreReferenceAngle, vec![binding("shift+r")]),
+        (Action::ToggleSettings, vec![binding("s")]),
+        (Action::FlipTicks, vec![binding("shift+t")]),
+        (Action::TogglePrecise, vec![binding("period")]),
+        (Action::NudgeLeft, vec![binding("Left")]),
+        (Action::NudgeRight, vec![binding("Right")]),
+        (Action::NudgeUp, vec![binding("Up")]),
+        (Action::NudgeDown, vec![binding("Down")]),
+        (Action::Recenter, vec![binding("Home")]),
+        (Action::Recall, vec![binding("ctrl+alt+r")]),
+    ])
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidBindingError {
+    binding: String,
+    part: String,
+}
+
+impl InvalidBindingError {
+    fn new(binding: &str, part: &str) -> InvalidBindingError {
+        InvalidBindingError { binding: binding.to_string(), part: part.to_string() }
+    }
+}
+
+impl Display for InvalidBindingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown key or modifier '{}' in binding '{}'", self.part, self.binding)?;
+        let names = KEYSYMS.iter().map(|&(name, _)| name).chain(MODIFIERS.iter().map(|&(name, _)| name));
+        if let Some(suggestion) = suggest::closest(&self.part, names) {
+            write!(f, ", did you mean '{}'?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidBindingError {}
+
+/// A combination of modifier keys such as `ctrl+alt`. The empty combination is
+/// never considered held.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Modifiers(KeyButMask);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(KeyButMask::empty());
+    pub const SHIFT: Modifiers = Modifiers(KeyButMask::SHIFT);
+    pub const CONTROL: Modifiers = Modifiers(KeyButMask::CONTROL);
+    pub const ALT: Modifiers = Modifiers(KeyButMask::MOD1);
+    pub const SUPER: Modifiers = Modifiers(KeyButMask::MOD4);
+    /// Mod5, which is AltGr in most layouts that have one.
+    pub const ALTGR: Modifiers = Modifiers(KeyButMask::MOD5);
+
+    pub fn held(self, state: KeyButMask) -> bool {
+        !self.0.is_empty() && state.contains(self.0)
+    }
+
+    fn parse(names: &str) -> Result<Modifiers, &str> {
+        let mut mask = KeyButMask::empty();
+        for name in names.split('+').filter(|name| !name.is_empty()) {
+            let (_, modifier) = MODIFIERS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).ok_or(name)?;
+            mask |= *modifier;
+        }
+        Ok(Modifiers(mask))
+    }
+}
+
+impl FromStr for Modifiers {
+    type Err = InvalidBindingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Modifiers::parse(s).map_err(|part| InvalidBindingError::new(s, part))
+    }
+}
+
+impl Display for Modifiers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = [("shift", KeyButMask::SHIFT), ("ctrl", KeyButMask::CONTROL), ("alt", KeyButMask::MOD1), ("super", KeyButMask::MOD4), ("altgr", KeyButMask::MOD5)]
+            .into_iter()
+            .filter(|&(_, mask)| self.0.contains(mask))
+            .map(|(name, _)| name)
+            .collect();
+        f.write_str(&names.join("+"))
+    }
+}
+
+impl Serialize for Modifiers {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Modifiers {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    modifiers: Modifiers,
+    keysym: Keysym,
+}
+
+fn parse_keysym(name: &str) -> Option<Keysym> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_graphic() {
+            return Some(c.to_ascii_lowercase() as Keysym);
+        }
+    }
+    if let Some(hex) = name.strip_prefix("0x") {
+        return Keysym::from_str_radix(hex, 16).ok();
+    }
+    KEYSYMS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|&(_, keysym)| keysym)
+}
+
+fn keysym_name(keysym: Keysym) -> String {
+    match KEYSYMS.iter().find(|&&(_, k)| k == keysym) {
+        Some((name, _)) => name.to_string(),
+        None => match char::from_u32(keysym) {
+            Some(c) if c.is_ascii_graphic() => c.to_string(),
+            _ => format!("0x{:x}", keysym),
+        },
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = InvalidBindingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |part: &str| InvalidBindingError::new(s, part);
+
+        let (modifier_names, key) = match s.rsplit_once('+') {
+            Some((mods, "")) => (mods.strip_suffix('+').unwrap_or(mods), "plus"),
+            Some((mods, key)) => (mods, key),
+            None => ("", s),
+        };
+
+        let modifiers = Modifiers::parse(modifier_names).map_err(error)?;
+        let keysym = parse_keysym(key).ok_or_else(|| error(key))?;
+        Ok(KeyBinding { modifiers, keysym })
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.modifiers.0.is_empty() {
+            write!(f, "{}+", self.modifiers)?;
+        }
+        write!(f, "{}", keysym_name(self.keysym))
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+pub struct Keyboard {
+    min_keycode: Keycode,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<Keysym>,
+}
+
+impl Keyboard {
+    pub fn load(conn: &Connection) -> Result<Keyboard, Box<dyn Error>> {
+        let setup = conn.get_setup();
+        let reply = round_trip(conn, &GetKeyboardMapping {
+            first_keycode: setup.min_keycode(),
+            count: setup.max_keycode() - setup.min_keycode() + 1,
+        })?;
+        Ok(Keyboard {
+            min_keycode: setup.min_keycode(),
+            keysyms_per_keycode: reply.keysyms_per_keycode() as usize,
+            keysyms: reply.keysyms().to_vec(),
+        })
+    }
+
+    /// The keysym `keycode` types, from the shifted column if `shifted`.
+    /// Keys with nothing there, or the same, have no shifted keysym.
+    fn keysym(&self, keycode: Keycode, shifted: bool) -> Option<Keysym> {
+        let index = keycode.checked_sub(self.min_keycode)? as usize * self.keysyms_per_keycode;
+        if !shifted {
+            return self.keysyms.get(index).copied();
+        }
+        let keysym = *self.keysyms.get(index + 1).filter(|_| self.keysyms_per_keycode > 1)?;
+        (keysym != 0 && Some(&keysym) != self.keysyms.get(index)).then_some(keysym)
+    }
+
+    /// The keys and modifiers to grab for `bindings` to be pressed from
+    /// anywhere, whatever the lock keys. Shifted keysyms such as `plus`
+    /// are grabbed with Shift on the key that types them.
+    pub fn grabs(&self, bindings: &[KeyBinding]) -> Vec<(Keycode, ModMask)> {
+        let keys = (self.min_keycode..=Keycode::MAX).flat_map(|keycode| {
+            let plain = self.keysym(keycode, false).map(|keysym| (keycode, keysym, Modifiers::NONE));
+            let shifted = self.keysym(keycode, true).map(|keysym| (keycode, keysym, Modifiers::SHIFT));
+            plain.into_iter().chain(shifted)
+        });
+        keys.flat_map(|(keycode, keysym, shift)| bindings.iter().filter(move |binding| binding.keysym == keysym).map(move |binding| (keycode, binding.modifiers.0 | shift.0)))
+            .flat_map(|(keycode, modifiers)| LOCKS.map(|lock| (keycode, ModMask::from_bits_truncate((modifiers | lock).bits()))))
+            .collect()
+    }
+
+    /// The action bound to `keycode` pressed with `state`. With Shift held,
+    /// a binding of the key's own keysym with `shift+` comes first, then one
+    /// of the keysym Shift turns it into, such as `plus`, without.
+    pub fn action(&self, keymap: &Keymap, keycode: Keycode, state: KeyButMask) -> Option<Action> {
+        let modifiers = Modifiers(state & MODIFIER_MASK);
+        let bound = |keysym: Keysym, modifiers: Modifiers| {
+            keymap.iter()
+                .find(|(_, bindings)| bindings.iter().any(|b| b.keysym == keysym && b.modifiers == modifiers))
+                .map(|(&action, _)| action)
+        };
+        let plain = self.keysym(keycode, false).and_then(|keysym| bound(keysym, modifiers));
+        let shifted = || {
+            let keysym = self.keysym(keycode, true).filter(|_| state.contains(KeyButMask::SHIFT))?;
+            bound(keysym, Modifiers(modifiers.0 - KeyButMask::SHIFT))
+        };
+        plain.or_else(shifted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(s: &str) -> KeyBinding {
+        s.parse().unwrap()
+    }
+
+    /// Keycodes 8 to 11 typing `=`/`+`, `/`/`?`, `g`/`G` and Escape, as on a
+    /// US layout.
+    fn keyboard() -> Keyboard {
+        Keyboard { min_keycode: 8, keysyms_per_keycode: 2, keysyms: vec![0x3d, 0x2b, 0x2f, 0x3f, 0x67, 0x47, 0xff1b, 0] }
+    }
+
+    #[test]
+    fn bindings_display_as_they_parse() {
+        for s in ["q", "ctrl+c", "shift+ctrl+c", "alt+super+F1", "altgr+Left", "ctrl+plus", "question", "0x1008ff13"] {
+            assert_eq!(binding(s).to_string(), s);
+            assert_eq!(binding(&binding(s).to_string()), binding(s));
+        }
+    }
+
+    #[test]
+    fn bindings_parse_loosely() {
+        assert_eq!(binding("Ctrl+Shift+Q"), binding("shift+ctrl+q"));
+        assert_eq!(binding("control+mod1+mod4+mod5+escape"), binding("ctrl+alt+super+altgr+Escape"));
+        assert_eq!(binding("ctrl++"), binding("ctrl+plus"));
+        assert_eq!(binding("+"), binding("plus"));
+        assert_eq!(binding("?"), binding("question"));
+        assert_eq!(binding("ctrl+C").to_string(), "ctrl+c");
+    }
+
+    #[test]
+    fn refuses_unknown_modifiers_and_keys() {
+        for (s, part) in [("hyper+q", "hyper"), ("ctrl+nokey", "nokey"), ("", ""), ("ctrl+é", "é")] {
+            let error = s.parse::<KeyBinding>().unwrap_err();
+            assert_eq!(error.part, part, "{}", s);
+        }
+        let error = "ctl+q".parse::<KeyBinding>().unwrap_err();
+        assert_eq!(error.to_string(), "Unknown key or modifier 'ctl' in binding 'ctl+q', did you mean 'ctrl'?");
+        assert!("hyper".parse::<Modifiers>().is_err());
+        assert_eq!("".parse::<Modifiers>().unwrap(), Modifiers::NONE);
+    }
+
+    #[test]
+    fn modifiers_display_as_they_parse() {
+        for s in ["", "shift", "ctrl+alt", "shift+ctrl+alt+super+altgr"] {
+            assert_eq!(s.parse::<Modifiers>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn shifted_keysyms_match_with_shift_held() {
+        let keymap = Keymap::from([(Action::NudgeUp, vec![binding("plus")]), (Action::NudgeDown, vec![binding("equal")]), (Action::ToggleSettings, vec![binding("question")]), (Action::ToggleGrid, vec![binding("shift+g")]), (Action::ToggleGuides, vec![binding("g")])]);
+        let keyboard = keyboard();
+        assert_eq!(keyboard.action(&keymap, 8, KeyButMask::empty()), Some(Action::NudgeDown));
+        assert_eq!(keyboard.action(&keymap, 8, KeyButMask::SHIFT), Some(Action::NudgeUp));
+        assert_eq!(keyboard.action(&keymap, 9, KeyButMask::SHIFT | KeyButMask::LOCK), Some(Action::ToggleSettings));
+        assert_eq!(keyboard.action(&keymap, 9, KeyButMask::empty()), None);
+        assert_eq!(keyboard.action(&keymap, 10, KeyButMask::empty()), Some(Action::ToggleGuides));
+        assert_eq!(keyboard.action(&keymap, 10, KeyButMask::SHIFT), Some(Action::ToggleGrid));
+        // A shifted keysym bound with other modifiers needs those too.
+        assert_eq!(keyboard.action(&keymap, 8, KeyButMask::SHIFT | KeyButMask::CONTROL), None);
+    }
+
+    #[test]
+    fn shifted_keysyms_are_grabbed_with_shift() {
+        let grabs = keyboard().grabs(&[binding("ctrl+plus"), binding("Escape")]);
+        let plus = ModMask::SHIFT | ModMask::CONTROL;
+        assert_eq!(grabs[..4], LOCKS.map(|lock| (8, plus | ModMask::from_bits_truncate(lock.bits()))));
+        assert_eq!(grabs[4..], LOCKS.map(|lock| (11, ModMask::from_bits_truncate(lock.bits()))));
+    }
+}
@@ -0,0 +1,12 @@
+//! The measurement math and layout behind the `ruler` binary, free of any
+//! windowing, for tools that want to measure and draw the same way.
+
+pub mod color;
+pub mod edges;
+pub mod format;
+pub mod geom;
+pub mod measurement;
+pub mod output;
+pub mod scale;
+pub mod state;
+pub mod unit;
@@ -0,0 +1,27 @@
+use std::io::{self, IsTerminal};
+
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Sends diagnostics to stderr, one JSON object per event with `json`.
+/// Warnings are always shown, `-v` adds debug output such as drag transitions
+/// and `-vv` traces X round trips, each closing with its duration, and
+/// render timings.
+pub fn init(verbosity: u8, json: bool) {
+    let level = match verbosity {
+        0 => Level::WARN,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .with_target(false)
+        .with_span_events(FmtSpan::CLOSE)
+        .without_time();
+    if json {
+        builder.json().init();
+    } else {
+        builder.with_ansi(io::stderr().is_terminal()).init();
+    }
+}
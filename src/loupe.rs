@@ -0,0 +1,138 @@
+use std::error::Error;
+
+use cairo::{Context, Filter, ImageSurface, Operator, SurfacePattern};
+use glam::DVec2;
+use xcb::x::{ConfigWindow, ConfigureWindow, MapWindow, StackMode, UnmapWindow, Window};
+
+use crate::backend::{coordinate, Render, WindowGeometry, XCBObjects};
+use crate::capture;
+use crate::config::{Color, Settings};
+
+/// Pixels shown either side of the endpoint's own.
+pub const REACH: i16 = 7;
+/// Gap between the dragged handle and the loupe.
+const GAP: f64 = 12.0;
+
+/// A window next to the dragged endpoint showing the screen around it
+/// magnified, captured anew on every frame of the drag.
+pub struct LoupeWindow {
+    pub window: Window,
+    zoom: u16,
+    /// The last capture and where the endpoint's pixel is in it, to paint
+    /// again on Expose.
+    capture: Option<(ImageSurface, (i16, i16))>,
+}
+
+impl LoupeWindow {
+    pub fn create(xcb: &XCBObjects, zoom: u16) -> Result<LoupeWindow, Box<dyn Error>> {
+        let window = xcb.create_overlay(side(zoom), side(zoom))?;
+        Ok(LoupeWindow { window, zoom, capture: None })
+    }
+
+    pub fn side(&self) -> u16 {
+        side(self.zoom)
+    }
+
+    pub fn shown(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Captures the screen around `point` and shows it beside the handle
+    /// there, on whichever side stays on the screen.
+    pub fn follow(&mut self, xcb: &XCBObjects, render: &Render, point: DVec2, screen_size: DVec2, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        let pixel = (coordinate(point.x.floor(), "loupe x"), coordinate(point.y.floor(), "loupe y"));
+        let reach = 2 * REACH + 1;
+        let area = WindowGeometry {
+            x: (pixel.0 - REACH).clamp(0, (screen_size.x as i16 - reach).max(0)),
+            y: (pixel.1 - REACH).clamp(0, (screen_size.y as i16 - reach).max(0)),
+            w: reach as u16,
+            h: reach as u16,
+        };
+        let image = capture::capture(xcb, area)?;
+
+        let resized = settings.loupe.zoom() != self.zoom;
+        if resized {
+            self.zoom = settings.loupe.zoom();
+            render.resize(self.side() as i32, self.side() as i32)?;
+        }
+
+        let side = self.side() as f64;
+        let gap = settings.sizes.control_radius + GAP;
+        let x = if point.x + gap + side <= screen_size.x { point.x + gap } else { point.x - gap - side };
+        let y = if point.y - gap - side >= 0.0 { point.y - gap - side } else { point.y + gap };
+        let mut value_list = vec![ConfigWindow::X(x as i32), ConfigWindow::Y(y as i32)];
+        if resized {
+            value_list.extend([ConfigWindow::Width(self.side() as u32), ConfigWindow::Height(self.side() as u32)]);
+        }
+        value_list.push(ConfigWindow::StackMode(StackMode::Above));
+        xcb.conn.send_request(&ConfigureWindow { window: self.window, value_list: &value_list });
+        if !self.shown() {
+            xcb.conn.send_request(&MapWindow { window: self.window });
+        }
+
+        self.capture = Some((image, (pixel.0 - area.x, pixel.1 - area.y)));
+        self.paint(render, settings.theme.accent)
+    }
+
+    pub fn hide(&mut self, xcb: &XCBObjects) {
+        if self.capture.take().is_some() {
+            xcb.conn.send_request(&UnmapWindow { window: self.window });
+        }
+    }
+
+    pub fn paint(&self, render: &Render, accent: Color) -> Result<(), Box<dyn Error>> {
+        let Some((image, center)) = &self.capture else { return Ok(()) };
+        paint(&render.ctx, image, *center, self.zoom, accent)
+    }
+}
+
+fn side(zoom: u16) -> u16 {
+    (2 * REACH + 1) as u16 * zoom
+}
+
+/// Draws `image` magnified `zoom` times with a line between every pixel and
+/// a crosshair through the one at `center`.
+fn paint(ctx: &Context, image: &ImageSurface, center: (i16, i16), zoom: u16, accent: Color) -> Result<(), Box<dyn Error>> {
+    let zoom = zoom as f64;
+    let side = (2 * REACH + 1) as f64 * zoom;
+
+    ctx.save()?;
+    ctx.set_operator(Operator::Source);
+    ctx.scale(zoom, zoom);
+    let pattern = SurfacePattern::create(image);
+    // Each pixel a sharp square, rather than blurred into its neighbours.
+    pattern.set_filter(Filter::Nearest);
+    ctx.set_source(&pattern)?;
+    ctx.paint()?;
+    ctx.restore()?;
+
+    ctx.set_line_width(1.0);
+    ctx.set_source_rgba(0.0, 0.0, 0.0, 0.25);
+    for i in 1..2 * REACH + 1 {
+        let offset = i as f64 * zoom + 0.5;
+        ctx.move_to(offset, 0.0);
+        ctx.line_to(offset, side);
+        ctx.move_to(0.0, offset);
+        ctx.line_to(side, offset);
+    }
+    ctx.stroke()?;
+
+    let (x, y) = (center.0 as f64 * zoom, center.1 as f64 * zoom);
+    ctx.set_source_rgb(accent.r, accent.g, accent.b);
+    ctx.set_line_width(2.0);
+    ctx.rectangle(x, y, zoom, zoom);
+    // The arms stop short of the pixel, leaving it in plain view.
+    ctx.move_to(x + zoom / 2.0, 0.0);
+    ctx.line_to(x + zoom / 2.0, y - zoom);
+    ctx.move_to(x + zoom / 2.0, y + zoom * 2.0);
+    ctx.line_to(x + zoom / 2.0, side);
+    ctx.move_to(0.0, y + zoom / 2.0);
+    ctx.line_to(x - zoom, y + zoom / 2.0);
+    ctx.move_to(x + zoom * 2.0, y + zoom / 2.0);
+    ctx.line_to(side, y + zoom / 2.0);
+    ctx.stroke()?;
+
+    ctx.rectangle(1.0, 1.0, side - 2.0, side - 2.0);
+    ctx.stroke()?;
+    Ok(())
+}
@@ -1,536 +1,1645 @@
+#[cfg(feature = "scripting")]
 use std::rc::Rc;
 use std::error::Error;
-use std::f64::consts::PI;
-use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Sender};
 use std::time::{Duration, Instant};
 
-use cairo::{Context, Operator, XCBConnection, XCBDrawable, XCBSurface, XCBVisualType};
-use glam::{DVec2, IVec2, UVec2};
+use cairo::{Context, SvgSurface};
+use clap::Parser;
+use glam::DVec2;
+use tracing::{debug, info, trace, warn};
 use x::*;
-use xcb::{Connection, render, shape, VoidCookie, x, Xid};
-use xcb::x::Mapping::Keyboard;
-
-use crate::geom::{closest_point_below_line_on_circle};
-
-mod geom;
-
-xcb::atoms_struct! {
-    #[derive(Debug)]
-    struct Atoms {
-        wm_protocols => b"WM_PROTOCOLS",
-        wm_del_window => b"WM_DELETE_WINDOW",
-        motif_wm_hints => b"_MOTIF_WM_HINTS",
-        net_wm_state => b"_NET_WM_STATE",
-        new_wm_state_skip_pager => b"_NET_WM_STATE_SKIP_PAGER",
-        net_wm_state_above => b"_NET_WM_STATE_ABOVE",
-        net_wm_state_sticky => b"_NET_WM_STATE_STICKY",
-        net_wm_allowed_actions => b"_NET_WM_ALLOWED_ACTIONS",
-        new_wm_action_close => b"_NEW_WM_ACTION_CLOSE",
-    }
-}
-
-const RULER_HALF_WIDTH: f64 = 40.0;
-const TITLE: &str = "Ruler";
-const INITIAL_LENGTH: f64 = 400.0;
-const CONTROL_RADIUS: f64 = 20.0;
-const MIN_LENGTH: f64 = 200.0;
-
-#[derive(Debug, Copy, Clone)]
-struct VersionMismatchError {
-    client_major_version: u32,
-    client_minor_version: u32,
-    server_major_version: u32,
-    server_minor_version: u32,
-    extension_name: &'static str,
-}
-
-impl Display for VersionMismatchError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Versions of extension '{}' do not match. Server: (major {}, minor {}) Client: (major {}, minor {})", self.extension_name, self.server_major_version, self.server_minor_version, self.client_major_version, self.client_minor_version)
+use xcb::{x, xinput, Xid};
+
+use ruler::edges::Patch;
+use ruler::geom::{gap_between, line_pixels, snap_to_center_lines, snap_to_rects, Rect};
+use ruler::measurement::Measurement;
+use ruler::output::{ErrorReport, Region, Report, StreamFormat};
+use ruler::state::{Constraints, Handle, Mode, RulerState};
+use ruler::unit::Unit;
+
+use crate::backend::{checked, coordinate, extent, App, Damage, EventLoop, Output, Render, Role, Wakeup, Waker, WindowGeometry, XCBObjects};
+use crate::cli::{Args, ConfigAction, HistoryAction, Subcommand};
+use crate::bar::Bar;
+use crate::baseline::Baselines;
+use crate::clipboard::Clipboard;
+use crate::control::{Message, Request};
+use crate::dialog::{Dialog, Row};
+use crate::csvlog::CsvLog;
+use crate::draw::{draw, Gap, Strip, View};
+use crate::extra::{Extra, Extras};
+use crate::grid::Grid;
+use crate::guides::Guides;
+use crate::hud::Hud;
+#[cfg(feature = "http")]
+use crate::http::Endpoint;
+use crate::construction::Construction;
+use crate::config::{Anchor, Confinement, Config, NoConfigPathError, Settings, Sizes, Startup};
+use crate::instance::{Command, NoInstanceError};
+use crate::keys::{Action, Keyboard};
+use crate::loupe::LoupeWindow;
+use crate::marker::Marker;
+use crate::notify::Notifier;
+use crate::pick::PickCancelledError;
+#[cfg(feature = "scripting")]
+use crate::overlay::Overlay;
+use crate::session::Recorder;
+use crate::sync::Shared;
+use crate::target::{window_at, Follower, Span, Target};
+use crate::watch::Watch;
+
+mod backend;
+mod bar;
+mod baseline;
+mod capture;
+mod bundle;
+mod calibration;
+mod cli;
+mod clipboard;
+mod config;
+mod construction;
+mod control;
+mod csvlog;
+mod dialog;
+mod draw;
+mod dump;
+mod edid;
+mod env;
+mod extra;
+mod grid;
+mod guides;
+mod history;
+mod hud;
+mod hook;
+#[cfg(feature = "http")]
+mod http;
+mod instance;
+mod ipc;
+mod keys;
+mod logging;
+mod loupe;
+mod marker;
+mod notify;
+#[cfg(feature = "scripting")]
+mod overlay;
+mod persist;
+mod pick;
+#[cfg(feature = "dbus")]
+mod portal;
+#[cfg(feature = "watch")]
+mod reload;
+mod resources;
+#[cfg(feature = "screenshot")]
+mod screenshot;
+mod script;
+mod session;
+mod suggest;
+mod stats;
+mod sync;
+mod target;
+#[cfg(feature = "dbus")]
+mod tray;
+mod watch;
+mod xdg;
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    logging::init(args.verbose, args.json);
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if args.json => {
+            eprintln!("{}", serde_json::to_string(&ErrorReport { error: e.to_string() }).unwrap_or_default());
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
     }
 }
 
-impl Error for VersionMismatchError {}
-
-#[derive(Debug, Copy, Clone)]
-struct WindowGeometry {
-    x: i16,
-    y: i16,
-    w: u16,
-    h: u16,
-}
-
-impl WindowGeometry {
-    fn pos(&self) -> IVec2 {
-        IVec2::new(self.x as i32, self.y as i32)
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    match &args.subcommand {
+        Some(Subcommand::History { action }) => return match action {
+            HistoryAction::List => history::list(args.json),
+            HistoryAction::Clear => history::clear(),
+        },
+        Some(Subcommand::DumpConfig { write, force }) => {
+            let config = dump::default_config();
+            if !*write {
+                print!("{}", config);
+                return Ok(());
+            }
+            let path = args.config.clone().or_else(config::default_path).ok_or(NoConfigPathError)?;
+            return config::write_new(&path, &config, *force);
+        }
+        Some(Subcommand::Config { action }) => {
+            let path = args.config.clone().or_else(config::default_path).ok_or(NoConfigPathError)?;
+            return match action {
+                ConfigAction::Export { output } => bundle::export(&path, output.as_deref()),
+                ConfigAction::Import { input, force } => bundle::import(path, input.as_deref(), *force),
+            };
+        }
+        _ => {}
     }
 
-    fn size(&self) -> UVec2 {
-        UVec2::new(self.w as u32, self.h as u32)
+    let mut config = Config::load(args.config.as_deref())?;
+    let mut profile = args.profile.clone().or_else(env::profile).or_else(|| config.default_profile.clone());
+    let mut settings = resolve_settings(&config, profile.as_deref(), args)?;
+
+    let initial_length = settings.sizes.initial_length;
+    let half_width = settings.sizes.half_width;
+
+    let (width, height) = (extent(initial_length + half_width * 2.0, "window width"), extent(half_width * 2.0, "window height"));
+    let xcb = XCBObjects::setup(width, height)?;
+    if settings.startup.mode == Mode::Pick {
+        let (from, to) = pick::pick(&xcb)?;
+        let measurement = Measurement { from, to, px_per_mm: screen_density(&xcb, &settings, from) };
+        if let Some(format) = args.region {
+            println!("{}", Region::from(&measurement).line(format));
+        } else if args.json {
+            println!("{}", Report::from(&measurement).line(StreamFormat::Json));
+        } else if let Some(format) = &args.format {
+            println!("{}", format.render(&measurement));
+        } else {
+            println!("{}", Report::from(&measurement));
+        }
+        remember(&settings, &measurement);
+        return Ok(());
     }
-}
+    let app = App::new(xcb, width, height)?;
+    // Before the other windows are made, so a second instance only ever makes the one.
+    let registered = match instance::running(&app.xcb.conn, app.xcb.atoms.ruler_instance)? {
+        Some(owner) if settings.startup.single_instance || args.requires_instance() => {
+            instance::forward(&app.xcb.conn, owner, app.xcb.atoms.ruler_command, args.command())?;
+            return Ok(());
+        }
+        Some(_) => false,
+        None if args.requires_instance() => return Err(Box::new(NoInstanceError)),
+        None => {
+            instance::claim(&app.xcb.conn, app.xcb.atoms.ruler_instance, app.xcb.window)?;
+            true
+        }
+    };
 
-#[derive(Copy, Clone)]
-enum Dragging {
-    From,
-    To,
-    None,
-}
+    let mut loupe = LoupeWindow::create(&app.xcb, settings.loupe.zoom())?;
+    app.open(loupe.window, Role::Loupe, loupe.side(), loupe.side())?;
+    let (screen_width, screen_height) = (app.xcb.screen.width_in_pixels(), app.xcb.screen.height_in_pixels());
+    let grid = Grid::create(&app.xcb, DVec2::new(screen_width as f64, screen_height as f64))?;
+    app.open(grid.window, Role::Grid, screen_width, screen_height)?;
+    let construction = Construction::create(&app.xcb, DVec2::new(screen_width as f64, screen_height as f64))?;
+    app.open(construction.window, Role::Construction, screen_width, screen_height)?;
+    let baselines = Baselines::create(&app.xcb, DVec2::new(screen_width as f64, screen_height as f64), &settings)?;
+    app.open(baselines.window, Role::Baselines, screen_width, screen_height)?;
+    let mut hud = Hud::create(&app.xcb)?;
+    app.open(hud.window, Role::Hud, 1, 1)?;
+    let marker = Marker::create(&app.xcb)?;
+    app.open(marker.window, Role::Marker, 1, 1)?;
+    let mut dialog = Dialog::create(&app.xcb)?;
+    app.open(dialog.window, Role::Dialog, dialog::WIDTH, Dialog::height())?;
+    let (xcb, render) = (&app.xcb, &app.ruler());
+    let loupe_render = &app.render(Role::Loupe).expect("the loupe was just opened");
+    let grid_render = &app.render(Role::Grid).expect("the grid was just opened");
+    let construction_render = &app.render(Role::Construction).expect("the construction lines were just opened");
+    let baselines_render = &app.render(Role::Baselines).expect("the baselines were just opened");
+    let hud_render = &app.render(Role::Hud).expect("the HUD was just opened");
+    let marker_render = &app.render(Role::Marker).expect("the marker was just opened");
+    let dialog_render = &app.render(Role::Dialog).expect("the settings were just opened");
+    // Before anything spawns a thread, so the shutdown signals stay blocked in all of them.
+    let events = EventLoop::new()?;
 
-struct XCBObjects {
-    conn: Connection,
-    atoms: Atoms,
-    screen: ScreenBuf,
-    window: Window,
-    colormap: Colormap,
-    depth: DepthBuf,
-    gcontext: Gcontext,
-    visual_type: Visualtype,
-}
+    let root_geom = xcb.get_window_geometry(xcb.screen.root())?;
+    let screen_size = DVec2::new(root_geom.w as f64, root_geom.h as f64);
 
-fn check_versions(client_major: u32, client_minor: u32, server_major: u32, server_minor: u32, extension: &'static str) -> Result<(), Box<VersionMismatchError>> {
-    if server_major != client_major || server_major != client_major {
-        Err(Box::new(VersionMismatchError {
-            client_major_version: client_major,
-            client_minor_version: client_minor,
-            server_major_version: server_major,
-            server_minor_version: server_minor,
-            extension_name: extension,
-        }))
-    } else {
-        Ok(())
+    let dock = settings.startup.dock;
+    if let Some(edge) = dock {
+        xcb.dock(edge, (half_width * 2.0) as u32, screen_size)?;
     }
-}
 
-impl XCBObjects {
-    fn setup(width: u16, height: u16) -> Result<XCBObjects, Box<dyn Error>> {
-        let (conn, screen_num) = Connection::connect(None)?;
+    checked(&xcb.conn, &MapWindow { window: xcb.window })?;
+    // While the ruler is hidden, the keys grabbed to recall it.
+    let mut hidden = None;
+    // The last key press, click, pointer move or command, for `auto_hide_minutes`.
+    let mut last_used = Instant::now();
 
-        let cookie = conn.send_request(&render::QueryVersion {
-            client_major_version: render::MAJOR_VERSION,
-            client_minor_version: render::MINOR_VERSION,
-        });
-        let reply = conn.wait_for_reply(cookie)?;
-        check_versions(render::MAJOR_VERSION, render::MINOR_VERSION,
-                       reply.major_version(), reply.minor_version(), render::XNAME)?;
+    let mut keyboard = Keyboard::load(&xcb.conn)?;
 
-        let cookie = conn.send_request(&shape::QueryVersion {});
-        let reply = conn.wait_for_reply(cookie)?;
-        check_versions(shape::MAJOR_VERSION, shape::MINOR_VERSION,
-                       reply.major_version() as u32, reply.minor_version() as u32, render::XNAME)?;
+    config.set_resources(resources::theme_overlay(&resources::read_database(&xcb.conn, xcb.screen.root())?));
+    settings = resolve_settings(&config, profile.as_deref(), args)?;
 
+    #[cfg(feature = "watch")]
+    reload::spawn_watchers(Waker::new(xcb.window, xcb.atoms.ruler_reload)?, config.path.as_deref())?;
 
-        let xcb = {
-            let atoms = Atoms::intern_all(&conn)?;
-            let screen = conn.get_setup().roots().nth(screen_num as usize).unwrap();
-            let screen_buf = screen.to_owned();
-            let colormap: Colormap = conn.generate_id();
-            let depth = screen.allowed_depths().find(|d| d.depth() == 32).unwrap().to_owned();
-            let visual_type = depth.visuals().iter().find(|v| v.class() == VisualClass::TrueColor).unwrap().clone();
-            let window: Window = conn.generate_id();
-            let gcontext = conn.generate_id();
-
-            XCBObjects { conn, atoms, screen: screen_buf, depth, visual_type, window, gcontext, colormap }
-        };
-
-        let root = xcb.screen.root();
-
-        xcb.conn.send_and_check_request(&CreateColormap {
-            alloc: ColormapAlloc::None,
-            mid: xcb.colormap,
-            window: root,
-            visual: xcb.visual_type.visual_id(),
-        })?;
-
-        xcb.conn.send_and_check_request(&CreateWindow {
-            depth: xcb.depth.depth(),
-            wid: xcb.window,
-            parent: root,
-            x: 0,
-            y: 0,
-            width,
-            height,
-            border_width: 0,
-            class: WindowClass::InputOutput,
-            visual: xcb.visual_type.visual_id(),
-            value_list: &[
-                Cw::BorderPixel(0x00000000),
-                Cw::WinGravity(Gravity::NorthWest),
-                Cw::EventMask(EventMask::EXPOSURE | EventMask::KEY_PRESS | EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION | EventMask::STRUCTURE_NOTIFY),
-                Cw::Colormap(xcb.colormap)
-            ],
-        })?;
-
-        xcb.conn.send_and_check_request(&ChangeProperty {
-            mode: PropMode::Replace,
-            window: xcb.window,
-            property: xcb.atoms.motif_wm_hints,
-            r#type: ATOM_INTEGER,
-            data: &[2u32, 0u32, 0u32, 0u32, 0u32],
-        })?;
-
-        xcb.conn.send_and_check_request(&ChangeProperty {
-            mode: PropMode::Replace,
-            window: xcb.window,
-            property: ATOM_WM_NAME,
-            r#type: ATOM_STRING,
-            data: TITLE.as_bytes(),
-        })?;
-
-        xcb.conn.send_and_check_request(&ChangeProperty {
-            mode: PropMode::Replace,
-            window: xcb.window,
-            property: xcb.atoms.wm_protocols,
-            r#type: ATOM_ATOM,
-            data: &[xcb.atoms.wm_del_window],
-        })?;
-
-        xcb.conn.send_and_check_request(&ChangeProperty {
-            mode: PropMode::Replace,
-            window: xcb.window,
-            property: xcb.atoms.net_wm_state,
-            r#type: ATOM_ATOM,
-            data: &[xcb.atoms.net_wm_state_above, xcb.atoms.new_wm_state_skip_pager],
-        })?;
-
-        xcb.conn.send_and_check_request(&ChangeProperty {
-            mode: PropMode::Replace,
-            window: xcb.window,
-            property: xcb.atoms.net_wm_allowed_actions,
-            r#type: ATOM_ATOM,
-            data: &[xcb.atoms.new_wm_action_close],
-        })?;
-
-        xcb.conn.send_and_check_request(&CreateGc {
-            cid: xcb.gcontext,
-            drawable: Drawable::Window(xcb.window),
-            value_list: &[Gc::Background(xcb.screen.black_pixel()), Gc::GraphicsExposures(false)],
-        })?;
-
-        xcb.conn.send_and_check_request(&MapWindow { window: xcb.window })?;
-
-        Ok(xcb)
-    }
-
-    fn set_window_shape_from_points(&self, from: DVec2, to: DVec2) -> VoidCookie {
-        let rect_1 = Rectangle {
-            x: (from.x - CONTROL_RADIUS) as i16,
-            y: (from.y - CONTROL_RADIUS) as i16,
-            width: (CONTROL_RADIUS * 2.0) as u16,
-            height: (CONTROL_RADIUS * 2.0) as u16,
-        };
-        let rect_2 = Rectangle {
-            x: (to.x - CONTROL_RADIUS) as i16,
-            y: (to.y - CONTROL_RADIUS) as i16,
-            ..rect_1
-        };
-
-        self.set_window_shape(shape::Sk::Input, &[rect_1, rect_2])
+    let (sender, messages) = mpsc::channel::<Message>();
+    script::spawn_reader(Waker::new(xcb.window, xcb.atoms.ruler_control)?, sender.clone());
+    if let Some(path) = &args.replay {
+        session::spawn_replay(path, args.speed, Waker::new(xcb.window, xcb.atoms.ruler_control)?, sender.clone())?;
     }
-
-    fn set_window_shape(&self, kind: shape::Sk, rectangles: &[Rectangle]) -> VoidCookie {
-        self.conn.send_request(&shape::Rectangles {
-            operation: shape::So::Set,
-            destination_kind: kind,
-            ordering: ClipOrdering::Unsorted,
-            destination_window: self.window,
-            x_offset: 0,
-            y_offset: 0,
-            rectangles,
-        })
-    }
-
-    fn get_window_geometry(&self, window: Window) -> Result<WindowGeometry, Box<dyn Error>> {
-        let cookie = self.conn.send_request(&GetGeometry {
-            drawable: Drawable::Window(window),
-        });
-        let reply = self.conn.wait_for_reply(cookie)?;
-        Ok(WindowGeometry { x: reply.x(), y: reply.y(), w: reply.width(), h: reply.height() })
+    if settings.startup.tray {
+        #[cfg(feature = "dbus")]
+        tray::spawn(Waker::new(xcb.window, xcb.atoms.ruler_control)?, sender.clone());
+        #[cfg(not(feature = "dbus"))]
+        warn!("The tray icon is unavailable, ruler was built without the dbus feature");
     }
-}
-
-struct Render {
-    surface: XCBSurface,
-    ctx: Context,
-}
-
-impl Render {
-    fn setup(xcb: &XCBObjects, height: u16, width: u16) -> Result<Render, Box<dyn Error>> {
-        let surface = unsafe {
-            let cairo_conn = XCBConnection::from_raw_none(xcb.conn.get_raw_conn() as *mut cairo::ffi::xcb_connection_t);
-            let visual_type = XCBVisualType::from_raw_none(&xcb.visual_type as *const Visualtype as *mut cairo::ffi::xcb_visualtype_t);
-            let drawable = XCBDrawable(xcb.window.resource_id());
-            XCBSurface::create(&cairo_conn, &drawable, &visual_type, width as i32, height as i32)?
-        };
-        xcb.conn.flush()?;
-        let cairo = Context::new(&surface)?;
-        Ok(Render { ctx: cairo, surface })
+    // Held until the end, dropping it removes the socket.
+    let _server = if registered {
+        ipc::Server::listen(Waker::new(xcb.window, xcb.atoms.ruler_control)?, sender).unwrap_or_else(|e| {
+            warn!("Not listening for control connections: {}", e);
+            None
+        })
+    } else {
+        None
+    };
+    let mut observers = Observers {
+        subscribers: Vec::new(),
+        watch: args.watch(),
+        bar: args.bar_fifo.as_deref().map(Bar::spawn).transpose()?,
+        recorder: args.record.as_deref().map(Recorder::create).transpose()?,
+        #[cfg(feature = "http")]
+        http: args.http.map(Endpoint::serve).transpose()?,
+    };
+    #[cfg(not(feature = "http"))]
+    if args.http.is_some() {
+        warn!("Ignoring --http, ruler was built without the http feature");
     }
-
-    fn resize(&self, width: i32, height: i32) -> Result<(), Box<dyn Error>> {
-        self.surface.set_size(width, height)?;
-        Ok(())
+    let notifier = Notifier::spawn();
+    let mut log = args.log.as_deref().map(CsvLog::open).transpose()?;
+
+    let mut unit = None;
+    if settings.sync {
+        match sync::read(&xcb.conn, xcb.screen.root(), xcb.atoms.ruler_sync) {
+            Ok(Some(shared)) => {
+                adopt(shared, &config, &mut profile, &mut unit);
+                settings = resolve_settings(&config, profile.as_deref(), args)?;
+            }
+            Ok(None) => share(xcb, &settings, profile.as_deref(), unit),
+            Err(e) => warn!("Failed to read the state shared by other rulers: {}", e),
+        }
     }
-}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let xcb = Rc::new(XCBObjects::setup((INITIAL_LENGTH + RULER_HALF_WIDTH * 2.0) as u16, (RULER_HALF_WIDTH * 2.0) as u16)?);
-
-    let root_geom = xcb.get_window_geometry(xcb.screen.root())?;
-
-    let (mut from, mut to) = {
-        let from_x = (root_geom.w as f64 - INITIAL_LENGTH) / 2.0 + RULER_HALF_WIDTH;
-        let from_y = root_geom.h as f64 / 2.0 + RULER_HALF_WIDTH;
-
-        (DVec2::new(from_x, from_y), DVec2::new(from_x + INITIAL_LENGTH, from_y))
+    let (from, to) = match dock {
+        Some(edge) => edge.endpoints(settings.sizes.half_width, screen_size),
+        None => place(xcb, &settings.startup, initial_length, screen_size)?,
     };
-
-    let render = {
-        let window_geom = compute_window_geometry(from, to);
-        let render = Render::setup(&xcb, window_geom.w, window_geom.h)?;
-        render
+    let mut state = RulerState::new(from, to, settings.startup.mode, screen_size);
+    state.unit = unit;
+    state.bounds = bounds(xcb, settings.drag.confine_to, state.from, screen_size);
+    state.body = settings.drag.body.then_some(settings.sizes.half_width);
+
+    let px_per_mm = screen_density(xcb, &settings, state.from);
+    let mut view = View {
+        sampling: false,
+        samples: Default::default(),
+        luminance: false,
+        profile: Default::default(),
+        gap: None,
+        calibrating: false,
+        zoom_strip: false,
+        strip: None,
+        committed: Default::default(),
+        reference: None,
+        reference_angle: None,
+        flip_ticks: false,
+        precise: false,
+        dock,
+        px_per_mm,
+        #[cfg(feature = "scripting")]
+        overlay: load_overlay(&settings),
+        labels: Default::default(),
+        stats: Default::default(),
+        loupe: settings.loupe.enabled,
+        edge_snap: None,
     };
+    #[cfg(not(feature = "scripting"))]
+    if settings.overlay_script.is_some() {
+        warn!("Ignoring overlay_script, ruler was built without the scripting feature");
+    }
 
-    let mut dragging = Dragging::None;
+    observers.moved(&settings, &state.measurement(view.px_per_mm));
 
-    let mut last_update = Instant::now();
+    let mut clipboard = Clipboard::default();
+
+    let guides = Guides::create(xcb, &settings.theme)?;
+    let mut frame = Frame { time: Instant::now(), geometry: None, endpoints: (DVec2::ZERO, DVec2::ZERO), guides, grid, construction, baselines, marker };
+    let mut damage = Damage::default();
+    // The windows to snap to, looked up once per drag when first needed.
+    let mut windows: Option<Vec<Rect>> = None;
+    // And the monitors, for their center lines.
+    let mut monitors: Option<Vec<Rect>> = None;
+    let mut target: Option<Target> = None;
+    let mut follower = Follower::default();
+    let mut extras = Extras::default();
 
     let mut first = true;
+    let mut handling: Option<Instant> = None;
 
-    loop {
-        let event = xcb.conn.wait_for_event()?;
+    'events: loop {
+        if let Some(start) = handling.take() {
+            view.stats.borrow_mut().handled(start.elapsed());
+        }
+        // Wherever the last event left it, as long as it is not held.
+        if !state.is_dragging() {
+            view.committed.commit(state.length());
+        }
+        if hidden.is_none() {
+            auto_hide(xcb, &events, &settings, &mut hidden, &companions(&frame, &extras), &keyboard, last_used)?;
+        }
+        let wakeup = events.next(&xcb.conn)?;
+        if let Some(flush) = events.take_flush() {
+            view.stats.borrow_mut().flushed(flush);
+        }
+        handling = Some(Instant::now());
+
+        let event = match wakeup {
+            Wakeup::Event(event) => event,
+            // A window update held back by the frame rate cap, or a color sample, is due.
+            Wakeup::Timer => {
+                reshape(xcb, render, &state, &settings, &view, &mut frame)?;
+                follow_loupe(xcb, &mut loupe, loupe_render, &state, &settings, &mut view);
+                if view.sampling || view.luminance {
+                    sample(xcb, render, &state, &settings, &view)?;
+                }
+                if view.zoom_strip {
+                    capture_strip(xcb, render, &state, &settings, &mut view)?;
+                }
+                continue;
+            }
+            // Ctrl-C or the session ending, quit as if asked to.
+            Wakeup::Signal(signal) => {
+                info!(signal, "Quitting on a signal");
+                break;
+            }
+        };
 
+        let used = match &event {
+            xcb::Event::X(Event::KeyPress(_) | Event::ButtonPress(_) | Event::ButtonRelease(_) | Event::MotionNotify(_)) => true,
+            xcb::Event::X(Event::ClientMessage(ev)) => ev.r#type() == xcb.atoms.ruler_command || ev.r#type() == xcb.atoms.ruler_control,
+            _ => false,
+        };
+        if used {
+            last_used = Instant::now();
+        }
+        if handle_extra(&app, &mut extras, &event, &settings, &view)? {
+            continue;
+        }
         match event {
-            xcb::Event::X(Event::Expose(_ev)) => {
+            // The loupe is small enough to paint in full for every exposed part.
+            xcb::Event::X(Event::Expose(ev)) if ev.window() == loupe.window => {
+                loupe.paint(loupe_render, settings.theme.accent)?;
+            }
+            xcb::Event::X(Event::Expose(ev)) if ev.window() == frame.marker.window => frame.marker.expose(&ev, marker_render, &settings)?,
+            xcb::Event::X(Event::Expose(ev)) if ev.window() == hud.window => hud.paint(hud_render, &settings)?,
+            xcb::Event::Input(xinput::Event::RawMotion(_)) => follow_pointer(xcb, &mut hud, hud_render, &state, &settings, &view),
+            xcb::Event::X(Event::Expose(ev)) if ev.window() == dialog.window => dialog.paint(dialog_render, &settings)?,
+            xcb::Event::X(Event::Expose(ev)) if ev.window() == frame.grid.window => frame.grid.expose(&ev, grid_render, &state, &settings)?,
+            xcb::Event::X(Event::Expose(ev)) if ev.window() == frame.construction.window => frame.construction.expose(&ev, construction_render, &state, &settings)?,
+            xcb::Event::X(Event::Expose(ev)) if ev.window() == frame.baselines.window => frame.baselines.expose(&ev, baselines_render, &state, &settings)?,
+            xcb::Event::X(Event::Expose(ev)) => {
+                // Windows dragged across a long ruler expose a little of it at
+                // a time, so only what they uncovered is drawn again.
+                let Some(area) = damage.expose(&ev) else { continue };
                 if first {
-                    update(&xcb, &render, from, to, &mut last_update, true);
+                    update(xcb, render, &state, &settings, &view, &mut frame, true)?;
                     first = false;
                 }
-                redraw(&render, from, to)?;
-                xcb.conn.flush()?;
+                render.ctx.save()?;
+                render.ctx.rectangle(area.x as f64, area.y as f64, area.width as f64, area.height as f64);
+                render.ctx.clip();
+                let drawn = redraw(render, &state, &settings, &view);
+                render.ctx.restore()?;
+                drawn?;
+                if view.sampling || view.luminance || view.zoom_strip {
+                    events.schedule_within(SAMPLE_SETTLE)?;
+                }
             }
-            xcb::Event::X(Event::ButtonPress(ev)) => {
-                if ev.detail() == 1 {
-                    let cursor = DVec2::new(ev.root_x() as f64, ev.root_y() as f64);
-                    if cursor.distance_squared(from) < 6400.0 {
-                        dragging = Dragging::From;
-                    } else if cursor.distance_squared(to) < 6400.0 {
-                        dragging = Dragging::To;
+            xcb::Event::X(Event::ButtonPress(ev)) if ev.event() == dialog.window => {
+                let forward = match ev.detail() {
+                    1 => true,
+                    3 => false,
+                    _ => continue,
+                };
+                let Some(row) = dialog.row_at(ev.event_y() as f64) else { continue };
+                match config.save(&row.step(&settings, forward)) {
+                    Ok(saved) => {
+                        config = saved;
+                        dialog.error = None;
+                        info!(?row, "saved a setting");
+                        if row == Row::Unit {
+                            // Or the unit picked at runtime would hide the change.
+                            state.unit = None;
+                            share(xcb, &settings, profile.as_deref(), state.unit);
+                        }
+                        settings = resolve_settings(&config, profile.as_deref(), args)?;
+                        apply_settings(xcb, render, &mut state, &settings, &mut view, &mut frame)?;
+                    }
+                    Err(e) => {
+                        warn!("Failed to save the setting: {}", e);
+                        dialog.error = Some(e.to_string());
                     }
                 }
+                dialog.paint(dialog_render, &settings)?;
+            }
+            xcb::Event::X(Event::ButtonPress(ev)) if ev.detail() == 1 && dock.is_none() => {
+                let cursor = DVec2::new(ev.root_x() as f64, ev.root_y() as f64);
+                if let Some(handle) = state.grab(cursor, settings.sizes.half_width * 2.0, ev.detail()) {
+                    let anchor = match handle {
+                        Handle::From => state.to,
+                        Handle::To => state.from,
+                        Handle::Body => (state.from + state.to) / 2.0,
+                        Handle::ArmFoot | Handle::ArmEnd => state.from.lerp(state.to, state.arm.at),
+                    };
+                    state.bounds = bounds(xcb, settings.drag.confine_to, anchor, state.screen_size);
+                    (windows, monitors) = (None, None);
+                    hud.hide(xcb);
+                    debug!(?handle, ?cursor, bounds = ?state.bounds, "drag started");
+                }
+            }
+            xcb::Event::X(Event::MotionNotify(ev)) if !state.is_dragging() => {
+                let cursor = DVec2::new(ev.root_x() as f64, ev.root_y() as f64);
+                if dock.is_none() && state.hover(Some(cursor), settings.sizes.half_width * 2.0) {
+                    redraw(render, &state, &settings, &view)?;
+                }
+            }
+            xcb::Event::X(Event::LeaveNotify(_)) if state.hover(None, settings.sizes.half_width * 2.0) => {
+                redraw(render, &state, &settings, &view)?;
             }
             xcb::Event::X(Event::MotionNotify(ev)) => {
-                match dragging {
-                    Dragging::From => {
-                        let screen_size = DVec2::new(root_geom.w as f64, root_geom.h as f64);
-                        let fix_distance = ev.state().intersects(KeyButMask::CONTROL);
-                        let fix_angle = ev.state().intersects(KeyButMask::SHIFT);
-                        handle_drag(&mut from, to, DVec2::new(ev.root_x() as f64, ev.root_y() as f64), screen_size, fix_distance, fix_angle);
-                        if let Some(_) = update(&xcb, &render, from, to, &mut last_update, false) {
-                            xcb.conn.flush()?;
+                let constraints = Constraints {
+                    fix_distance: state.locks.fix_distance || settings.drag.fix_distance.held(ev.state()),
+                    fix_angle: state.locks.fix_angle || settings.drag.fix_angle.held(ev.state()),
+                    symmetric: state.locks.symmetric || settings.drag.symmetric.held(ev.state()),
+                    clamp_to_screen: state.clamp_to_screen(settings.drag.clamp_to_screen),
+                    min_length: settings.sizes.min_length,
+                    snap_angle: settings.drag.snap_angle,
+                    snap_tolerance: settings.drag.snap_tolerance,
+                    snap_to_pixels: settings.drag.snap_to_pixels,
+                };
+                let constraints = if settings.drag.ignore_limits.held(ev.state()) { constraints.ignoring_limits() } else { constraints };
+                if !state.drag(DVec2::new(ev.root_x() as f64, ev.root_y() as f64), constraints) {
+                    continue;
+                }
+                view.edge_snap = settings.drag.snap_to_edges.held(ev.state()).then_some(settings.drag.edge_reach());
+                if let Some(reach) = view.edge_snap {
+                    snap_to_edge(xcb, &mut state, reach, constraints);
+                }
+                if settings.drag.snap_to_windows.held(ev.state()) {
+                    let windows = windows.get_or_insert_with(|| {
+                        xcb.windows().unwrap_or_else(|e| {
+                            warn!("Not snapping to windows, they could not be listed: {}", e);
+                            Vec::new()
+                        })
+                    });
+                    let monitors = monitors.get_or_insert_with(|| monitor_rects(xcb, state.screen_size));
+                    let reach = settings.drag.edge_reach() as f64;
+                    if let Some(endpoint) = state.dragged_endpoint() {
+                        let centered = monitors.iter().filter(|_| settings.drag.snap_to_center).find(|monitor| monitor.contains(endpoint)).and_then(|monitor| snap_to_center_lines(endpoint, monitor, reach));
+                        let snapped = [snap_to_rects(endpoint, windows, reach), centered].into_iter().flatten().min_by(|a, b| a.distance_squared(endpoint).total_cmp(&b.distance_squared(endpoint)));
+                        if let Some(snapped) = snapped {
+                            state.drag_to(snapped, constraints);
                         }
                     }
-                    Dragging::To => {
-                        let screen_size = DVec2::new(root_geom.w as f64, root_geom.h as f64);
-                        let fix_distance = ev.state().intersects(KeyButMask::CONTROL);
-                        let fix_angle = ev.state().intersects(KeyButMask::SHIFT);
-                        handle_drag(&mut to, from, DVec2::new(ev.root_x() as f64, ev.root_y() as f64), screen_size, fix_distance, fix_angle);
-                        if let Some(_) = update(&xcb, &render, from, to, &mut last_update, false) {
-                            xcb.conn.flush()?;
+                }
+                // The handles move with the endpoint, so the input shape follows at the same pace.
+                if let Some(geometry) = update(xcb, render, &state, &settings, &view, &mut frame, false)? {
+                    shape(xcb, &state, &settings, &view, geometry);
+                    follow_loupe(xcb, &mut loupe, loupe_render, &state, &settings, &mut view);
+                } else {
+                    // Catch up later, in case this was the last motion of the drag.
+                    events.schedule(settings.performance.min_interval().saturating_sub(frame.time.elapsed()))?;
+                }
+                observers.moved(&settings, &state.measurement(view.px_per_mm));
+            }
+            xcb::Event::X(Event::ButtonRelease(ev)) if state.is_dragging() => {
+                view.edge_snap = None;
+                if view.zoom_strip {
+                    events.schedule_within(SAMPLE_SETTLE)?;
+                }
+                if state.release(ev.detail()) {
+                    debug!(from = ?state.from, to = ?state.to, "drag finished");
+                    // The ruler may have been dragged onto another monitor.
+                    view.px_per_mm = screen_density(xcb, &settings, state.from);
+                    let measurement = state.measurement(view.px_per_mm);
+                    if let Some(format) = args.stream_format() {
+                        println!("{}", Report::from(&measurement).line(format));
+                    }
+                    observers.publish(&settings, &measurement);
+                    if settings.notify {
+                        notifier.notify(settings.format.render(&measurement));
+                    }
+                    if let Some(command) = &args.on_measure {
+                        hook::run(command, &Report::from(&measurement), &settings.format.render(&measurement));
+                    }
+                    if let Some(log) = &mut log {
+                        if let Err(e) = log.record(&Report::from(&measurement)) {
+                            warn!("Failed to log the measurement: {}", e);
                         }
                     }
-                    Dragging::None => {}
                 }
-            }
-            xcb::Event::X(Event::ButtonRelease(ev)) => {
-                if ev.detail() == 1 {
-                    dragging = Dragging::None;
-                    let pos = update(&xcb, &render, from, to, &mut last_update, true).unwrap().pos().as_dvec2();
-                    xcb.set_window_shape_from_points(from - pos, to - pos);
-                    xcb.conn.flush()?;
+                reshape(xcb, render, &state, &settings, &view, &mut frame)?;
+                if loupe.shown() {
+                    // Fill in the hole the loupe looked through.
+                    loupe.hide(xcb);
+                    redraw(render, &state, &settings, &view)?;
                 }
             }
             xcb::Event::X(Event::KeyPress(ev)) => {
-                if ev.detail() == 0x18 {
-                    break Ok(());
+                match keyboard.action(&settings.keymap, ev.detail(), ev.state()) {
+                    Some(Action::Quit | Action::CancelDrag | Action::ToggleSettings) if ev.event() == dialog.window => dialog.hide(xcb),
+                    Some(Action::ToggleSettings) => dialog.toggle(xcb),
+                    Some(Action::Quit) if extras.contains(ev.event()) => close_extra(&app, &mut extras, ev.event()),
+                    Some(Action::Quit) => break,
+                    Some(Action::CancelDrag) if state.is_dragging() => {
+                        state.cancel();
+                        loupe.hide(xcb);
+                        debug!("drag cancelled");
+                        refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                        observers.moved(&settings, &state.measurement(view.px_per_mm));
+                    }
+                    Some(Action::ToggleFixDistance) => {
+                        state.locks.fix_distance = !state.locks.fix_distance;
+                        debug!(fix_distance = state.locks.fix_distance, "toggled fixed distance");
+                    }
+                    Some(Action::ToggleFixAngle) => {
+                        state.locks.fix_angle = !state.locks.fix_angle;
+                        debug!(fix_angle = state.locks.fix_angle, "toggled fixed angle");
+                    }
+                    Some(Action::ToggleSymmetric) => {
+                        state.locks.symmetric = !state.locks.symmetric;
+                        debug!(symmetric = state.locks.symmetric, "toggled symmetric resizing");
+                    }
+                    Some(Action::ToggleClamp) => {
+                        state.locks.clamp_toggled = !state.locks.clamp_toggled;
+                        debug!(clamp_toggled = state.locks.clamp_toggled, "toggled clamping");
+                    }
+                    Some(Action::Copy) => {
+                        let text = settings.format.render(&state.measurement(view.px_per_mm));
+                        clipboard.set(&xcb.conn, &xcb.atoms, xcb.window, ev.time(), text)?;
+                    }
+                    Some(Action::Paste) => clipboard::request_primary(&xcb.conn, &xcb.atoms, xcb.window, ev.time())?,
+                    #[cfg(not(feature = "screenshot"))]
+                    Some(Action::Screenshot) => warn!("Not saving a screenshot, ruler was built without the screenshot feature"),
+                    #[cfg(feature = "screenshot")]
+                    Some(Action::Screenshot) => {
+                        let measurement = state.measurement(view.px_per_mm);
+                        match screenshot::save(xcb, &measurement, settings.sizes.half_width, screen_size, &capture_dir(&settings)) {
+                            Ok(path) => info!(path = %path.display(), "saved screenshot"),
+                            Err(e) => warn!("Failed to save a screenshot: {}", e),
+                        }
+                    }
+                    Some(Action::ExportSvg) => {
+                        let path = capture_dir(&settings).join(state.measurement(view.px_per_mm).file_name("svg"));
+                        match export_svg(&path, &state, &extras, &frame, &settings, &view) {
+                            Ok(()) => info!(path = %path.display(), "exported overlay"),
+                            Err(e) => warn!("Failed to export an overlay: {}", e),
+                        }
+                    }
+                    Some(Action::NextProfile) => {
+                        profile = config.next_profile(profile.as_deref());
+                        info!(profile = profile.as_deref().unwrap_or("base"), "switched profile");
+                        settings = resolve_settings(&config, profile.as_deref(), args)?;
+                        apply_settings(xcb, render, &mut state, &settings, &mut view, &mut frame)?;
+                        share(xcb, &settings, profile.as_deref(), state.unit);
+                    }
+                    Some(Action::ToggleFrameStats) => {
+                        let visible = !view.stats.borrow().visible;
+                        view.stats.borrow_mut().visible = visible;
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::ToggleSampler) => {
+                        view.sampling = !view.sampling;
+                        view.samples.set([None, None]);
+                        redraw(render, &state, &settings, &view)?;
+                        if view.sampling {
+                            events.schedule_within(SAMPLE_SETTLE)?;
+                        }
+                    }
+                    Some(Action::CopyColor) => {
+                        let index = if state.active_handle() == Some(Handle::To) { 1 } else { 0 };
+                        match view.samples.get()[index] {
+                            Some(sample) => clipboard.set(&xcb.conn, &xcb.atoms, xcb.window, ev.time(), sample.to_string())?,
+                            None => debug!("no color sampled to copy"),
+                        }
+                    }
+                    Some(Action::FlipTicks) => {
+                        view.flip_ticks = !view.flip_ticks;
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::ToggleLuminance) => {
+                        view.luminance = !view.luminance;
+                        view.profile.borrow_mut().clear();
+                        redraw(render, &state, &settings, &view)?;
+                        if view.luminance {
+                            events.schedule_within(SAMPLE_SETTLE)?;
+                        }
+                    }
+                    Some(Action::StoreReference) => {
+                        // Pressed again where it was stored, it is forgotten.
+                        let length = state.measurement(view.px_per_mm).length_mm();
+                        view.reference = if view.reference == Some(length) { None } else { Some(length) };
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::StoreReferenceAngle) => {
+                        let angle = DVec2::X.angle_between(state.direction());
+                        view.reference_angle = if view.reference_angle == Some(angle) { None } else { Some(angle) };
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::NewRuler) => new_ruler(&app, &mut extras, &state, &settings, &view)?,
+                    Some(Action::DropMarker) if frame.marker.dropped() => frame.marker.clear(xcb),
+                    Some(Action::DropMarker) => match pick::pick_points(xcb, 1) {
+                        Ok(points) => {
+                            frame.marker.drop_at(points[0]);
+                            frame.marker.follow(xcb, &render.ctx, &state, &settings, view.px_per_mm)?;
+                        }
+                        Err(e) if e.is::<PickCancelledError>() => debug!("dropping a marker was cancelled"),
+                        Err(e) => warn!("Failed to pick a point for the marker: {}", e),
+                    },
+                    Some(Action::ToggleCursorDistance) => {
+                        hud.enabled = !hud.enabled;
+                        if let Err(e) = xcb.watch_pointer(hud.enabled) {
+                            warn!("Not showing the distance to the pointer, its motion cannot be followed: {}", e);
+                            hud.enabled = false;
+                        }
+                        follow_pointer(xcb, &mut hud, hud_render, &state, &settings, &view);
+                    }
+                    Some(Action::ToggleZoomStrip) => {
+                        view.zoom_strip = !view.zoom_strip;
+                        view.strip = None;
+                        redraw(render, &state, &settings, &view)?;
+                        if view.zoom_strip {
+                            events.schedule_within(SAMPLE_SETTLE)?;
+                        }
+                    }
+                    Some(Action::MeasureWindow) if view.docked() => warn!("Ignoring measure_window, a docked ruler cannot be moved"),
+                    Some(Action::MeasureWindow) => {
+                        let next = target.and_then(|target| target.next((state.from, state.to))).or_else(|| pick_window(xcb));
+                        if let Some((window, span)) = next {
+                            target = Some(lay_across(&mut state, &settings, window, span));
+                            refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                            observers.publish(&settings, &state.measurement(view.px_per_mm));
+                        }
+                    }
+                    Some(Action::MeasureGap) if view.docked() => warn!("Ignoring measure_gap, a docked ruler cannot be moved"),
+                    Some(Action::MeasureGap) => {
+                        let Some(windows) = pick_windows(xcb, 2) else { continue };
+                        match gap_between(&windows[0], &windows[1]) {
+                            Some((from, to)) => {
+                                state.set(Some(from), Some(to), state.clamp_to_screen(settings.drag.clamp_to_screen));
+                                let centers = ((windows[0].min + windows[0].max) / 2.0).distance((windows[1].min + windows[1].max) / 2.0);
+                                debug!(?windows, centers, "measured the gap between windows");
+                                view.gap = Some(Gap { endpoints: (state.from, state.to), centers });
+                                refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                                observers.publish(&settings, &state.measurement(view.px_per_mm));
+                            }
+                            None => warn!("The windows overlap, there is no gap between them to measure"),
+                        }
+                    }
+                    Some(Action::ToggleFollowFocus) if view.docked() => warn!("Ignoring toggle_follow_focus, a docked ruler cannot be moved"),
+                    Some(Action::ToggleFollowFocus) => {
+                        debug!(follow_focus = !follower.enabled, "toggled following the focus");
+                        if let Some(window) = follower.toggle(xcb) {
+                            target = Some(lay_across(&mut state, &settings, window, target.map_or(Span::Width, |target| target.span)));
+                            refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                            observers.publish(&settings, &state.measurement(view.px_per_mm));
+                        }
+                    }
+                    Some(Action::ToggleGuides) => frame.guides.toggle(xcb, &state),
+                    Some(Action::ToggleGrid) => frame.grid.toggle(xcb, &state, &settings),
+                    Some(Action::ToggleConstruction) => frame.construction.toggle(xcb, &state),
+                    Some(Action::ToggleBaseline) => frame.baselines.toggle(xcb, &state),
+                    Some(Action::BaselineTaller) => frame.baselines.adjust(xcb, &state, true),
+                    Some(Action::BaselineShorter) => frame.baselines.adjust(xcb, &state, false),
+                    Some(Action::ToggleLoupe) => {
+                        view.loupe = !view.loupe;
+                        follow_loupe(xcb, &mut loupe, loupe_render, &state, &settings, &mut view);
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::CancelDrag) if view.calibrating => {
+                        view.calibrating = false;
+                        debug!("calibration cancelled");
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::Calibrate) if !view.calibrating => {
+                        view.calibrating = true;
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::Calibrate) => {
+                        view.calibrating = false;
+                        match calibrate(state.from.distance(state.to), &settings) {
+                            Ok(px_per_mm) => {
+                                view.px_per_mm = screen_density(xcb, &settings, state.from);
+                                if view.px_per_mm != DVec2::splat(px_per_mm) {
+                                    warn!("The px_per_mm setting still takes precedence over the calibration");
+                                }
+                            }
+                            Err(e) => warn!("Failed to calibrate: {}", e),
+                        }
+                        redraw(render, &state, &settings, &view)?;
+                        observers.moved(&settings, &state.measurement(view.px_per_mm));
+                    }
+                    Some(Action::TogglePrecise) => {
+                        view.precise = !view.precise;
+                        redraw(render, &state, &settings, &view)?;
+                    }
+                    Some(Action::Recall) => toggle(xcb, &mut hidden, &companions(&frame, &extras), &keyboard, &settings),
+                    Some(Action::Recenter) if view.docked() => warn!("Ignoring recenter, a docked ruler cannot be moved"),
+                    Some(Action::Recenter) if !state.is_dragging() => {
+                        let (from, to) = centered(xcb, &state, settings.sizes.initial_length)?;
+                        state.bounds = bounds(xcb, settings.drag.confine_to, (from + to) / 2.0, state.screen_size);
+                        state.set(Some(from), Some(to), state.clamp_to_screen(settings.drag.clamp_to_screen));
+                        view.px_per_mm = screen_density(xcb, &settings, state.from);
+                        refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                        observers.publish(&settings, &state.measurement(view.px_per_mm));
+                    }
+                    Some(Action::NudgeLeft | Action::NudgeRight | Action::NudgeUp | Action::NudgeDown) if view.docked() => warn!("Ignoring the nudge, a docked ruler cannot be moved"),
+                    Some(action @ (Action::NudgeLeft | Action::NudgeRight | Action::NudgeUp | Action::NudgeDown)) if !state.is_dragging() => {
+                        let step = if view.precise { PRECISE_NUDGE } else { 1.0 };
+                        let offset = match action {
+                            Action::NudgeLeft => -DVec2::X,
+                            Action::NudgeRight => DVec2::X,
+                            Action::NudgeUp => -DVec2::Y,
+                            _ => DVec2::Y,
+                        } * step;
+                        let (from, to) = match state.active_handle() {
+                            Some(Handle::From) => (Some(state.from + offset), None),
+                            Some(Handle::To) => (None, Some(state.to + offset)),
+                            _ => (Some(state.from + offset), Some(state.to + offset)),
+                        };
+                        state.set(from, to, state.clamp_to_screen(settings.drag.clamp_to_screen));
+                        refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                        observers.publish(&settings, &state.measurement(view.px_per_mm));
+                    }
+                    // Nudges and recentering wait for the drag to end.
+                    Some(Action::CancelDrag | Action::Recenter | Action::NudgeLeft | Action::NudgeRight | Action::NudgeUp | Action::NudgeDown) | None => {}
+                }
+            }
+            xcb::Event::X(Event::SelectionRequest(ev)) => {
+                clipboard.handle_request(&xcb.conn, &xcb.atoms, &ev)?;
+            }
+            xcb::Event::X(Event::SelectionClear(ev)) => clipboard.handle_clear(&ev),
+            xcb::Event::X(Event::SelectionNotify(ev)) if ev.selection() == ATOM_PRIMARY => {
+                let Some(text) = clipboard::read_primary(&xcb.conn, &ev)? else {
+                    warn!("Nothing to paste, PRIMARY holds no text");
+                    continue;
+                };
+                match clipboard::parse_points(&text) {
+                    _ if view.docked() => warn!("Ignoring paste, a docked ruler cannot be moved"),
+                    Some((first, second)) => {
+                        state.set(Some(first), second, state.clamp_to_screen(settings.drag.clamp_to_screen));
+                        refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                        observers.publish(&settings, &state.measurement(view.px_per_mm));
+                    }
+                    None => warn!("Can't paste '{}', expected two or four numbers, as in X,Y or X1,Y1 X2,Y2", text.trim()),
+                }
+            }
+            xcb::Event::X(Event::MappingNotify(ev)) if ev.request() == Mapping::Keyboard => {
+                keyboard = Keyboard::load(&xcb.conn)?;
+            }
+            xcb::Event::X(Event::ClientMessage(ev)) if ev.r#type() == xcb.atoms.ruler_reload => {
+                match config.reload() {
+                    Ok(reloaded) => {
+                        config = reloaded;
+                        info!("reloaded config");
+                        if let Some(name) = profile.as_deref().filter(|&name| !config.has_profile(name)) {
+                            warn!("Profile '{}' no longer exists, falling back to the base config", name);
+                            profile = None;
+                        }
+                        settings = resolve_settings(&config, profile.as_deref(), args)?;
+                        apply_settings(xcb, render, &mut state, &settings, &mut view, &mut frame)?;
+                    }
+                    Err(e) => warn!("Failed to reload config: {}", e),
+                }
+            }
+            xcb::Event::X(Event::PropertyNotify(ev)) if ev.window() == xcb.screen.root() && ev.atom() == xcb.atoms.net_active_window && follower.enabled => {
+                if let Some(window) = follower.focus_changed(xcb) {
+                    target = Some(lay_across(&mut state, &settings, window, target.map_or(Span::Width, |target| target.span)));
+                    refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                    observers.publish(&settings, &state.measurement(view.px_per_mm));
+                }
+            }
+            // The followed window moved or was resized, or the window manager
+            // moving its frame told it so.
+            xcb::Event::X(Event::ConfigureNotify(ev)) if follower.window == Some(ev.window()) => {
+                if let Some(window) = follower.moved(xcb) {
+                    target = Some(lay_across(&mut state, &settings, window, target.map_or(Span::Width, |target| target.span)));
+                    refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                    observers.moved(&settings, &state.measurement(view.px_per_mm));
+                }
+            }
+            xcb::Event::X(Event::PropertyNotify(ev)) if ev.window() == xcb.screen.root() && ev.atom() == ATOM_RESOURCE_MANAGER => {
+                config.set_resources(resources::theme_overlay(&resources::read_database(&xcb.conn, xcb.screen.root())?));
+                settings = resolve_settings(&config, profile.as_deref(), args)?;
+                apply_settings(xcb, render, &mut state, &settings, &mut view, &mut frame)?;
+            }
+            xcb::Event::X(Event::PropertyNotify(ev)) if ev.window() == xcb.screen.root() && ev.atom() == xcb.atoms.ruler_sync && settings.sync => {
+                let shared = match sync::read(&xcb.conn, xcb.screen.root(), xcb.atoms.ruler_sync) {
+                    Ok(Some(shared)) => shared,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!("Failed to read the state shared by other rulers: {}", e);
+                        continue;
+                    }
+                };
+                if adopt(shared, &config, &mut profile, &mut state.unit) {
+                    debug!(profile = profile.as_deref().unwrap_or("base"), unit = ?state.unit, "synced with another ruler");
+                    settings = resolve_settings(&config, profile.as_deref(), args)?;
+                    apply_settings(xcb, render, &mut state, &settings, &mut view, &mut frame)?;
+                }
+            }
+            xcb::Event::X(Event::ClientMessage(ev)) if ev.r#type() == xcb.atoms.ruler_command => {
+                let ClientMessageData::Data32(data) = ev.data() else { continue };
+                match Command::decode(data) {
+                    Some(Command::Place { orientation, anchor }) => {
+                        if dock.is_none() {
+                            let mut startup = settings.startup.clone();
+                            startup.orientation = orientation.unwrap_or(startup.orientation);
+                            startup.anchor = anchor.unwrap_or(startup.anchor);
+                            (state.from, state.to) = place(xcb, &startup, settings.sizes.initial_length, screen_size)?;
+                        }
+                        if hidden.is_some() {
+                            toggle(xcb, &mut hidden, &companions(&frame, &extras), &keyboard, &settings);
+                        }
+                        refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                        observers.publish(&settings, &state.measurement(view.px_per_mm));
+                    }
+                    Some(Command::Set { .. }) if view.docked() => warn!("Ignoring set, a docked ruler cannot be moved"),
+                    Some(Command::Set { length, angle }) => {
+                        state.resize(length, angle, state.clamp_to_screen(settings.drag.clamp_to_screen));
+                        refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                        observers.publish(&settings, &state.measurement(view.px_per_mm));
+                    }
+                    Some(Command::Toggle) => toggle(xcb, &mut hidden, &companions(&frame, &extras), &keyboard, &settings),
+                    Some(Command::Quit) => break,
+                    None => {}
                 }
             }
+            xcb::Event::X(Event::ClientMessage(ev)) if ev.r#type() == xcb.atoms.ruler_control => {
+                for Message { request, reply } in messages.try_iter() {
+                    let response = match request {
+                        Request::Get => format!("state {}", Report::from(&state.measurement(view.px_per_mm))),
+                        Request::Set { .. } | Request::Rotate(_) if view.docked() => "error a docked ruler cannot be moved".to_string(),
+                        Request::Set { from: new_from, to: new_to } => {
+                            state.set(new_from, new_to, state.clamp_to_screen(settings.drag.clamp_to_screen));
+                            refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                            observers.publish(&settings, &state.measurement(view.px_per_mm));
+                            "ok".to_string()
+                        }
+                        Request::Rotate(degrees) => {
+                            state.rotate(degrees, state.clamp_to_screen(settings.drag.clamp_to_screen));
+                            refresh(xcb, render, &state, &settings, &view, &mut frame)?;
+                            observers.publish(&settings, &state.measurement(view.px_per_mm));
+                            "ok".to_string()
+                        }
+                        Request::Unit(unit) => {
+                            state.unit = Some(unit);
+                            redraw(render, &state, &settings, &view)?;
+                            share(xcb, &settings, profile.as_deref(), state.unit);
+                            "ok".to_string()
+                        }
+                        Request::Subscribe => {
+                            observers.subscribers.push(reply.clone());
+                            "ok".to_string()
+                        }
+                        Request::NewRuler => {
+                            new_ruler(&app, &mut extras, &state, &settings, &view)?;
+                            "ok".to_string()
+                        }
+                        Request::Toggle => {
+                            toggle(xcb, &mut hidden, &companions(&frame, &extras), &keyboard, &settings);
+                            "ok".to_string()
+                        }
+                        Request::Quit => {
+                            let _ = reply.send("ok".to_string());
+                            break 'events;
+                        }
+                    };
+                    let _ = reply.send(response);
+                }
+            }
+            xcb::Event::X(Event::ClientMessage(ev)) if ev.window() == dialog.window => dialog.hide(xcb),
             xcb::Event::X(Event::ClientMessage(ev)) => {
                 if let ClientMessageData::Data32([atom, ..]) = ev.data() {
                     if atom == xcb.atoms.wm_del_window.resource_id() {
-                        break Ok(());
+                        break;
                     }
                 }
             }
             _ => {}
         }
     }
-}
 
-fn update(xcb: &XCBObjects, render: &Render, from: DVec2, to: DVec2, last_update: &mut Instant, force: bool) -> Option<(WindowGeometry)> {
-    let now = Instant::now();
-    if force || now - *last_update > Duration::from_millis(16) {
-        let geometry = compute_window_geometry(from, to);
-        render.resize(geometry.w as i32, geometry.h as i32);
-        xcb.conn.send_request(&ConfigureWindow {
-            window: xcb.window,
-            value_list: &[
-                ConfigWindow::X(geometry.x as i32),
-                ConfigWindow::Y(geometry.y as i32),
-                ConfigWindow::Width(geometry.w as u32),
-                ConfigWindow::Height(geometry.h as u32)
-            ],
-        });
-        *last_update = now;
-        Some(geometry)
+    let measurement = state.measurement(view.px_per_mm);
+    if let Some(format) = args.region {
+        println!("{}", Region::from(&measurement).line(format));
+    } else if args.json {
+        println!("{}", Report::from(&measurement).line(StreamFormat::Json));
     } else {
-        None
+        println!("{}", settings.format.render(&measurement));
+    }
+    remember(&settings, &measurement);
+    if settings.performance.frame_stats {
+        view.stats.borrow().log_summary();
+    }
+
+    Ok(())
+}
+
+fn remember(settings: &Settings, measurement: &Measurement) {
+    if settings.history {
+        if let Err(e) = history::append(measurement) {
+            warn!("Failed to record the measurement in the history: {}", e);
+        }
     }
 }
 
-fn redraw(render: &Render, from: DVec2, to: DVec2) -> Result<(), Box<dyn Error>> {
-    let geometry = compute_window_geometry(from, to);
+/// Takes over the unit and profile published by another ruler, returning
+/// whether either changed. Profiles this config does not have are skipped.
+fn adopt(shared: Shared, config: &Config, profile: &mut Option<String>, unit: &mut Option<Unit>) -> bool {
+    let mut changed = shared.unit != *unit;
+    *unit = shared.unit;
+    match shared.profile {
+        Some(name) if !config.has_profile(&name) => warn!("Not switching to profile '{}' of another ruler, there is no such profile", name),
+        shared => {
+            changed |= shared != *profile;
+            *profile = shared;
+        }
+    }
+    changed
+}
+
+fn share(xcb: &XCBObjects, settings: &Settings, profile: Option<&str>, unit: Option<Unit>) {
+    if settings.sync {
+        let shared = Shared { unit, profile: profile.map(str::to_string) };
+        if let Err(e) = sync::publish(&xcb.conn, xcb.screen.root(), xcb.atoms.ruler_sync, &shared) {
+            warn!("Failed to share the state with other rulers: {}", e);
+        }
+    }
+}
+
+fn resolve_settings(config: &Config, profile: Option<&str>, args: &Args) -> Result<Settings, Box<dyn Error>> {
+    let mut settings = config.settings(profile)?;
+    args.apply(&mut settings);
+    Ok(settings)
+}
+
+fn place(xcb: &XCBObjects, startup: &Startup, length: f64, screen_size: DVec2) -> Result<(DVec2, DVec2), Box<dyn Error>> {
+    let direction = startup.orientation.direction();
+    let (from, to) = match startup.anchor {
+        Anchor::Center => {
+            let center = screen_size / 2.0;
+            (center - direction * length / 2.0, center + direction * length / 2.0)
+        }
+        Anchor::Cursor => {
+            let cursor = xcb.query_pointer()?;
+            (cursor, cursor + direction * length)
+        }
+    };
+
+    Ok((from.clamp(DVec2::ZERO, screen_size), to.clamp(DVec2::ZERO, screen_size)))
+}
+
+/// Moves and resizes the window to fit the ruler, at most as often as
+/// `max_fps` allows unless forced. Moves that leave the size as it was skip
+/// the surface resize and only send the new position.
+fn update(xcb: &XCBObjects, render: &Render, state: &RulerState, settings: &Settings, view: &View, frame: &mut Frame, force: bool) -> Result<Option<WindowGeometry>, Box<dyn Error>> {
+    let now = Instant::now();
+    if !force && now - frame.time < settings.performance.min_interval() {
+        return Ok(None);
+    }
+    let geometry = compute_window_geometry(state, &settings.sizes);
     let pos = geometry.pos().as_dvec2();
-    draw(&render.ctx, from - pos, to - pos)?;
+    let endpoints = (state.from - pos, state.to - pos);
+    match frame.geometry {
+        Some(last) if (last.w, last.h) == (geometry.w, geometry.h) => {
+            if last != geometry {
+                xcb.conn.send_request(&ConfigureWindow {
+                    window: xcb.window,
+                    value_list: &[ConfigWindow::X(geometry.x as i32), ConfigWindow::Y(geometry.y as i32)],
+                });
+            }
+            if endpoints != frame.endpoints {
+                // The picture changed within a window of the same size, which
+                // the server would not ask to repaint on its own.
+                xcb.request_redraw();
+            }
+        }
+        _ => {
+            render.resize(geometry.w as i32, geometry.h as i32)?;
+            xcb.conn.send_request(&ConfigureWindow {
+                window: xcb.window,
+                value_list: &[
+                    ConfigWindow::X(geometry.x as i32),
+                    ConfigWindow::Y(geometry.y as i32),
+                    ConfigWindow::Width(geometry.w as u32),
+                    ConfigWindow::Height(geometry.h as u32)
+                ],
+            });
+        }
+    }
+    (frame.time, frame.geometry, frame.endpoints) = (now, Some(geometry), endpoints);
+    frame.guides.follow(xcb, state);
+    frame.grid.follow(xcb, state, settings);
+    frame.construction.follow(xcb, state);
+    frame.baselines.follow(xcb, state);
+    frame.marker.follow(xcb, &render.ctx, state, settings, view.px_per_mm)?;
+    Ok(Some(geometry))
+}
+
+fn reshape(xcb: &XCBObjects, render: &Render, state: &RulerState, settings: &Settings, view: &View, frame: &mut Frame) -> Result<(), Box<dyn Error>> {
+    let geometry = update(xcb, render, state, settings, view, frame, true)?.unwrap();
+    shape(xcb, state, settings, view, geometry);
     Ok(())
 }
 
-fn compute_window_geometry(from: DVec2, to: DVec2) -> WindowGeometry {
-    let min_x = from.x.min(to.x) - RULER_HALF_WIDTH;
-    let max_x = from.x.max(to.x) + RULER_HALF_WIDTH;
-    let min_y = from.y.min(to.y) - RULER_HALF_WIDTH;
-    let max_y = from.y.max(to.y) + RULER_HALF_WIDTH;
-    WindowGeometry {
-        x: min_x as i16,
-        y: min_y as i16,
-        w: (max_x - min_x) as u16,
-        h: (max_y - min_y) as u16,
+/// Limits input to the handles of a window at `geometry`. Docked rulers have
+/// no handles, so they keep the default input shape covering the whole window.
+fn shape(xcb: &XCBObjects, state: &RulerState, settings: &Settings, view: &View, geometry: WindowGeometry) {
+    if !view.docked() {
+        let pos = geometry.pos().as_dvec2();
+        let region: Vec<Rect> = state.input_region(settings.sizes.control_radius).iter().map(|rect| Rect { min: rect.min - pos, max: rect.max - pos }).collect();
+        xcb.set_input_shape(xcb.window, &region);
     }
 }
 
-fn handle_drag(dragging: &mut DVec2, other: DVec2, cursor: DVec2, screen_size: DVec2, fix_distance: bool, fix_angle: bool) {
-    let mut new_vec = cursor;
+fn refresh(xcb: &XCBObjects, render: &Render, state: &RulerState, settings: &Settings, view: &View, frame: &mut Frame) -> Result<(), Box<dyn Error>> {
+    reshape(xcb, render, state, settings, view, frame)?;
+    redraw(render, state, settings, view)?;
+    Ok(())
+}
 
-    if fix_distance {
-        let new_diff_normalized = (new_vec - other).try_normalize().unwrap_or(DVec2::new(1.0, 0.0));
-        let old_distance = dragging.distance(other);
-        new_vec = other + new_diff_normalized * old_distance;
+/// Refreshes the window after the settings changed, keeping a docked ruler
+/// flush with its edge as its width changes.
+fn apply_settings(xcb: &XCBObjects, render: &Render, state: &mut RulerState, settings: &Settings, view: &mut View, frame: &mut Frame) -> Result<(), Box<dyn Error>> {
+    #[cfg(feature = "scripting")]
+    {
+        view.overlay = load_overlay(settings);
+    }
+    view.px_per_mm = screen_density(xcb, settings, state.from);
+    if let Some(edge) = view.dock {
+        xcb.dock(edge, (settings.sizes.half_width * 2.0) as u32, state.screen_size)?;
+        (state.from, state.to) = edge.endpoints(settings.sizes.half_width, state.screen_size);
+    }
+    state.bounds = bounds(xcb, settings.drag.confine_to, state.from, state.screen_size);
+    state.body = settings.drag.body.then_some(settings.sizes.half_width);
+    frame.guides.set_color(xcb, &settings.theme);
+    frame.grid.invalidate();
+    frame.construction.invalidate();
+    frame.baselines.reset(settings);
+    frame.marker.invalidate();
+    refresh(xcb, render, state, settings, view, frame)
+}
 
-        new_vec = closest_point_below_line_on_circle(other, old_distance, DVec2::ZERO, DVec2::X, new_vec);
-        new_vec = closest_point_below_line_on_circle(other, old_distance, screen_size, DVec2::X, new_vec);
-        new_vec = closest_point_below_line_on_circle(other, old_distance, DVec2::ZERO, DVec2::Y, new_vec);
-        new_vec = closest_point_below_line_on_circle(other, old_distance, screen_size, DVec2::Y, new_vec);
+/// Pixels a nudge moves by with `toggle_precise` on, rather than a whole one.
+const PRECISE_NUDGE: f64 = 0.25;
+
+/// How long after the ruler is redrawn its colors are sampled, for the
+/// compositor to have put the new frame, with its holes, on the screen.
+const SAMPLE_SETTLE: Duration = Duration::from_millis(50);
+
+/// Reads the colors under the endpoints and the luminance along the line,
+/// whichever are on, through the holes the ruler leaves there, and shows
+/// them if they changed.
+fn sample(xcb: &XCBObjects, render: &Render, state: &RulerState, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    let mut changed = false;
+    if view.sampling {
+        let pixels = capture::pixels(xcb, &[state.from, state.to])?;
+        let samples = [pixels[0], pixels[1]];
+        changed |= samples != view.samples.get();
+        view.samples.set(samples);
+    }
+    if view.luminance {
+        let profile = capture::pixels(xcb, &line_pixels(state.from, state.to))?.into_iter().map(|pixel| pixel.map(|pixel| pixel.luminance())).collect::<Vec<_>>();
+        changed |= profile != *view.profile.borrow();
+        *view.profile.borrow_mut() = profile;
     }
+    if changed {
+        redraw(render, state, settings, view)?;
+    }
+    Ok(())
+}
 
-    if fix_angle {
-        let old_diff_normalized = (*dragging - other).try_normalize().unwrap_or(DVec2::X);
-        new_vec = other + old_diff_normalized * new_vec.distance(other);
+/// Captures the screen under the start of the line for the zoomed strip
+/// once the ruler has come to rest somewhere new, hiding the ruler to see
+/// what it covers. Turns the strip off if the screen cannot be captured.
+fn capture_strip(xcb: &XCBObjects, render: &Render, state: &RulerState, settings: &Settings, view: &mut View) -> Result<(), Box<dyn Error>> {
+    if state.is_dragging() || view.strip.as_ref().is_some_and(|strip| strip.endpoints == (state.from, state.to)) {
+        return Ok(());
+    }
+    let zoom = settings.loupe.zoom() as f64;
+    let area = Strip::area(state.from, state.to, settings.sizes.half_width, zoom);
+    let (min, max) = (area.min.max(DVec2::ZERO), area.max.min(state.screen_size));
+    if min.cmpge(max).any() {
+        return Ok(());
+    }
+    let geometry = WindowGeometry { x: coordinate(min.x, "strip x"), y: coordinate(min.y, "strip y"), w: extent(max.x - min.x, "strip width"), h: extent(max.y - min.y, "strip height") };
+    match xcb.hidden(|| capture::capture(xcb, geometry)) {
+        Ok(Ok(image)) => view.strip = Some(Strip { image, origin: min, endpoints: (state.from, state.to), zoom }),
+        Ok(Err(e)) | Err(e) => {
+            warn!("Turning the zoomed strip off, the screen could not be captured: {}", e);
+            view.zoom_strip = false;
+        }
     }
+    redraw(render, state, settings, view)
+}
 
-    if other.distance_squared(new_vec) < MIN_LENGTH.powi(2) {
-        let diff_normalized = (new_vec - other).try_normalize().unwrap_or(DVec2::X);
-        new_vec = other + diff_normalized * MIN_LENGTH;
+/// Moves the dragged endpoint onto the strongest edge on the screen within
+/// `reach` of it, seen through the hole the ruler leaves there.
+fn snap_to_edge(xcb: &XCBObjects, state: &mut RulerState, reach: usize, constraints: Constraints) {
+    let Some(endpoint) = state.dragged_endpoint() else { return };
+    // A pixel more each way, for the changes to the pixels at the rim.
+    let pixels = Patch::pixels(endpoint, reach + 1);
+    match capture::pixels(xcb, &pixels) {
+        Ok(colors) => {
+            let patch = Patch::new(endpoint, reach + 1, colors.into_iter().map(|color| color.map(|color| color.luminance())).collect());
+            if let Some(snapped) = patch.snap(endpoint, reach as f64) {
+                state.drag_to(snapped, constraints);
+            }
+        }
+        Err(e) => warn!("Not snapping to an edge, the screen could not be captured: {}", e),
     }
+}
 
-    *dragging = new_vec.clamp(DVec2::ZERO, screen_size);
+/// Pixels per millimeter at `point` from the `px_per_mm` setting, or else
+/// the size configured for the monitor it is on, or else the saved
+/// calibration, or else the monitor's EDID or what its driver reports, or
+/// else the X server's figure for the whole screen.
+fn screen_density(xcb: &XCBObjects, settings: &Settings, point: DVec2) -> DVec2 {
+    if let Some(px_per_mm) = settings.px_per_mm {
+        return DVec2::splat(px_per_mm);
+    }
+    let outputs = xcb.outputs().unwrap_or_else(|e| {
+        warn!("Failed to list the monitors: {}", e);
+        Vec::new()
+    });
+    let output = outputs.into_iter().find(|output| output.rect.contains(point));
+    let per_mm = |output: &Output, size_mm: DVec2| size_mm.cmpgt(DVec2::ZERO).all().then(|| (output.rect.max - output.rect.min) / size_mm);
+    if let Some((output, size)) = output.as_ref().and_then(|output| Some((output, settings.outputs.get(&output.name)?))) {
+        match per_mm(output, DVec2::from(size.size_mm)) {
+            Some(px_per_mm) => return px_per_mm,
+            None => warn!(output = output.name, "Ignoring the configured size of the monitor, it must be positive"),
+        }
+    }
+    if let Some(px_per_mm) = calibration::load() {
+        return DVec2::splat(px_per_mm);
+    }
+    output.and_then(|output| output.edid_size_mm.and_then(|size_mm| per_mm(&output, size_mm)).or_else(|| per_mm(&output, output.size_mm)))
+        .unwrap_or_else(|| xcb.px_per_mm())
 }
 
-fn draw(ctx: &Context, from: DVec2, to: DVec2) -> Result<(), Box<dyn Error>> {
-    let opacity = 0.6;
-    let bg = 1.0;
-    let accent = 0.7;
+/// Saves the density a ruler `length_px` long along the calibration's
+/// reference object gives, and returns it.
+fn calibrate(length_px: f64, settings: &Settings) -> Result<f64, Box<dyn Error>> {
+    let px_per_mm = calibration::px_per_mm(length_px, settings.calibration.reference_mm)?;
+    let path = calibration::save(px_per_mm)?;
+    info!(px_per_mm, path = %path.display(), "saved the calibration");
+    Ok(px_per_mm)
+}
 
-    ctx.set_operator(Operator::Source);
-    ctx.set_source_rgba(0.0, 0.0, 0.0, 0.0);
-    ctx.paint()?;
+/// Puts the ruler across `window` the way `span` says, remembering it for
+/// `measure_window` to measure it the next way.
+fn lay_across(state: &mut RulerState, settings: &Settings, window: Rect, span: Span) -> Target {
+    let (from, to) = span.endpoints(&window);
+    state.set(Some(from), Some(to), state.clamp_to_screen(settings.drag.clamp_to_screen));
+    debug!(?window, ?span, "measured a window");
+    Target { window, span, endpoints: (state.from, state.to) }
+}
 
-    ctx.save()?;
+/// Has the user click a window to measure the width of, `None` if they
+/// cancel or there is no window there.
+fn pick_window(xcb: &XCBObjects) -> Option<(Rect, Span)> {
+    pick_windows(xcb, 1).map(|windows| (windows[0], Span::Width))
+}
 
-    ctx.set_line_width(2.0);
+/// Has the user click `count` windows, `None` if they cancel or any click
+/// misses.
+fn pick_windows(xcb: &XCBObjects, count: usize) -> Option<Vec<Rect>> {
+    let picked = pick::pick_points(xcb, count).and_then(|points| {
+        let windows = xcb.windows()?;
+        Ok(points.iter().map(|&point| window_at(&windows, point)).collect::<Option<Vec<_>>>())
+    });
+    match picked {
+        Ok(Some(windows)) => Some(windows),
+        Ok(None) => {
+            debug!("no window to measure there");
+            None
+        }
+        Err(e) if e.is::<PickCancelledError>() => {
+            debug!("measuring windows was cancelled");
+            None
+        }
+        Err(e) => {
+            warn!("Failed to pick windows to measure: {}", e);
+            None
+        }
+    }
+}
 
-    ctx.translate(from.x, from.y);
-    let angle = DVec2::X.angle_between(to - from);
-    ctx.rotate(angle);
+/// Shows the loupe beside the dragged endpoint while it is on, or hides it.
+/// A screen that cannot be captured, as under XWayland without the portal,
+/// turns it off.
+fn follow_loupe(xcb: &XCBObjects, loupe: &mut LoupeWindow, render: &Render, state: &RulerState, settings: &Settings, view: &mut View) {
+    match state.dragged_endpoint() {
+        Some(endpoint) if view.loupe => {
+            if let Err(e) = loupe.follow(xcb, render, endpoint, state.screen_size, settings) {
+                warn!("Turning the loupe off, the screen could not be captured: {}", e);
+                view.loupe = false;
+                loupe.hide(xcb);
+            }
+        }
+        _ => loupe.hide(xcb),
+    }
+}
 
-    let length = from.distance(to);
-    let length_pixels = length as u32;
+/// Puts the distance from the pointer to the line beside it, unless the
+/// ruler is being dragged and the pointer is on it anyway.
+fn follow_pointer(xcb: &XCBObjects, hud: &mut Hud, render: &Render, state: &RulerState, settings: &Settings, view: &View) {
+    if !hud.enabled || state.is_dragging() {
+        hud.hide(xcb);
+        return;
+    }
+    let shown = xcb.query_pointer().and_then(|pointer| hud.follow(xcb, render, pointer, state, view.px_per_mm, settings));
+    if let Err(e) = shown {
+        debug!("Failed to show the distance to the pointer: {}", e);
+    }
+}
 
-    ctx.rectangle(0.0, -RULER_HALF_WIDTH, length, RULER_HALF_WIDTH * 2.0);
-    ctx.set_source_rgba(bg, bg, bg, opacity);
-    ctx.fill()?;
+fn redraw(render: &Render, state: &RulerState, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    let geometry = compute_window_geometry(state, &settings.sizes);
+    let start = Instant::now();
+    draw(&render.ctx, state, geometry.pos().as_dvec2(), settings, view)?;
+    let elapsed = start.elapsed();
+    view.stats.borrow_mut().drew(elapsed);
+    trace!(?elapsed, w = geometry.w, h = geometry.h, "redrew");
+    Ok(())
+}
 
-    ctx.rectangle(0.0, -RULER_HALF_WIDTH, length, RULER_HALF_WIDTH * 2.0);
-    ctx.set_source_rgba(accent, accent, accent, opacity);
-    ctx.stroke()?;
+/// Adds a ruler beside the last one and shows it.
+fn new_ruler(app: &App, extras: &mut Extras, state: &RulerState, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    let window = app.xcb.create_ruler(1, 1)?;
+    app.open(window, Role::Extra, 1, 1)?;
+    let extra = extras.add(window, state, settings.sizes.half_width, view.px_per_mm);
+    let render = app.render_of(window).expect("the ruler was just opened");
+    place_extra(&app.xcb, &render, extra, settings)?;
+    checked(&app.xcb.conn, &MapWindow { window })?;
+    debug!(from = ?extra.state.from, to = ?extra.state.to, "added a ruler");
+    Ok(())
+}
 
-    ctx.set_source_rgba(bg, bg, bg, opacity);
+fn close_extra(app: &App, extras: &mut Extras, window: Window) {
+    if extras.remove(window).is_some() {
+        app.close(window);
+        app.xcb.conn.send_request(&DestroyWindow { window });
+    }
+}
 
-    ctx.arc(0.0, 0.0, CONTROL_RADIUS, 0.0, PI * 2.0);
-    ctx.fill()?;
+/// Handles `event` if it is for one of the added rulers, returning whether
+/// it was. They are dragged like the first, without its snapping or any of
+/// the windows following it.
+fn handle_extra(app: &App, extras: &mut Extras, event: &xcb::Event, settings: &Settings, view: &View) -> Result<bool, Box<dyn Error>> {
+    let window = match event {
+        xcb::Event::X(Event::Expose(ev)) => ev.window(),
+        xcb::Event::X(Event::ButtonPress(ev)) => ev.event(),
+        xcb::Event::X(Event::ButtonRelease(ev)) => ev.event(),
+        xcb::Event::X(Event::MotionNotify(ev)) => ev.event(),
+        xcb::Event::X(Event::LeaveNotify(ev)) => ev.event(),
+        xcb::Event::X(Event::ClientMessage(ev)) => ev.window(),
+        _ => return Ok(false),
+    };
+    let (Some(extra), Some(render)) = (extras.get_mut(window), app.render_of(window)) else { return Ok(false) };
+    let xcb = &app.xcb;
+    let grab_distance = settings.sizes.half_width * 2.0;
+    match event {
+        // Drawn in full, so only once the last exposed part arrives.
+        xcb::Event::X(Event::Expose(ev)) if ev.count() == 0 => redraw_extra(&render, extra, settings, view)?,
+        xcb::Event::X(Event::ButtonPress(ev)) if ev.detail() == 1 => {
+            extra.state.grab(DVec2::new(ev.root_x() as f64, ev.root_y() as f64), grab_distance, ev.detail());
+        }
+        xcb::Event::X(Event::MotionNotify(ev)) if !extra.state.is_dragging() && extra.state.hover(Some(DVec2::new(ev.root_x() as f64, ev.root_y() as f64)), grab_distance) => {
+            redraw_extra(&render, extra, settings, view)?;
+        }
+        xcb::Event::X(Event::LeaveNotify(_)) if extra.state.hover(None, grab_distance) => redraw_extra(&render, extra, settings, view)?,
+        xcb::Event::X(Event::MotionNotify(ev)) => {
+            let constraints = Constraints {
+                fix_distance: settings.drag.fix_distance.held(ev.state()),
+                fix_angle: settings.drag.fix_angle.held(ev.state()),
+                symmetric: settings.drag.symmetric.held(ev.state()),
+                clamp_to_screen: extra.state.clamp_to_screen(settings.drag.clamp_to_screen),
+                min_length: settings.sizes.min_length,
+                snap_angle: settings.drag.snap_angle,
+                snap_tolerance: settings.drag.snap_tolerance,
+                snap_to_pixels: settings.drag.snap_to_pixels,
+            };
+            let constraints = if settings.drag.ignore_limits.held(ev.state()) { constraints.ignoring_limits() } else { constraints };
+            if extra.state.drag(DVec2::new(ev.root_x() as f64, ev.root_y() as f64), constraints) {
+                place_extra(xcb, &render, extra, settings)?;
+            }
+        }
+        xcb::Event::X(Event::ButtonRelease(ev)) if extra.state.release(ev.detail()) => {
+            extra.px_per_mm = screen_density(xcb, settings, extra.state.from);
+            place_extra(xcb, &render, extra, settings)?;
+        }
+        xcb::Event::X(Event::ClientMessage(ev)) => {
+            if let ClientMessageData::Data32([atom, ..]) = ev.data() {
+                if atom == xcb.atoms.wm_del_window.resource_id() {
+                    close_extra(app, extras, window);
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(true)
+}
 
-    ctx.arc(length, 0.0, CONTROL_RADIUS, 0.0, PI * 2.0);
-    ctx.fill()?;
+/// Moves and resizes an added ruler's window to fit it, limiting input to
+/// its handles, and has it drawn again.
+fn place_extra(xcb: &XCBObjects, render: &Render, extra: &Extra, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let geometry = compute_window_geometry(&extra.state, &settings.sizes);
+    render.resize(geometry.w as i32, geometry.h as i32)?;
+    xcb.conn.send_request(&ConfigureWindow {
+        window: extra.window,
+        value_list: &[
+            ConfigWindow::X(geometry.x as i32),
+            ConfigWindow::Y(geometry.y as i32),
+            ConfigWindow::Width(geometry.w as u32),
+            ConfigWindow::Height(geometry.h as u32),
+        ],
+    });
+    let pos = geometry.pos().as_dvec2();
+    let region: Vec<Rect> = extra.state.input_region(settings.sizes.control_radius).iter().map(|rect| Rect { min: rect.min - pos, max: rect.max - pos }).collect();
+    xcb.set_input_shape(extra.window, &region);
+    xcb.conn.send_request(&ClearArea { exposures: true, window: extra.window, x: 0, y: 0, width: 0, height: 0 });
+    Ok(())
+}
 
-    ctx.set_source_rgba(accent, accent, accent, opacity);
+/// Draws an added ruler in its accent, with none of the readouts that only
+/// the first has.
+fn redraw_extra(render: &Render, extra: &Extra, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    let (settings, view) = extra_look(extra, settings, view);
+    redraw(render, &extra.state, &settings, &view)
+}
 
-    ctx.arc(0.0, 0.0, CONTROL_RADIUS, PI * 0.5, PI * 1.5);
-    ctx.stroke()?;
+/// The settings and view an extra ruler is drawn with: its own accent and
+/// density, without the readouts only the first ruler has.
+fn extra_look(extra: &Extra, settings: &Settings, view: &View) -> (Settings, View) {
+    let mut settings = settings.clone();
+    settings.theme.accent = extra.accent;
+    let view = View {
+        sampling: false,
+        samples: Default::default(),
+        luminance: false,
+        profile: Default::default(),
+        gap: None,
+        calibrating: false,
+        zoom_strip: false,
+        strip: None,
+        committed: Default::default(),
+        reference: None,
+        reference_angle: None,
+        flip_ticks: false,
+        precise: false,
+        dock: None,
+        px_per_mm: extra.px_per_mm,
+        labels: Default::default(),
+        loupe: false,
+        edge_snap: None,
+        ..view.clone()
+    };
+    (settings, view)
+}
 
-    ctx.arc(length, 0.0, CONTROL_RADIUS, PI * 1.5, PI * 0.5);
-    ctx.stroke()?;
+#[cfg(feature = "scripting")]
+fn load_overlay(settings: &Settings) -> Option<Rc<Overlay>> {
+    let path = settings.overlay_script.as_deref()?;
+    Overlay::load(path).map(Rc::new).map_err(|e| warn!("Failed to load the overlay script {}: {}", path.display(), e)).ok()
+}
 
-    ctx.set_font_size(14.0);
+/// Writes what is shown on the screen of the ruler to `path`: the overlays
+/// beneath, then the marker, the extra rulers and the ruler itself on top.
+fn export_svg(path: &Path, state: &RulerState, extras: &Extras, frame: &Frame, settings: &Settings, view: &View) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let surface = SvgSurface::new(state.screen_size.x, state.screen_size.y, Some(path))?;
+    let ctx = Context::new(&surface)?;
+    frame.grid.export(&ctx, state, settings)?;
+    frame.baselines.export(&ctx, state, settings)?;
+    frame.construction.export(&ctx, state, settings)?;
+    frame.guides.export(&ctx, state, &settings.theme)?;
+    frame.marker.export(&ctx, settings)?;
+    // Each ruler in a group of its own, since drawing one starts by clearing
+    // everything below it.
+    let layer = |state: &RulerState, settings: &Settings, view: &View| -> Result<(), Box<dyn Error>> {
+        ctx.push_group();
+        let drawn = draw(&ctx, state, DVec2::ZERO, settings, view);
+        ctx.pop_group_to_source()?;
+        ctx.paint()?;
+        drawn
+    };
+    for extra in &extras.rulers {
+        let (settings, view) = extra_look(extra, settings, view);
+        layer(&extra.state, &settings, &view)?;
+    }
+    layer(state, settings, view)?;
+    surface.finish();
+    Ok(())
+}
 
-    for i in (0..length_pixels).step_by(5) {
-        let inner_width = RULER_HALF_WIDTH - match i % 50 {
-            0 => 17.0,
-            25 => 12.0,
-            _ => 7.0
-        };
+fn capture_dir(settings: &Settings) -> PathBuf {
+    settings.screenshot_dir.clone().or_else(xdg::pictures_dir).unwrap_or_default()
+}
 
-        ctx.line_to(i as f64, -inner_width);
-        ctx.line_to(i as f64, -RULER_HALF_WIDTH);
-        ctx.stroke()?;
+/// Where the ruler goes `length` long and level across the middle of the
+/// monitor its own middle is on, or else the one under the pointer, keeping
+/// to the monitor's width.
+fn centered(xcb: &XCBObjects, state: &RulerState, length: f64) -> Result<(DVec2, DVec2), Box<dyn Error>> {
+    let monitors = monitor_rects(xcb, state.screen_size);
+    let middle = (state.from + state.to) / 2.0;
+    let monitor = match monitors.iter().find(|monitor| monitor.contains(middle)) {
+        Some(monitor) => *monitor,
+        None => {
+            let cursor = xcb.query_pointer()?;
+            monitors.iter().find(|monitor| monitor.contains(cursor)).copied().unwrap_or(Rect::from_size(state.screen_size))
+        }
+    };
+    let center = (monitor.min + monitor.max) / 2.0;
+    let half = DVec2::X * length.min(monitor.max.x - monitor.min.x) / 2.0;
+    Ok((center - half, center + half))
+}
+
+/// The monitors making up the screen, or the whole screen as one without
+/// RandR 1.5.
+fn monitor_rects(xcb: &XCBObjects, screen_size: DVec2) -> Vec<Rect> {
+    let monitors = xcb.monitors().unwrap_or_else(|e| {
+        warn!("Failed to list the monitors: {}", e);
+        Vec::new()
+    });
+    if monitors.is_empty() {
+        return vec![Rect::from_size(screen_size)];
     }
+    monitors.into_iter().map(|monitor| Rect { min: monitor.pos().as_dvec2(), max: monitor.pos().as_dvec2() + DVec2::new(monitor.w as f64, monitor.h as f64) }).collect()
+}
 
-    ctx.save()?;
-    ctx.translate(30.0, RULER_HALF_WIDTH - 30.0);
+/// The part of the screen clamped endpoints stay in, `anchor` being the
+/// endpoint that stays in place. The screen is read again every time, since
+/// monitors may have been plugged in or rearranged since the last drag.
+fn bounds(xcb: &XCBObjects, confinement: Confinement, anchor: DVec2, screen_size: DVec2) -> Rect {
+    let rect = |geometry: WindowGeometry| {
+        let min = geometry.pos().as_dvec2();
+        Rect { min, max: min + DVec2::new(geometry.w as f64, geometry.h as f64) }
+    };
+    match confinement {
+        Confinement::Desktop => xcb.get_window_geometry(xcb.screen.root()).map(rect).unwrap_or_else(|e| {
+            warn!("Failed to read the screen size, keeping the one from startup: {}", e);
+            Rect::from_size(screen_size)
+        }),
+        Confinement::Monitor => {
+            let monitors = xcb.monitors().unwrap_or_else(|e| {
+                warn!("Failed to list the monitors: {}", e);
+                Vec::new()
+            });
+            match monitors.into_iter().map(rect).find(|monitor| monitor.contains(anchor)) {
+                Some(monitor) => monitor,
+                None => bounds(xcb, Confinement::Desktop, anchor, screen_size),
+            }
+        }
+        Confinement::Rect([x, y, width, height]) => Rect { min: DVec2::new(x, y), max: DVec2::new(x + width, y + height) },
+    }
+}
 
-    ctx.line_to(0.0, 0.0);
-    ctx.line_to(30.0, 0.0);
-    ctx.stroke()?;
+fn compute_window_geometry(state: &RulerState, sizes: &Sizes) -> WindowGeometry {
+    let RulerState { from, to, .. } = *state;
+    // The arm's end is the one point of it that can be off the ruler's box.
+    let end = state.arm_points().map_or(from, |(_, end)| end);
+    let min_x = from.x.min(to.x).min(end.x) - sizes.half_width;
+    let max_x = from.x.max(to.x).max(end.x) + sizes.half_width;
+    let min_y = from.y.min(to.y).min(end.y) - sizes.half_width;
+    let max_y = from.y.max(to.y).max(end.y) + sizes.half_width;
+    WindowGeometry {
+        x: coordinate(min_x, "window x"),
+        y: coordinate(min_y, "window y"),
+        w: extent(max_x - min_x, "window width"),
+        h: extent(max_y - min_y, "window height"),
+    }
+}
 
-    ctx.line_to(0.0, 0.0);
-    let horizontal = DVec2::from_angle(angle) * 30.0;
-    ctx.line_to(horizontal.x, -horizontal.y);
-    ctx.stroke()?;
+/// Hides the ruler once it has gone `auto_hide_minutes` without use since
+/// `last_used`, or has the timer wake the loop up when it will have.
+fn auto_hide(xcb: &XCBObjects, events: &EventLoop, settings: &Settings, hidden: &mut Option<Vec<(Keycode, ModMask)>>, companions: &[Window], keyboard: &Keyboard, last_used: Instant) -> Result<(), Box<dyn Error>> {
+    if settings.auto_hide_minutes <= 0.0 {
+        return Ok(());
+    }
+    let after = Duration::from_secs_f64(settings.auto_hide_minutes * 60.0);
+    match after.checked_sub(last_used.elapsed()) {
+        Some(remaining) if !remaining.is_zero() => events.schedule_within(remaining)?,
+        _ => {
+            info!(minutes = settings.auto_hide_minutes, "hiding after a while without use");
+            toggle(xcb, hidden, companions, keyboard, settings);
+        }
+    }
+    Ok(())
+}
 
-    ctx.arc(0.0, 0.0, 16.0, 0.0, -angle);
-    ctx.stroke()?;
+/// The windows shown along with the ruler, to hide and show with it.
+fn companions(frame: &Frame, extras: &Extras) -> Vec<Window> {
+    let overlays = [
+        (frame.grid.window, frame.grid.shown()),
+        (frame.construction.window, frame.construction.shown()),
+        (frame.baselines.window, frame.baselines.shown()),
+        (frame.marker.window, frame.marker.shown()),
+    ];
+    let overlays = overlays.into_iter().filter(|&(_, shown)| shown).map(|(window, _)| window);
+    frame.guides.shown_windows().iter().copied().chain(overlays).chain(extras.rulers.iter().map(|extra| extra.window)).collect()
+}
 
-    let display_angle = if angle > 0.0 { PI * 2.0 - angle } else { angle.abs() } * 180.0 / PI;
-    let angle_string = format!("{:.2}°", display_angle);
-    let extents = ctx.text_extents(&angle_string)?;
-    ctx.translate(35.0, extents.height());
-    ctx.text_path(&angle_string);
-    ctx.fill()?;
-    ctx.restore()?;
+/// Hides the ruler and its `companions`, grabbing the `recall` keys on the
+/// root window so they bring it back from anywhere, or shows them again and
+/// lets go of the keys.
+fn toggle(xcb: &XCBObjects, hidden: &mut Option<Vec<(Keycode, ModMask)>>, companions: &[Window], keyboard: &Keyboard, settings: &Settings) {
+    let root = xcb.screen.root();
+    match hidden.take() {
+        Some(grabs) => {
+            for (key, modifiers) in grabs {
+                xcb.conn.send_request(&UngrabKey { key, grab_window: root, modifiers });
+            }
+            for &window in [xcb.window].iter().chain(companions) {
+                xcb.conn.send_request(&MapWindow { window });
+            }
+        }
+        None => {
+            for &window in [xcb.window].iter().chain(companions) {
+                xcb.conn.send_request(&UnmapWindow { window });
+            }
+            let bindings = settings.keymap.get(&Action::Recall).map_or(&[][..], Vec::as_slice);
+            let grabs = keyboard.grabs(bindings);
+            for &(key, modifiers) in &grabs {
+                let grab = GrabKey { owner_events: false, grab_window: root, modifiers, key, pointer_mode: GrabMode::Async, keyboard_mode: GrabMode::Async };
+                if let Err(e) = checked(&xcb.conn, &grab) {
+                    warn!("The recall key could not be grabbed, another client may have it: {}", e);
+                    break;
+                }
+            }
+            *hidden = Some(grabs);
+        }
+    }
+}
+
+/// The window as it was last updated, and the guides, grid, construction
+/// lines and baselines following it.
+struct Frame {
+    time: Instant,
+    geometry: Option<WindowGeometry>,
+    /// The endpoints relative to the window, which a pure move leaves as they are.
+    endpoints: (DVec2, DVec2),
+    /// Moved along with the window.
+    guides: Guides,
+    grid: Grid,
+    construction: Construction,
+    baselines: Baselines,
+    marker: Marker,
+}
 
-    ctx.translate(50.0, -7.0);
+/// Everything following the ruler's state: control subscribers and the
+/// `--watch`, `--bar-fifo` and `--record` outputs.
+struct Observers {
+    subscribers: Vec<Sender<String>>,
+    watch: Option<Watch>,
+    bar: Option<Bar>,
+    recorder: Option<Recorder>,
+    #[cfg(feature = "http")]
+    http: Option<Endpoint>,
+}
 
-    for i in (0..length_pixels).step_by(50).skip(1) {
-        let str = i.to_string();
-        let extents = ctx.text_extents(&str)?;
-        ctx.translate(-extents.width() / 2.0, 0.0);
-        ctx.text_path(&str);
-        ctx.translate(50.0 + extents.width() / 2.0, 0.0);
-        let visibility = ((length - i as f64) / 50.0).min(1.0);
-        let color = accent * visibility + bg * (1.0 - visibility);
-        ctx.set_source_rgba(color, color, color, opacity);
-        ctx.fill()?;
+impl Observers {
+    /// Passes on an intermediate state, such as one in the middle of a drag.
+    fn moved(&mut self, settings: &Settings, measurement: &Measurement) {
+        if let Some(watch) = &mut self.watch {
+            watch.observe(measurement, false);
+        }
+        self.follow(settings, measurement);
     }
 
-    ctx.restore()?;
+    /// Passes on a finished change to everyone, subscribers included.
+    fn publish(&mut self, settings: &Settings, measurement: &Measurement) {
+        let line = format!("state {}", Report::from(measurement));
+        self.subscribers.retain(|subscriber| subscriber.send(line.clone()).is_ok());
+        if let Some(watch) = &mut self.watch {
+            watch.observe(measurement, true);
+        }
+        self.follow(settings, measurement);
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    fn follow(&mut self, settings: &Settings, measurement: &Measurement) {
+        if let Some(bar) = &mut self.bar {
+            bar.update(settings.format.render(measurement));
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(measurement);
+        }
+        #[cfg(feature = "http")]
+        if let Some(http) = &self.http {
+            http.update(&Report::from(measurement));
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::error::Error;
+
+use cairo::{Context, FontSlant, FontWeight};
+use glam::DVec2;
+use xcb::x::{ClearArea, ConfigWindow, ConfigureWindow, ExposeEvent, MapWindow, StackMode, UnmapWindow, Window};
+
+use ruler::geom::project_onto_line;
+use ruler::state::RulerState;
+
+use crate::backend::{coordinate, extent, Render, WindowGeometry, XCBObjects};
+use crate::config::Settings;
+use crate::hud::distance_text;
+
+/// Radius of the ring around the marker.
+const RING: f64 = 5.0;
+/// Side of the square marking the right angle at the foot.
+const RIGHT_ANGLE: f64 = 7.0;
+/// Gap between the perpendicular and its label.
+const LABEL_GAP: f64 = 6.0;
+const PADDING: f64 = 4.0;
+
+/// A point clicked with `drop_marker` and the perpendicular from it to the
+/// line through the ruler, labelled with its length, in a window of its own
+/// that the pointer passes through.
+pub struct Marker {
+    pub window: Window,
+    point: Option<DVec2>,
+    /// What was laid out and the label, while the window is mapped.
+    shown: Option<(Layout, String)>,
+    /// The endpoints it was last laid out for.
+    at: Option<(DVec2, DVec2)>,
+}
+
+/// Where the parts of a marker go on the screen.
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    point: DVec2,
+    /// Where the perpendicular meets the line.
+    foot: DVec2,
+    /// The end of the ruler the line is extended from to reach the foot,
+    /// when the foot is past it.
+    extended_from: Option<DVec2>,
+    /// The top left of the label's box, and its size.
+    label: (DVec2, DVec2),
+    window: WindowGeometry,
+}
+
+impl Marker {
+    pub fn create(xcb: &XCBObjects) -> Result<Marker, Box<dyn Error>> {
+        let window = xcb.create_overlay(1, 1)?;
+        Ok(Marker { window, point: None, shown: None, at: None })
+    }
+
+    pub fn dropped(&self) -> bool {
+        self.point.is_some()
+    }
+
+    /// Whether its window is mapped, as it is once laid out.
+    pub fn shown(&self) -> bool {
+        self.shown.is_some()
+    }
+
+    pub fn drop_at(&mut self, point: DVec2) {
+        self.point = Some(point);
+    }
+
+    pub fn clear(&mut self, xcb: &XCBObjects) {
+        self.point = None;
+        self.at = None;
+        if self.shown.take().is_some() {
+            xcb.conn.send_request(&UnmapWindow { window: self.window });
+        }
+    }
+
+    /// Forgets what was laid out, so the next `follow` paints again.
+    pub fn invalidate(&mut self) {
+        self.at = None;
+    }
+
+    /// Lays the perpendicular out again if the ruler moved or its length
+    /// reads differently, measuring the label with `ctx`.
+    pub fn follow(&mut self, xcb: &XCBObjects, ctx: &Context, state: &RulerState, settings: &Settings, px_per_mm: DVec2) -> Result<(), Box<dyn Error>> {
+        let Some(point) = self.point else { return Ok(()) };
+        let text = distance_text(point, state, state.unit_or(settings.unit), px_per_mm);
+        if self.at == Some((state.from, state.to)) && self.shown.as_ref().is_some_and(|(_, shown)| *shown == text) {
+            return Ok(());
+        }
+
+        ctx.save()?;
+        ctx.select_font_face(&settings.theme.font, FontSlant::Normal, FontWeight::Normal);
+        ctx.set_font_size(settings.theme.font_size * 0.75);
+        let extents = ctx.text_extents(&text);
+        ctx.restore()?;
+        let label_size = DVec2::new(extents?.width(), settings.theme.font_size * 0.75) + PADDING * 2.0;
+        let layout = layout(point, state, label_size);
+
+        let window = layout.window;
+        xcb.conn.send_request(&ConfigureWindow {
+            window: self.window,
+            value_list: &[
+                ConfigWindow::X(window.x as i32),
+                ConfigWindow::Y(window.y as i32),
+                ConfigWindow::Width(window.w as u32),
+                ConfigWindow::Height(window.h as u32),
+                ConfigWindow::StackMode(StackMode::Above),
+            ],
+        });
+        if self.shown.is_none() {
+            xcb.conn.send_request(&MapWindow { window: self.window });
+        }
+        // A move alone would leave the old picture in place.
+        xcb.conn.send_request(&ClearArea { exposures: true, window: self.window, x: 0, y: 0, width: 0, height: 0 });
+        (self.shown, self.at) = (Some((layout, text)), Some((state.from, state.to)));
+        Ok(())
+    }
+
+    /// Paints the marker once the last of a series of Expose events for it
+    /// arrives, since it is drawn in full.
+    pub fn expose(&self, ev: &ExposeEvent, render: &Render, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        let Some((layout, text)) = &self.shown else { return Ok(()) };
+        if ev.count() > 0 {
+            return Ok(());
+        }
+        render.resize(layout.window.w as i32, layout.window.h as i32)?;
+        render.clear()?;
+        render.ctx.save()?;
+        render.ctx.translate(-(layout.window.x as f64), -(layout.window.y as f64));
+        let painted = paint(&render.ctx, layout, text, settings);
+        render.ctx.restore()?;
+        painted
+    }
+
+    /// Draws the marker onto `ctx` in screen coordinates, if one is shown.
+    pub fn export(&self, ctx: &Context, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        match &self.shown {
+            Some((layout, text)) => paint(ctx, layout, text, settings),
+            None => Ok(()),
+        }
+    }
+}
+
+fn layout(point: DVec2, state: &RulerState, label_size: DVec2) -> Layout {
+    let along = state.to - state.from;
+    let foot = project_onto_line(point, state.from, along);
+    // How far along the ruler the foot is, from 0 at `from` to 1 at `to`.
+    let t = if along == DVec2::ZERO { 0.0 } else { (foot - state.from).dot(along) / along.length_squared() };
+    let extended_from = if t < 0.0 { Some(state.from) } else if t > 1.0 { Some(state.to) } else { None };
+
+    // Beside the middle of the perpendicular, on the side away from `to`
+    // along the line, where the ruler is less likely to be.
+    let middle = (point + foot) / 2.0;
+    let side = -along.try_normalize().unwrap_or(DVec2::X);
+    // Far enough that the box, however it is turned to the line, clears it.
+    let half = side.abs() * label_size / 2.0;
+    let center = middle + side * (LABEL_GAP + half.x + half.y);
+    let label = (center - label_size / 2.0, label_size);
+
+    let mut min = point.min(foot).min(label.0) - RING - 2.0;
+    let mut max = point.max(foot).max(label.0 + label.1) + RING + 2.0;
+    if let Some(end) = extended_from {
+        (min, max) = (min.min(end), max.max(end));
+    }
+    let (min, max) = (min.floor(), max.ceil());
+    let window = WindowGeometry {
+        x: coordinate(min.x, "marker x"),
+        y: coordinate(min.y, "marker y"),
+        w: extent(max.x - min.x, "marker width").max(1),
+        h: extent(max.y - min.y, "marker height").max(1),
+    };
+    Layout { point, foot, extended_from, label, window }
+}
+
+fn paint(ctx: &Context, layout: &Layout, text: &str, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let theme = &settings.theme;
+    let accent = theme.accent;
+    ctx.save()?;
+    ctx.set_source_rgba(accent.r, accent.g, accent.b, theme.opacity.max(0.8));
+    ctx.set_line_width(1.5);
+
+    let Layout { point, foot, .. } = *layout;
+    ctx.arc(point.x, point.y, RING, 0.0, std::f64::consts::PI * 2.0);
+    ctx.stroke()?;
+    ctx.move_to(point.x, point.y);
+    ctx.line_to(foot.x, foot.y);
+    ctx.stroke()?;
+
+    if let Some(end) = layout.extended_from {
+        ctx.save()?;
+        ctx.set_dash(&[4.0, 3.0], 0.0);
+        ctx.move_to(end.x, end.y);
+        ctx.line_to(foot.x, foot.y);
+        ctx.stroke()?;
+        ctx.restore()?;
+    }
+
+    // The square in the corner between the perpendicular and the line.
+    if let Some(up) = (point - foot).try_normalize() {
+        let side = up.perp() * RIGHT_ANGLE;
+        let up = up * RIGHT_ANGLE.min(point.distance(foot));
+        ctx.move_to(foot.x + up.x, foot.y + up.y);
+        ctx.line_to(foot.x + up.x + side.x, foot.y + up.y + side.y);
+        ctx.line_to(foot.x + side.x, foot.y + side.y);
+        ctx.stroke()?;
+    }
+
+    let (corner, size) = layout.label;
+    ctx.rectangle(corner.x, corner.y, size.x, size.y);
+    ctx.set_source_rgba(theme.background.r, theme.background.g, theme.background.b, theme.opacity);
+    ctx.fill()?;
+    ctx.select_font_face(&theme.font, FontSlant::Normal, FontWeight::Normal);
+    ctx.set_font_size(theme.font_size * 0.75);
+    let extents = ctx.text_extents(text)?;
+    ctx.move_to(corner.x + PADDING - extents.x_bearing(), corner.y + (size.y - extents.height()) / 2.0 - extents.y_bearing());
+    ctx.text_path(text);
+    ctx.set_source_rgba(accent.r, accent.g, accent.b, theme.opacity.max(0.8));
+    ctx.fill()?;
+    ctx.restore()?;
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use std::f64::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use glam::DVec2;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Measurement {
+    pub from: DVec2,
+    pub to: DVec2,
+    pub px_per_mm: DVec2,
+}
+
+impl Measurement {
+    pub fn length_px(&self) -> f64 {
+        self.from.distance(self.to)
+    }
+
+    pub fn length_mm(&self) -> f64 {
+        ((self.to - self.from) / self.px_per_mm).length()
+    }
+
+    /// Counterclockwise angle from the screen's x-axis in degrees, in `[0, 360)`.
+    pub fn angle(&self) -> f64 {
+        let angle = (self.to - self.from).try_normalize().map_or(0.0, |dir| DVec2::X.angle_between(dir));
+        (-angle).rem_euclid(PI * 2.0).to_degrees()
+    }
+
+    /// A file name describing the measurement, such as `ruler-412px-109.0mm-0.0deg-1728912345.png`.
+    pub fn file_name(&self, extension: &str) -> String {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        format!("ruler-{:.0}px-{:.1}mm-{:.1}deg-{}.{}", self.length_px(), self.length_mm(), self.angle(), timestamp, extension)
+    }
+}
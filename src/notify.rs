@@ -0,0 +1,62 @@
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use tracing::warn;
+
+#[cfg(feature = "dbus")]
+use std::collections::HashMap;
+#[cfg(feature = "dbus")]
+use std::error::Error;
+#[cfg(feature = "dbus")]
+use std::sync::mpsc::Receiver;
+
+#[cfg(feature = "dbus")]
+const TIMEOUT_MS: i32 = 5000;
+
+/// Shows desktop notifications through `org.freedesktop.Notifications`. The
+/// D-Bus calls happen on a thread of their own so a slow notification daemon
+/// never stalls the ruler, and each notification replaces the previous one.
+pub struct Notifier {
+    sender: Sender<String>,
+}
+
+impl Notifier {
+    pub fn spawn() -> Notifier {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            #[cfg(feature = "dbus")]
+            if let Err(e) = deliver(receiver) {
+                warn!("Stopped sending notifications: {}", e);
+            }
+            #[cfg(not(feature = "dbus"))]
+            if receiver.recv().is_ok() {
+                warn!("Notifications are unavailable, ruler was built without the dbus feature");
+            }
+        });
+        Notifier { sender }
+    }
+
+    pub fn notify(&self, body: String) {
+        let _ = self.sender.send(body);
+    }
+}
+
+#[cfg(feature = "dbus")]
+fn deliver(bodies: Receiver<String>) -> Result<(), Box<dyn Error>> {
+    // Only connect to the session bus once there is something to show.
+    let Ok(first) = bodies.recv() else { return Ok(()) };
+    let conn = zbus::blocking::Connection::session()?;
+    let mut id = 0u32;
+    for body in std::iter::once(first).chain(bodies) {
+        let hints: HashMap<&str, zbus::zvariant::Value> = HashMap::new();
+        let reply = conn.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &("ruler", id, "", "Measurement", body.as_str(), Vec::<&str>::new(), hints, TIMEOUT_MS),
+        )?;
+        id = reply.body().deserialize()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::fmt::{Display, Formatter};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::measurement::Measurement;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum StreamFormat {
+    /// `key=value` pairs separated by spaces
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// How `--region` prints the selection, for use in screenshot pipelines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum RegionFormat {
+    /// `WxH+X+Y`, as printed by slop
+    Slop,
+    /// `X,Y WxH`, as printed by slurp
+    Slurp,
+}
+
+/// The axis-aligned rectangle spanned by the two endpoints.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<&Measurement> for Region {
+    fn from(m: &Measurement) -> Self {
+        let min = m.from.min(m.to).round();
+        let size = (m.to - m.from).abs().round();
+        Region { x: min.x as i32, y: min.y as i32, width: size.x as u32, height: size.y as u32 }
+    }
+}
+
+impl Region {
+    pub fn line(&self, format: RegionFormat) -> String {
+        let Region { x, y, width, height } = self;
+        match format {
+            RegionFormat::Slop => format!("{}x{}+{}+{}", width, height, x, y),
+            RegionFormat::Slurp => format!("{},{} {}x{}", x, y, width, height),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct Lengths {
+    pub px: f64,
+    pub mm: f64,
+    pub cm: f64,
+    #[serde(rename = "in")]
+    pub inches: f64,
+}
+
+/// A measurement with everything derived from it, as printed by `--stream`.
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct Report {
+    pub from: Point,
+    pub to: Point,
+    pub length: Lengths,
+    /// Counterclockwise degrees in `[0, 360)`
+    pub angle: f64,
+}
+
+impl From<&Measurement> for Report {
+    fn from(m: &Measurement) -> Self {
+        let mm = m.length_mm();
+        Report {
+            from: Point { x: m.from.x, y: m.from.y },
+            to: Point { x: m.to.x, y: m.to.y },
+            length: Lengths { px: m.length_px(), mm, cm: mm / 10.0, inches: mm / 25.4 },
+            angle: m.angle(),
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Report { from, to, length, angle } = self;
+        write!(
+            f,
+            "from={},{} to={},{} px={:.0} mm={:.1} cm={:.2} in={:.2} angle={:.2}",
+            from.x, from.y, to.x, to.y, length.px, length.mm, length.cm, length.inches, angle
+        )
+    }
+}
+
+/// A fatal error, printed to stderr in JSON mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub error: String,
+}
+
+impl Report {
+    pub fn line(&self, format: StreamFormat) -> String {
+        match format {
+            StreamFormat::Text => self.to_string(),
+            StreamFormat::Json => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+}
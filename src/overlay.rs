@@ -0,0 +1,95 @@
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::path::Path;
+use std::rc::Rc;
+
+use cairo::Context;
+use glam::DVec2;
+use rhai::{Dynamic, Engine, ImmutableString, Map, Scope, AST};
+use tracing::warn;
+
+/// What a script asks to draw, in the ruler's own coordinates: `x` runs
+/// along the ruler from its start and `y` across it, both in pixels.
+#[derive(Debug, Clone)]
+enum Primitive {
+    Line { from: DVec2, to: DVec2 },
+    Label { at: DVec2, text: String },
+}
+
+/// The ruler as a script sees it.
+#[derive(Debug, Copy, Clone)]
+pub struct State {
+    pub length: f64,
+    pub angle: f64,
+    pub half_width: f64,
+    pub px_per_unit: f64,
+}
+
+/// A rhai script drawing on top of the ruler. It defines `overlay(ruler)`,
+/// which is called for every frame with a map of the ruler's `length`,
+/// `angle`, `half_width` and `px_per_unit`, and draws by calling
+/// `line(x1, y1, x2, y2)` and `label(x, y, text)`.
+pub struct Overlay {
+    engine: Engine,
+    ast: AST,
+    primitives: Rc<RefCell<Vec<Primitive>>>,
+    failed: Cell<bool>,
+}
+
+fn number(value: &Dynamic) -> f64 {
+    value.as_float().ok().or_else(|| value.as_int().ok().map(|i| i as f64)).unwrap_or(0.0)
+}
+
+impl Overlay {
+    pub fn load(path: &Path) -> Result<Overlay, Box<dyn Error>> {
+        let mut engine = Engine::new();
+        let primitives = Rc::new(RefCell::new(Vec::new()));
+
+        let lines = primitives.clone();
+        engine.register_fn("line", move |x1: Dynamic, y1: Dynamic, x2: Dynamic, y2: Dynamic| {
+            let (from, to) = (DVec2::new(number(&x1), number(&y1)), DVec2::new(number(&x2), number(&y2)));
+            lines.borrow_mut().push(Primitive::Line { from, to });
+        });
+        let labels = primitives.clone();
+        engine.register_fn("label", move |x: Dynamic, y: Dynamic, text: ImmutableString| {
+            labels.borrow_mut().push(Primitive::Label { at: DVec2::new(number(&x), number(&y)), text: text.to_string() });
+        });
+
+        let ast = engine.compile_file(path.to_path_buf())?;
+        Ok(Overlay { engine, ast, primitives, failed: Cell::new(false) })
+    }
+
+    /// Runs the script for `state` and draws what it asked for onto `ctx`,
+    /// which has to be set up in ruler coordinates. A failing script is
+    /// reported once and then drawn as far as it got.
+    pub fn draw(&self, ctx: &Context, state: State) -> Result<(), Box<dyn Error>> {
+        let mut ruler = Map::new();
+        ruler.insert("length".into(), Dynamic::from_float(state.length));
+        ruler.insert("angle".into(), Dynamic::from_float(state.angle));
+        ruler.insert("half_width".into(), Dynamic::from_float(state.half_width));
+        ruler.insert("px_per_unit".into(), Dynamic::from_float(state.px_per_unit));
+
+        self.primitives.borrow_mut().clear();
+        if let Err(e) = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, "overlay", (ruler,)) {
+            if !self.failed.replace(true) {
+                warn!("Overlay script failed: {}", e);
+            }
+        }
+
+        for primitive in self.primitives.borrow().iter() {
+            match primitive {
+                Primitive::Line { from, to } => {
+                    ctx.move_to(from.x, from.y);
+                    ctx.line_to(to.x, to.y);
+                    ctx.stroke()?;
+                }
+                Primitive::Label { at, text } => {
+                    ctx.move_to(at.x, at.y);
+                    ctx.text_path(text);
+                    ctx.fill()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use toml::{Table, Value};
+
+#[derive(Debug, Clone)]
+pub struct UneditableSettingError {
+    key: String,
+}
+
+impl Display for UneditableSettingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not set '{}' in the config file, it is written in a way only a text editor can change", self.key)
+    }
+}
+
+impl Error for UneditableSettingError {}
+
+/// The name in a `[table]` header line.
+fn header(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with("[[") {
+        return None;
+    }
+    Some(line.strip_prefix('[')?.split_once(']')?.0.trim())
+}
+
+/// Whether `line` sets `name`, ignoring what it is set to.
+fn sets(line: &str, name: &str) -> bool {
+    line.trim_start().strip_prefix(name).is_some_and(|rest| rest.trim_start().starts_with('='))
+}
+
+/// The comment after the value on a `key = value` line, as it was spaced.
+/// Strings may hold a `#` too, so it is the first one after a whole value.
+fn trailing_comment(line: &str) -> &str {
+    let Some((_, rest)) = line.split_once('=') else { return "" };
+    for (i, _) in rest.match_indices('#') {
+        if format!("key = {}", &rest[..i]).parse::<Table>().is_ok() {
+            let start = rest[..i].trim_end().len();
+            return &rest[start..];
+        }
+    }
+    ""
+}
+
+fn get<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (parents, name) = key.rsplit_once('.').map_or((None, key), |(parents, name)| (Some(parents), name));
+    let mut table = table;
+    for parent in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        table = table.get(parent)?.as_table()?;
+    }
+    table.get(name)
+}
+
+/// Sets `key`, a dotted path such as `theme.opacity`, in the text of a config
+/// file, leaving every other line and the comment after the value as they
+/// were. It goes at the end of its table, which is added at the end of the
+/// file if there is none. Settings the file spells as dotted keys, inline
+/// tables or values across lines cannot be changed this way.
+pub fn set(text: &str, key: &str, value: &Value) -> Result<String, UneditableSettingError> {
+    let (table, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+
+    // The lines of the table, after its header up to the next one.
+    let start = if table.is_empty() { Some(0) } else { lines.iter().position(|line| header(line) == Some(table)).map(|i| i + 1) };
+    let line = format!("{} = {}", name, value);
+    match start {
+        Some(start) => {
+            let end = lines[start..].iter().position(|line| header(line).is_some()).map_or(lines.len(), |i| start + i);
+            match (start..end).find(|&i| sets(&lines[i], name)) {
+                Some(i) => {
+                    let indent = &lines[i][..lines[i].len() - lines[i].trim_start().len()];
+                    lines[i] = format!("{}{}{}", indent, line, trailing_comment(&lines[i]));
+                }
+                None => {
+                    // Before the blank lines and comments leading up to the next table.
+                    let last = (start..end).rev().find(|&i| !lines[i].trim().is_empty() && !lines[i].trim_start().starts_with('#'));
+                    lines.insert(last.map_or(start, |i| i + 1), line);
+                }
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|last| !last.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.extend([format!("[{}]", table), line]);
+        }
+    }
+
+    let mut edited = lines.join("\n");
+    edited.push('\n');
+    match edited.parse::<Table>() {
+        Ok(parsed) if get(&parsed, key) == Some(value) => Ok(edited),
+        _ => Err(UneditableSettingError { key: key.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# Measure in millimeters
+unit = \"mm\" # for print work
+
+[theme]
+accent = \"#ff0000\" # red
+opacity = 0.6
+
+# Snapping
+[drag]
+body = true
+";
+
+    #[test]
+    fn replaces_a_value_keeping_its_comment() {
+        let edited = set(CONFIG, "theme.accent", &Value::String("#00ff00".to_string())).unwrap();
+        assert_eq!(edited, CONFIG.replace("accent = \"#ff0000\"", "accent = \"#00ff00\""));
+        let edited = set(CONFIG, "unit", &Value::String("px".to_string())).unwrap();
+        assert_eq!(edited, CONFIG.replace("unit = \"mm\"", "unit = \"px\""));
+    }
+
+    #[test]
+    fn adds_a_value_to_the_end_of_its_table() {
+        let edited = set(CONFIG, "theme.font_size", &Value::Float(16.0)).unwrap();
+        assert_eq!(edited, CONFIG.replace("opacity = 0.6\n", "opacity = 0.6\nfont_size = 16.0\n"));
+    }
+
+    #[test]
+    fn adds_a_missing_table() {
+        let edited = set(CONFIG, "loupe.zoom", &Value::Integer(6)).unwrap();
+        assert_eq!(edited, format!("{}\n[loupe]\nzoom = 6\n", CONFIG));
+        assert_eq!(set("", "drag.snap_angle", &Value::Float(15.0)).unwrap(), "[drag]\nsnap_angle = 15.0\n");
+    }
+
+    #[test]
+    fn refuses_what_it_cannot_edit() {
+        let inline = "theme = { opacity = 0.6 }\n";
+        assert!(set(inline, "theme.opacity", &Value::Float(0.8)).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::thread;
+use std::time::Duration;
+
+use glam::DVec2;
+use xcb::x::{ButtonIndex, CloseFont, CreateGlyphCursor, CURRENT_TIME, Cursor, Event, EventMask, FreeCursor, GrabKeyboard, GrabMode, GrabPointer, GrabStatus, OpenFont, UngrabKeyboard, UngrabPointer, WINDOW_NONE};
+
+use crate::backend::{checked, round_trip, XCBObjects};
+
+// The crosshair glyph of the standard X cursor font, followed by its mask.
+const XC_CROSSHAIR: u16 = 34;
+// When started from a hotkey, the window manager may still hold the keyboard for a moment.
+const GRAB_ATTEMPTS: u32 = 50;
+const GRAB_RETRY: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Copy, Clone)]
+pub struct PickCancelledError;
+
+impl Display for PickCancelledError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Picking was cancelled")
+    }
+}
+
+impl Error for PickCancelledError {}
+
+#[derive(Debug, Copy, Clone)]
+pub struct GrabFailedError(GrabStatus);
+
+impl Display for GrabFailedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to grab the pointer and keyboard: {:?}", self.0)
+    }
+}
+
+impl Error for GrabFailedError {}
+
+/// Lets the user click two points anywhere on screen with a crosshair
+/// cursor, without showing the ruler. Any key or another button cancels.
+pub fn pick(xcb: &XCBObjects) -> Result<(DVec2, DVec2), Box<dyn Error>> {
+    let points = pick_points(xcb, 2)?;
+    Ok((points[0], points[1]))
+}
+
+/// Lets the user click `count` points like `pick`.
+pub fn pick_points(xcb: &XCBObjects, count: usize) -> Result<Vec<DVec2>, Box<dyn Error>> {
+    let font = xcb.conn.generate_id();
+    checked(&xcb.conn, &OpenFont { fid: font, name: b"cursor" })?;
+    let cursor: Cursor = xcb.conn.generate_id();
+    checked(&xcb.conn, &CreateGlyphCursor {
+        cid: cursor,
+        source_font: font,
+        mask_font: font,
+        source_char: XC_CROSSHAIR,
+        mask_char: XC_CROSSHAIR + 1,
+        fore_red: 0,
+        fore_green: 0,
+        fore_blue: 0,
+        back_red: u16::MAX,
+        back_green: u16::MAX,
+        back_blue: u16::MAX,
+    })?;
+    xcb.conn.send_request(&CloseFont { font });
+
+    let picked = match grab(xcb, cursor)? {
+        GrabStatus::Success => wait_for_clicks(xcb, count),
+        status => Err(Box::new(GrabFailedError(status)) as Box<dyn Error>),
+    };
+
+    xcb.conn.send_request(&UngrabPointer { time: CURRENT_TIME });
+    xcb.conn.send_request(&UngrabKeyboard { time: CURRENT_TIME });
+    checked(&xcb.conn, &FreeCursor { cursor })?;
+    picked
+}
+
+fn grab(xcb: &XCBObjects, cursor: Cursor) -> Result<GrabStatus, Box<dyn Error>> {
+    let root = xcb.screen.root();
+    let mut status = GrabStatus::AlreadyGrabbed;
+    for _ in 0..GRAB_ATTEMPTS {
+        status = round_trip(&xcb.conn, &GrabPointer {
+            owner_events: false,
+            grab_window: root,
+            event_mask: EventMask::BUTTON_PRESS,
+            pointer_mode: GrabMode::Async,
+            keyboard_mode: GrabMode::Async,
+            confine_to: WINDOW_NONE,
+            cursor,
+            time: CURRENT_TIME,
+        })?.status();
+        if status == GrabStatus::Success {
+            status = round_trip(&xcb.conn, &GrabKeyboard {
+                owner_events: false,
+                grab_window: root,
+                time: CURRENT_TIME,
+                pointer_mode: GrabMode::Async,
+                keyboard_mode: GrabMode::Async,
+            })?.status();
+        }
+        if status == GrabStatus::Success {
+            break;
+        }
+        thread::sleep(GRAB_RETRY);
+    }
+    Ok(status)
+}
+
+fn wait_for_clicks(xcb: &XCBObjects, count: usize) -> Result<Vec<DVec2>, Box<dyn Error>> {
+    let mut points = Vec::with_capacity(count);
+    while points.len() < count {
+        match xcb.conn.wait_for_event()? {
+            xcb::Event::X(Event::ButtonPress(ev)) if ev.detail() == ButtonIndex::N1 as u8 => {
+                points.push(DVec2::new(ev.root_x() as f64, ev.root_y() as f64));
+            }
+            xcb::Event::X(Event::ButtonPress(_) | Event::KeyPress(_)) => return Err(Box::new(PickCancelledError)),
+            _ => {}
+        }
+    }
+    Ok(points)
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process;
+
+use cairo::{Context, Format, ImageSurface};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::backend::WindowGeometry;
+
+const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PATH: &str = "/org/freedesktop/portal/desktop";
+
+#[derive(Debug, Copy, Clone)]
+pub struct PortalError(&'static str);
+
+impl Display for PortalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Screenshot portal: {}", self.0)
+    }
+}
+
+impl Error for PortalError {}
+
+/// Takes a screenshot through `org.freedesktop.portal.Screenshot` and cuts
+/// `area` out of it. The portal writes the screenshot to a file, which is
+/// removed again once read. Coordinates are assumed to match, which holds
+/// unless the compositor scales XWayland.
+pub fn screenshot(area: WindowGeometry) -> Result<ImageSurface, Box<dyn Error>> {
+    let conn = Connection::session()?;
+    let token = format!("ruler{}", process::id());
+    let sender = conn.unique_name().ok_or(PortalError("no name on the session bus"))?.trim_start_matches(':').replace('.', "_");
+    let request_path = format!("{}/request/{}/{}", PATH, sender, token);
+
+    // Subscribe before asking, the response may come before the reply.
+    let request = Proxy::new(&conn, DESTINATION, request_path.as_str(), "org.freedesktop.portal.Request")?;
+    let mut responses = request.receive_signal("Response")?;
+    let options = HashMap::from([("handle_token", Value::from(token.as_str())), ("interactive", Value::from(false))]);
+    conn.call_method(Some(DESTINATION), PATH, Some("org.freedesktop.portal.Screenshot"), "Screenshot", &("", options))?;
+
+    let response = responses.next().ok_or(PortalError("no response"))?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) = response.body().deserialize()?;
+    if code != 0 {
+        return Err(Box::new(PortalError("the screenshot was denied or cancelled")));
+    }
+    let uri = results.get("uri").ok_or(PortalError("no screenshot in the response"))?;
+    let path = file_path(&String::try_from(uri.try_clone()?)?).ok_or(PortalError("the screenshot is not a local file"))?;
+
+    let screen = ImageSurface::create_from_png(&mut File::open(&path)?)?;
+    let _ = fs::remove_file(&path);
+
+    let cropped = ImageSurface::create(Format::Rgb24, area.w as i32, area.h as i32)?;
+    let ctx = Context::new(&cropped)?;
+    ctx.set_source_surface(&screen, -area.x as f64, -area.y as f64)?;
+    ctx.paint()?;
+    drop(ctx);
+    Ok(cropped)
+}
+
+/// Turns a `file://` URI into a path, undoing its percent-encoding.
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' {
+            let hex = std::str::from_utf8(encoded.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(encoded[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(decoded).ok()?))
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::thread;
+
+use inotify::{Inotify, WatchMask};
+use signal_hook::consts::SIGUSR1;
+use signal_hook::iterator::Signals;
+use tracing::warn;
+
+use crate::backend::Waker;
+
+fn watch_signals(waker: Waker) -> Result<(), Box<dyn Error>> {
+    let mut signals = Signals::new([SIGUSR1])?;
+    for _ in signals.forever() {
+        waker.wake()?;
+    }
+    Ok(())
+}
+
+fn watch_file(waker: Waker, dir: &Path, name: &OsStr) -> Result<(), Box<dyn Error>> {
+    let mut inotify = Inotify::init()?;
+    inotify.watches().add(dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE | WatchMask::DELETE)?;
+    let mut buffer = [0; 4096];
+    loop {
+        let events = inotify.read_events_blocking(&mut buffer)?;
+        if events.into_iter().any(|event| event.name == Some(name)) {
+            waker.wake()?;
+        }
+    }
+}
+
+/// Spawns threads that request a config reload on SIGUSR1 and whenever the
+/// file at `path` is written, created, replaced or removed.
+pub fn spawn_watchers(waker: Waker, path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let signal_waker = waker.clone();
+    thread::spawn(move || {
+        if let Err(e) = watch_signals(signal_waker) {
+            warn!("Stopped listening for SIGUSR1: {}", e);
+        }
+    });
+
+    if let Some((dir, name)) = path.and_then(|path| Some((path.parent()?.to_path_buf(), path.file_name()?.to_os_string()))) {
+        thread::spawn(move || {
+            if let Err(e) = watch_file(waker, &dir, &name) {
+                warn!("Not watching {} for changes: {}", dir.display(), e);
+            }
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::error::Error;
+
+use toml::{Table, Value};
+use xcb::Connection;
+use tracing::warn;
+use xcb::x::{ATOM_RESOURCE_MANAGER, ATOM_STRING, GetProperty, Window};
+
+use crate::backend::round_trip;
+use crate::config::Color;
+
+// Resource names are matched case-insensitively, so both `font_size` and the
+// more Xresources-like `fontSize` work.
+const THEME_RESOURCES: &[(&str, &str, Kind)] = &[
+    ("background", "background", Kind::Color),
+    ("accent", "accent", Kind::Color),
+    ("opacity", "opacity", Kind::Number),
+    ("font", "font", Kind::String),
+    ("font_size", "font_size", Kind::Number),
+    ("fontsize", "font_size", Kind::Number),
+];
+
+#[derive(Copy, Clone)]
+enum Kind {
+    Color,
+    Number,
+    String,
+}
+
+pub fn read_database(conn: &Connection, root: Window) -> Result<String, Box<dyn Error>> {
+    let reply = round_trip(conn, &GetProperty {
+        delete: false,
+        window: root,
+        property: ATOM_RESOURCE_MANAGER,
+        r#type: ATOM_STRING,
+        long_offset: 0,
+        long_length: u32::MAX / 4,
+    })?;
+    Ok(String::from_utf8_lossy(reply.value::<u8>()).into_owned())
+}
+
+fn parse_value(name: &str, value: &str, kind: Kind) -> Option<Value> {
+    let parsed = match kind {
+        Kind::Color => value.parse::<Color>().ok().map(|_| Value::String(value.to_string())),
+        Kind::Number => value.parse::<f64>().ok().map(Value::Float),
+        Kind::String => Some(Value::String(value.to_string())),
+    };
+    if parsed.is_none() {
+        warn!("Ignoring invalid X resource ruler.{}: {}", name, value);
+    }
+    parsed
+}
+
+/// Extracts `ruler.*` entries from a resource database into a config overlay.
+pub fn theme_overlay(database: &str) -> Table {
+    let mut theme = Table::new();
+    for line in database.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let Some(name) = key.trim().strip_prefix("ruler").or_else(|| key.trim().strip_prefix("Ruler")) else { continue };
+        let Some(name) = name.strip_prefix(['.', '*']) else { continue };
+        let Some(&(_, field, kind)) = THEME_RESOURCES.iter().find(|(n, _, _)| n.eq_ignore_ascii_case(name)) else { continue };
+        if let Some(value) = parse_value(name, value.trim(), kind) {
+            theme.insert(field.to_string(), value);
+        }
+    }
+
+    let mut overlay = Table::new();
+    if !theme.is_empty() {
+        overlay.insert("theme".to_string(), Value::Table(theme));
+    }
+    overlay
+}
@@ -0,0 +1,88 @@
+use crate::unit::Unit;
+
+/// Tick layout for a unit: a tick every `step` units, longer ones every
+/// `mid` ticks and labelled ones every `major` ticks.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Scale {
+    pub step: f64,
+    pub mid: u32,
+    pub major: u32,
+}
+
+/// Kinds of tick from the least to the most prominent.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TickKind {
+    Minor,
+    Mid,
+    Major,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tick {
+    /// Distance from the start of the ruler in pixels
+    pub position: f64,
+    /// Distance from the start of the ruler in the scale's unit
+    pub value: f64,
+    pub kind: TickKind,
+}
+
+impl Tick {
+    /// The number printed under major ticks past the start.
+    pub fn label_value(&self) -> Option<i64> {
+        (self.kind == TickKind::Major && self.position > 0.0).then(|| self.value.round() as i64)
+    }
+
+    pub fn label(&self) -> Option<String> {
+        self.label_value().map(|value| value.to_string())
+    }
+}
+
+impl Scale {
+    pub fn for_unit(unit: Unit) -> Scale {
+        match unit {
+            Unit::Px => Scale { step: 5.0, mid: 5, major: 10 },
+            Unit::Mm => Scale { step: 1.0, mid: 5, major: 10 },
+            Unit::Cm => Scale { step: 0.1, mid: 5, major: 10 },
+            Unit::In => Scale { step: 0.125, mid: 4, major: 8 },
+        }
+    }
+
+    /// The ticks along a ruler `length` pixels long, `px_per_unit` apart per
+    /// unit. Minor ticks, and then mid ticks, are left out where they would be
+    /// less than `min_spacing` pixels apart, but major ticks never are.
+    pub fn ticks(self, length: f64, px_per_unit: f64, min_spacing: f64) -> impl Iterator<Item = Tick> + Clone {
+        let spacing = self.step * px_per_unit;
+        let least = if spacing >= min_spacing {
+            TickKind::Minor
+        } else if spacing * self.mid as f64 >= min_spacing {
+            TickKind::Mid
+        } else {
+            TickKind::Major
+        };
+        (0..).map(move |n: u32| {
+            let kind = if n.is_multiple_of(self.major) {
+                TickKind::Major
+            } else if n.is_multiple_of(self.mid) {
+                TickKind::Mid
+            } else {
+                TickKind::Minor
+            };
+            Tick { position: n as f64 * spacing, value: n as f64 * self.step, kind }
+        }).take_while(move |tick| tick.position < length).filter(move |tick| tick.kind >= least)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_ticks_are_left_out() {
+        let scale = Scale::for_unit(Unit::Mm);
+        let kinds = |min_spacing| scale.ticks(100.0, 4.0, min_spacing).map(|tick| tick.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(4.0).len(), 25);
+        // Mid ticks are 20 pixels apart, major ticks 40.
+        assert_eq!(kinds(8.0), vec![TickKind::Major, TickKind::Mid, TickKind::Major, TickKind::Mid, TickKind::Major]);
+        assert_eq!(kinds(30.0), vec![TickKind::Major; 3]);
+    }
+}
@@ -0,0 +1,44 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use cairo::{Context, Format, ImageSurface};
+use glam::DVec2;
+
+use ruler::measurement::Measurement;
+
+use crate::backend::{coordinate, extent, WindowGeometry, XCBObjects};
+use crate::capture;
+
+/// Saves the band of the screen under the ruler, `half_width` to either side
+/// of the line, as a PNG in `dir`. The band is rotated to run left to right
+/// from `from`, and the file is named after the measurement.
+pub fn save(xcb: &XCBObjects, m: &Measurement, half_width: f64, screen_size: DVec2, dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let normal = (m.to - m.from).try_normalize().unwrap_or(DVec2::X).perp() * half_width;
+    let corners = [m.from + normal, m.from - normal, m.to + normal, m.to - normal];
+    let min = corners.iter().fold(DVec2::MAX, |a, &b| a.min(b)).max(DVec2::ZERO).floor();
+    let max = corners.iter().fold(DVec2::MIN, |a, &b| a.max(b)).min(screen_size).ceil();
+    let size = (max - min).max(DVec2::ONE);
+    let area = WindowGeometry {
+        x: coordinate(min.x, "capture x"),
+        y: coordinate(min.y, "capture y"),
+        w: extent(size.x, "capture width"),
+        h: extent(size.y, "capture height"),
+    };
+    let screen = xcb.hidden(|| capture::capture(xcb, area))??;
+
+    let length = m.length_px().round().max(1.0);
+    let band = ImageSurface::create(Format::Rgb24, length as i32, (half_width * 2.0).round() as i32)?;
+    let ctx = Context::new(&band)?;
+    ctx.translate(0.0, half_width);
+    ctx.rotate(-DVec2::X.angle_between(m.to - m.from));
+    ctx.translate(min.x - m.from.x, min.y - m.from.y);
+    ctx.set_source_surface(&screen, 0.0, 0.0)?;
+    ctx.paint()?;
+    drop(ctx);
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(m.file_name("png"));
+    band.write_to_png(&mut File::create(&path)?)?;
+    Ok(path)
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::io::{self, BufRead, IsTerminal};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use tracing::warn;
+
+use crate::backend::Waker;
+use crate::control::Message;
+
+/// Reads control requests such as `set from 100 200` or `rotate 45` from
+/// stdin when it is not a terminal, so another program can drive the ruler.
+/// States asked for are printed to stdout and errors reported as warnings.
+pub fn spawn_reader(waker: Waker, sender: Sender<Message>) {
+    if io::stdin().is_terminal() {
+        return;
+    }
+
+    let (reply, replies) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for line in replies {
+            match line.strip_prefix("error ") {
+                Some(message) => warn!("{}", message),
+                None if line == "ok" => {}
+                None => println!("{}", line),
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        if let Err(e) = read(waker, sender, reply) {
+            warn!("Stopped reading commands from stdin: {}", e);
+        }
+    });
+}
+
+fn read(waker: Waker, sender: Sender<Message>, reply: Sender<String>) -> Result<(), Box<dyn Error>> {
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.parse() {
+            Ok(request) => {
+                sender.send(Message { request, reply: reply.clone() })?;
+                waker.wake()?;
+            }
+            Err(e) => warn!("{}", e),
+        }
+    }
+    Ok(())
+}
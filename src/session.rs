@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use glam::DVec2;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use ruler::measurement::Measurement;
+use ruler::output::Point;
+
+use crate::backend::Waker;
+use crate::control::{Message, Request};
+
+/// One line of a recorded session: where the endpoints were, `time` seconds
+/// after recording started. Nothing else of the ruler is recorded.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct Frame {
+    time: f64,
+    from: Point,
+    to: Point,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidSessionError {
+    line: usize,
+    message: String,
+}
+
+impl Display for InvalidSessionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid session file, line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for InvalidSessionError {}
+
+/// Writes every move of the endpoints to a file for `--record`.
+pub struct Recorder {
+    file: BufWriter<File>,
+    start: Instant,
+    last: Option<(Point, Point)>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Recorder, Box<dyn Error>> {
+        Ok(Recorder { file: BufWriter::new(File::create(path)?), start: Instant::now(), last: None })
+    }
+
+    pub fn record(&mut self, measurement: &Measurement) {
+        let from = Point { x: measurement.from.x, y: measurement.from.y };
+        let to = Point { x: measurement.to.x, y: measurement.to.y };
+        if self.last == Some((from, to)) {
+            return;
+        }
+        self.last = Some((from, to));
+        let frame = Frame { time: self.start.elapsed().as_secs_f64(), from, to };
+        if let Err(e) = self.write(&frame) {
+            warn!("Failed to record the session: {}", e);
+        }
+    }
+
+    fn write(&mut self, frame: &Frame) -> Result<(), Box<dyn Error>> {
+        writeln!(self.file, "{}", serde_json::to_string(frame)?)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Plays a session recorded with `--record` back through the control
+/// channel, `speed` times as fast as it was recorded, and quits at the end.
+pub fn spawn_replay(path: &Path, speed: f64, waker: Waker, sender: Sender<Message>) -> Result<(), Box<dyn Error>> {
+    let frames = fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str::<Frame>(line).map_err(|e| InvalidSessionError { line: i + 1, message: e.to_string() }))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    thread::spawn(move || {
+        let (reply, _replies) = mpsc::channel();
+        let start = Instant::now();
+        let point = |p: Point| Some(DVec2::new(p.x, p.y));
+        let requests = frames.iter().map(|frame| (frame.time, Request::Set { from: point(frame.from), to: point(frame.to) }));
+        let last = frames.last().map_or(0.0, |frame| frame.time);
+        for (time, request) in requests.chain([(last, Request::Quit)]) {
+            thread::sleep(Duration::from_secs_f64(time / speed).saturating_sub(start.elapsed()));
+            if sender.send(Message { request, reply: reply.clone() }).is_err() {
+                return;
+            }
+            if let Err(e) = waker.wake() {
+                warn!("Stopped replaying the session: {}", e);
+                return;
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,565 @@
+use clap::ValueEnum;
+use glam::DVec2;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::geom::{closest_point_on_circle_in_rect, round_to_grid, snap_angle, tessellate_bar, Rect};
+use crate::measurement::Measurement;
+use crate::unit::Unit;
+
+/// Bound for unclamped endpoints that keeps window positions and sizes within
+/// what X can represent.
+pub const COORDINATE_LIMIT: f64 = 16000.0;
+
+/// Width of the strips the bar is hit-tested and shaped with, in pixels.
+const BODY_STRIP: f64 = 8.0;
+
+/// Smallest radius handles shrink to on a short ruler, in pixels.
+const MIN_HANDLE_RADIUS: f64 = 4.0;
+
+/// What the ruler measures. Each mode gets its own variant here as it is added.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Distance and angle between two endpoints
+    Line,
+    /// Click two points anywhere with a crosshair, print them and exit
+    Pick,
+    /// A line with a second arm square to it, to slide along it
+    TSquare,
+    /// A horizontal and a vertical ruler from `from`, reading how far `to`
+    /// is from it along each
+    LShape,
+}
+
+/// A part of the ruler to grab: one of its endpoints, or the bar between
+/// them to move it as a whole.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Handle {
+    From,
+    To,
+    Body,
+    /// Where a T-square's arm meets the ruler, sliding along it.
+    ArmFoot,
+    /// The far end of a T-square's arm, setting its length.
+    ArmEnd,
+}
+
+/// The second arm of a T-square, square to the ruler where it stands on it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Arm {
+    /// How far along the ruler it stands, from 0 at `from` to 1 at `to`.
+    pub at: f64,
+    /// In pixels, towards the ruler's ticks when positive and away from
+    /// them when negative.
+    pub length: f64,
+}
+
+impl Default for Arm {
+    fn default() -> Self {
+        Arm { at: 0.5, length: 100.0 }
+    }
+}
+
+/// What the pointer is doing with the ruler.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interaction {
+    Idle,
+    /// Over a handle with no button held.
+    Hovering(Handle),
+    /// Holding `button` down on a handle, with the endpoints, the arm and the
+    /// pointer as they were when the drag started so it can be cancelled.
+    Dragging { handle: Handle, start: (DVec2, DVec2), arm: Arm, cursor: DVec2, button: u8 },
+}
+
+/// Constraints latched with the `toggle_*` key bindings, on top of the ones
+/// applied while a modifier is held.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Locks {
+    pub fix_distance: bool,
+    pub fix_angle: bool,
+    pub symmetric: bool,
+    /// Flips the `clamp_to_screen` setting.
+    pub clamp_toggled: bool,
+}
+
+/// Everything that limits where a dragged endpoint may go.
+#[derive(Debug, Copy, Clone)]
+pub struct Constraints {
+    pub fix_distance: bool,
+    pub fix_angle: bool,
+    /// The other endpoint moves the mirrored way, so the middle stays put.
+    pub symmetric: bool,
+    pub clamp_to_screen: bool,
+    pub min_length: f64,
+    /// Degrees the angle snaps to multiples of, 0 to not snap.
+    pub snap_angle: f64,
+    /// How many degrees off a multiple of `snap_angle` still snaps.
+    pub snap_tolerance: f64,
+    pub snap_to_pixels: PixelSnap,
+}
+
+impl Constraints {
+    /// These constraints with the endpoints let off the screen and closer
+    /// together than the minimum length, while `ignore_limits` is held.
+    pub fn ignoring_limits(self) -> Constraints {
+        Constraints { clamp_to_screen: false, min_length: 0.0, ..self }
+    }
+}
+
+/// Where on a pixel dragged endpoints land, if they are kept to whole pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PixelSnap {
+    /// Wherever the constraints leave them.
+    #[default]
+    Off,
+    /// In the middle of the nearest pixel.
+    Centers,
+    /// On the nearest corner between pixels.
+    Boundaries,
+}
+
+impl PixelSnap {
+    /// `point` on the nearest pixel center or boundary, kept in `bounds` if
+    /// given.
+    fn apply(self, point: DVec2, bounds: Option<&Rect>) -> DVec2 {
+        let offset = match self {
+            PixelSnap::Off => return point,
+            PixelSnap::Centers => 0.5,
+            PixelSnap::Boundaries => 0.0,
+        };
+        round_to_grid(point, offset, bounds)
+    }
+}
+
+/// The ruler itself: its endpoints in screen coordinates, what the pointer
+/// is doing with it and what was changed at runtime. Drawing and reporting
+/// only read it, and every change goes through its methods.
+#[derive(Debug, Clone)]
+pub struct RulerState {
+    pub from: DVec2,
+    pub to: DVec2,
+    pub mode: Mode,
+    pub interaction: Interaction,
+    /// Unit picked at runtime, taking precedence over the settings.
+    pub unit: Option<Unit>,
+    pub locks: Locks,
+    /// Size of the root window.
+    pub screen_size: DVec2,
+    /// Clamped endpoints stay within this, the whole screen unless
+    /// confined to a part of it.
+    pub bounds: Rect,
+    /// Half width of the bar when it can be grabbed, `None` when only the
+    /// endpoints can.
+    pub body: Option<f64>,
+    /// Only shown in T-square mode.
+    pub arm: Arm,
+}
+
+impl RulerState {
+    pub fn new(from: DVec2, to: DVec2, mode: Mode, screen_size: DVec2) -> RulerState {
+        RulerState { from, to, mode, interaction: Interaction::Idle, unit: None, locks: Locks::default(), screen_size, bounds: Rect::from_size(screen_size), body: None, arm: Arm::default() }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.from.distance(self.to)
+    }
+
+    /// Unit vector from `from` to `to`, along the screen's x-axis for a ruler
+    /// of no length.
+    pub fn direction(&self) -> DVec2 {
+        (self.to - self.from).try_normalize().unwrap_or(DVec2::X)
+    }
+
+    /// Where the arm stands on the ruler and where it ends, in T-square mode.
+    pub fn arm_points(&self) -> Option<(DVec2, DVec2)> {
+        (self.mode == Mode::TSquare).then(|| {
+            let foot = self.from.lerp(self.to, self.arm.at);
+            (foot, foot - self.direction().perp() * self.arm.length)
+        })
+    }
+
+    pub fn measurement(&self, px_per_mm: DVec2) -> Measurement {
+        Measurement { from: self.from, to: self.to, px_per_mm }
+    }
+
+    /// The unit picked at runtime, or `default` from the settings.
+    pub fn unit_or(&self, default: Unit) -> Unit {
+        self.unit.unwrap_or(default)
+    }
+
+    /// Whether endpoints stay on the screen, given the `clamp_to_screen` setting.
+    pub fn clamp_to_screen(&self, setting: bool) -> bool {
+        setting != self.locks.clamp_toggled
+    }
+
+    pub fn confine(&self, point: DVec2, clamp_to_screen: bool) -> DVec2 {
+        if clamp_to_screen {
+            self.bounds.clamp(point)
+        } else {
+            point.clamp(DVec2::splat(-COORDINATE_LIMIT), DVec2::splat(COORDINATE_LIMIT))
+        }
+    }
+
+    /// The endpoint within `grab_distance` of `cursor`, the closer one if both
+    /// are and `from` on a tie, then either end of the arm, or else the bar if
+    /// it can be grabbed and is under `cursor`.
+    pub fn handle_at(&self, cursor: DVec2, grab_distance: f64) -> Option<Handle> {
+        let grab_distance_squared = grab_distance.powi(2);
+        let near = |point: DVec2| cursor.distance_squared(point) < grab_distance_squared;
+        let (from_distance, to_distance) = (cursor.distance_squared(self.from), cursor.distance_squared(self.to));
+        let arm = self.arm_points();
+        if from_distance < grab_distance_squared && from_distance <= to_distance {
+            Some(Handle::From)
+        } else if to_distance < grab_distance_squared {
+            Some(Handle::To)
+        } else if arm.is_some_and(|(_, end)| near(end)) {
+            Some(Handle::ArmEnd)
+        } else if arm.is_some_and(|(foot, _)| near(foot)) {
+            Some(Handle::ArmFoot)
+        } else if self.body_region().iter().any(|strip| strip.contains(cursor)) {
+            Some(Handle::Body)
+        } else {
+            None
+        }
+    }
+
+    /// The bars from end to end, most often the one between the endpoints.
+    pub fn bars(&self) -> Vec<(DVec2, DVec2)> {
+        match self.mode {
+            Mode::LShape => vec![(self.from, DVec2::new(self.to.x, self.from.y)), (self.from, DVec2::new(self.from.x, self.to.y))],
+            Mode::Line | Mode::Pick | Mode::TSquare => vec![(self.from, self.to)],
+        }
+    }
+
+    /// The bars as strips, empty when they cannot be grabbed.
+    pub fn body_region(&self) -> Vec<Rect> {
+        let Some(half_width) = self.body else { return Vec::new() };
+        self.bars().into_iter().flat_map(|(start, end)| tessellate_bar(start, end, half_width, BODY_STRIP)).collect()
+    }
+
+    /// The radius of the handles, `control_radius` unless the ruler is too
+    /// short for two of them side by side.
+    pub fn handle_radius(&self, control_radius: f64) -> f64 {
+        (self.length() / 2.0).clamp(MIN_HANDLE_RADIUS.min(control_radius), control_radius)
+    }
+
+    /// Where the pointer reaches the ruler rather than what is below it: a
+    /// square around each handle, and the bar when it can be grabbed.
+    pub fn input_region(&self, control_radius: f64) -> Vec<Rect> {
+        let radius = self.handle_radius(control_radius);
+        let square = |point: DVec2| Rect { min: point - radius, max: point + radius };
+        let mut region = vec![square(self.from), square(self.to)];
+        if let Some((foot, end)) = self.arm_points() {
+            region.extend([square(foot), square(end)]);
+        }
+        region.extend(self.body_region());
+        region
+    }
+
+    /// The handle being dragged or hovered, to highlight.
+    pub fn active_handle(&self) -> Option<Handle> {
+        match self.interaction {
+            Interaction::Idle => None,
+            Interaction::Hovering(handle) | Interaction::Dragging { handle, .. } => Some(handle),
+        }
+    }
+
+    /// The endpoint being dragged, if it is one rather than the whole ruler.
+    pub fn dragged_endpoint(&self) -> Option<DVec2> {
+        match self.interaction {
+            Interaction::Dragging { handle: Handle::From, .. } => Some(self.from),
+            Interaction::Dragging { handle: Handle::To, .. } => Some(self.to),
+            _ => None,
+        }
+    }
+
+    /// Moves the endpoint being dragged as close to `point` as `constraints`
+    /// allow, as when it snaps to something on the screen, returning false if
+    /// no endpoint is being dragged.
+    pub fn drag_to(&mut self, point: DVec2, constraints: Constraints) -> bool {
+        self.dragged_endpoint().is_some() && self.drag(point, constraints)
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        matches!(self.interaction, Interaction::Dragging { .. })
+    }
+
+    /// Follows the pointer outside of a drag, returning whether the hovered
+    /// handle changed. `None` means the pointer left the ruler.
+    pub fn hover(&mut self, cursor: Option<DVec2>, grab_distance: f64) -> bool {
+        if self.is_dragging() {
+            return false;
+        }
+        let previous = self.interaction;
+        self.interaction = match cursor.and_then(|cursor| self.handle_at(cursor, grab_distance)) {
+            Some(handle) => Interaction::Hovering(handle),
+            None => Interaction::Idle,
+        };
+        self.interaction != previous
+    }
+
+    /// Starts dragging the handle under `cursor` with `button`, if there is one.
+    pub fn grab(&mut self, cursor: DVec2, grab_distance: f64, button: u8) -> Option<Handle> {
+        if self.is_dragging() {
+            return None;
+        }
+        let handle = self.handle_at(cursor, grab_distance)?;
+        self.interaction = Interaction::Dragging { handle, start: (self.from, self.to), arm: self.arm, cursor, button };
+        Some(handle)
+    }
+
+    /// Moves the dragged endpoint as close to `cursor` as `constraints`
+    /// allow, returning false if no endpoint is being dragged.
+    pub fn drag(&mut self, cursor: DVec2, constraints: Constraints) -> bool {
+        let Interaction::Dragging { handle, start, cursor: grabbed, .. } = self.interaction else { return false };
+        let (dragged, other) = match handle {
+            Handle::From => (self.from, self.to),
+            Handle::To => (self.to, self.from),
+            Handle::Body => {
+                // The whole ruler moves so `from` lands on a pixel, and `to`
+                // along with it once it is on one.
+                let offset = constraints.snap_to_pixels.apply(start.0 + cursor - grabbed, None) - start.0;
+                self.shift(start, offset, constraints.clamp_to_screen);
+                return true;
+            }
+            Handle::ArmFoot => {
+                let along = self.to - self.from;
+                let at = if along == DVec2::ZERO { 0.0 } else { (cursor - self.from).dot(along) / along.length_squared() };
+                self.arm.at = at.clamp(0.0, 1.0);
+                return true;
+            }
+            Handle::ArmEnd => {
+                let foot = self.from.lerp(self.to, self.arm.at);
+                let end = self.confine(cursor, constraints.clamp_to_screen);
+                self.arm.length = -(end - foot).dot(self.direction().perp());
+                return true;
+            }
+        };
+        if constraints.symmetric {
+            self.drag_symmetric(handle, dragged, other, cursor, constraints);
+            return true;
+        }
+        let moved = self.confine(constrain(dragged, other, cursor, self.bounds, constraints), constraints.clamp_to_screen);
+        let moved = constraints.snap_to_pixels.apply(moved, constraints.clamp_to_screen.then_some(&self.bounds));
+        if handle == Handle::From {
+            self.from = moved;
+        } else {
+            self.to = moved;
+        }
+        true
+    }
+
+    /// Moves the endpoint `handle` towards `cursor` as the constraints allow
+    /// around the middle of the ruler, and `other` the mirrored way.
+    fn drag_symmetric(&mut self, handle: Handle, dragged: DVec2, other: DVec2, cursor: DVec2, constraints: Constraints) {
+        let middle = (dragged + other) / 2.0;
+        // Where both an endpoint and its mirror image stay in the bounds.
+        let min = self.bounds.min.max(middle * 2.0 - self.bounds.max);
+        let bounds = Rect { min, max: self.bounds.max.min(middle * 2.0 - self.bounds.min).max(min) };
+        let half = Constraints { min_length: constraints.min_length / 2.0, ..constraints };
+        let moved = constrain(dragged, middle, cursor, bounds, half);
+        let moved = if constraints.clamp_to_screen { bounds.clamp(moved) } else { self.confine(moved, false) };
+        let moved = constraints.snap_to_pixels.apply(moved, None);
+        let mirrored = self.confine(middle * 2.0 - moved, constraints.clamp_to_screen);
+        let moved = self.confine(moved, constraints.clamp_to_screen);
+        if handle == Handle::From {
+            (self.from, self.to) = (moved, mirrored);
+        } else {
+            (self.from, self.to) = (mirrored, moved);
+        }
+    }
+
+    /// Moves the endpoints from `start` by `offset` while keeping the ruler as
+    /// it is, so a clamped ruler stops at the bounds as a whole.
+    fn shift(&mut self, (from, to): (DVec2, DVec2), offset: DVec2, clamp_to_screen: bool) {
+        let offset = if clamp_to_screen { offset.max(self.bounds.min - from.min(to)).min(self.bounds.max - from.max(to)) } else { offset };
+        self.from = self.confine(from + offset, clamp_to_screen);
+        self.to = self.confine(to + offset, clamp_to_screen);
+    }
+
+    /// Ends a drag done with `button`, returning whether there was one. The
+    /// pointer is still over the handle, so it stays hovered.
+    pub fn release(&mut self, button: u8) -> bool {
+        match self.interaction {
+            Interaction::Dragging { handle, button: held, .. } if held == button => {
+                self.interaction = Interaction::Hovering(handle);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Puts the endpoints back where the drag started, returning whether there
+    /// was a drag to cancel.
+    pub fn cancel(&mut self) -> bool {
+        let Interaction::Dragging { start: (from, to), arm, .. } = self.interaction else { return false };
+        (self.from, self.to, self.arm) = (from, to, arm);
+        self.interaction = Interaction::Idle;
+        true
+    }
+
+    /// Moves either or both endpoints.
+    pub fn set(&mut self, from: Option<DVec2>, to: Option<DVec2>, clamp_to_screen: bool) {
+        self.from = from.map_or(self.from, |point| self.confine(point, clamp_to_screen));
+        self.to = to.map_or(self.to, |point| self.confine(point, clamp_to_screen));
+    }
+
+    /// Turns the ruler around `from` by `degrees` counterclockwise.
+    pub fn rotate(&mut self, degrees: f64, clamp_to_screen: bool) {
+        // Screen y points down, so counterclockwise is a negative rotation.
+        let to = self.from + DVec2::from_angle(-degrees.to_radians()).rotate(self.to - self.from);
+        self.to = self.confine(to, clamp_to_screen);
+    }
+
+    /// Gives the ruler a new length in pixels and counterclockwise angle in
+    /// degrees, keeping `from` in place and either as is if left out.
+    pub fn resize(&mut self, length: Option<f64>, angle: Option<f64>, clamp_to_screen: bool) {
+        let length = length.unwrap_or(self.length());
+        let direction = match angle {
+            Some(degrees) => DVec2::from_angle(-degrees.to_radians()),
+            None => self.direction(),
+        };
+        self.to = self.confine(self.from + direction * length, clamp_to_screen);
+    }
+}
+
+/// Where `dragged` goes when pulled towards `cursor` with `other` held in place.
+fn constrain(dragged: DVec2, other: DVec2, cursor: DVec2, bounds: Rect, constraints: Constraints) -> DVec2 {
+    let mut new_vec = cursor;
+    let min_length = constraints.min_length;
+
+    if constraints.fix_angle {
+        let old_diff_normalized = (dragged - other).try_normalize().unwrap_or(DVec2::X);
+        new_vec = other + old_diff_normalized * new_vec.distance(other);
+    } else {
+        new_vec = other + snap_angle(new_vec - other, constraints.snap_angle, constraints.snap_tolerance).0;
+    }
+
+    if other.distance_squared(new_vec) < min_length.powi(2) {
+        let diff_normalized = (new_vec - other).try_normalize().unwrap_or(DVec2::X);
+        new_vec = other + diff_normalized * min_length;
+    }
+
+    // Last, so nothing moves the point off the circle or out of the bounds again.
+    if constraints.fix_distance {
+        let new_diff_normalized = (new_vec - other).try_normalize().unwrap_or(DVec2::X);
+        let old_distance = dragged.distance(other);
+        new_vec = other + new_diff_normalized * old_distance;
+
+        if constraints.clamp_to_screen {
+            match closest_point_on_circle_in_rect(other, old_distance, bounds.min, bounds.max, new_vec) {
+                Some(point) => new_vec = point,
+                None => warn!(center = ?other, radius = old_distance, "The fixed-distance circle misses the bounds, leaving the point to be clamped"),
+            }
+        }
+    }
+
+    new_vec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: DVec2 = DVec2::new(1920.0, 1080.0);
+
+    fn defaults() -> Constraints {
+        Constraints { fix_distance: false, fix_angle: false, symmetric: false, clamp_to_screen: true, min_length: 20.0, snap_angle: 0.0, snap_tolerance: 0.0, snap_to_pixels: PixelSnap::Off }
+    }
+
+    /// A level ruler from (100, 540) to (500, 540), its `to` end grabbed.
+    fn dragging_to() -> RulerState {
+        let mut state = RulerState::new(DVec2::new(100.0, 540.0), DVec2::new(500.0, 540.0), Mode::Line, SCREEN);
+        state.grab(state.to, 40.0, 1);
+        state
+    }
+
+    #[test]
+    fn snapped_endpoints_land_on_pixels_again() {
+        let mut state = dragging_to();
+        let constraints = Constraints { snap_to_pixels: PixelSnap::Centers, ..defaults() };
+        state.drag(DVec2::new(612.3, 401.8), constraints);
+        assert_eq!(state.to, DVec2::new(612.5, 401.5));
+        // As onto the edge of a window, between two pixels.
+        state.drag_to(DVec2::new(604.2, 400.0), constraints);
+        assert_eq!(state.to, DVec2::new(604.5, 400.5));
+    }
+
+    #[test]
+    fn handles_shrink_on_short_rulers() {
+        let state = RulerState::new(DVec2::new(100.0, 540.0), DVec2::new(120.0, 540.0), Mode::Line, SCREEN);
+        let region = state.input_region(20.0);
+        assert_eq!(region[0], Rect { min: DVec2::new(90.0, 530.0), max: DVec2::new(110.0, 550.0) });
+        assert_eq!(region[1], Rect { min: DVec2::new(110.0, 530.0), max: DVec2::new(130.0, 550.0) });
+        assert_eq!(dragging_to().handle_radius(20.0), 20.0);
+    }
+
+    #[test]
+    fn symmetric_drags_mirror_the_other_endpoint() {
+        let mut state = dragging_to();
+        let constraints = Constraints { symmetric: true, ..defaults() };
+        state.drag(DVec2::new(600.0, 640.0), constraints);
+        assert_eq!((state.from, state.to), (DVec2::new(0.0, 440.0), DVec2::new(600.0, 640.0)));
+    }
+
+    #[test]
+    fn symmetric_drags_stop_where_the_mirror_image_leaves_the_screen() {
+        let mut state = dragging_to();
+        let constraints = Constraints { symmetric: true, ..defaults() };
+        // `from` would go to x = -100, so `to` stops at twice the middle.
+        state.drag(DVec2::new(700.0, 540.0), constraints);
+        assert_eq!((state.from, state.to), (DVec2::new(0.0, 540.0), DVec2::new(600.0, 540.0)));
+    }
+
+    #[test]
+    fn symmetric_drags_keep_each_half_to_half_the_minimum_length() {
+        let mut state = dragging_to();
+        let constraints = Constraints { symmetric: true, ..defaults() };
+        state.drag(DVec2::new(305.0, 540.0), constraints);
+        assert_eq!((state.from, state.to), (DVec2::new(290.0, 540.0), DVec2::new(310.0, 540.0)));
+    }
+
+    #[test]
+    fn snaps_keep_to_the_constraints() {
+        let mut state = dragging_to();
+        let constraints = Constraints { symmetric: true, ..defaults() };
+        state.drag(DVec2::new(560.0, 500.0), constraints);
+        state.drag_to(DVec2::new(550.0, 500.0), constraints);
+        assert_eq!((state.from, state.to), (DVec2::new(50.0, 580.0), DVec2::new(550.0, 500.0)));
+
+        let mut state = dragging_to();
+        let constraints = Constraints { fix_angle: true, ..defaults() };
+        // As far from `from` as the snapped point, and still level.
+        state.drag_to(DVec2::new(400.0, 940.0), constraints);
+        assert_eq!(state.to, DVec2::new(600.0, 540.0));
+    }
+
+    #[test]
+    fn fixed_distance_drags_keep_the_distance_past_a_corner() {
+        let mut state = RulerState::new(DVec2::new(1850.0, 60.0), DVec2::new(1750.0, 60.0), Mode::Line, SCREEN);
+        state.grab(state.to, 40.0, 1);
+        let constraints = Constraints { fix_distance: true, snap_angle: 45.0, snap_tolerance: 10.0, ..defaults() };
+        // Snapped to 45 degrees up and to the right, which is past the corner.
+        state.drag(DVec2::new(1950.0, -30.0), constraints);
+        assert!((state.to.distance(state.from) - 100.0).abs() < 1e-9, "{:?}", state.to);
+        assert_eq!(state.bounds.clamp(state.to), state.to);
+    }
+
+    #[test]
+    fn drags_ignoring_limits_go_short_and_off_the_screen() {
+        let mut state = dragging_to();
+        let constraints = defaults().ignoring_limits();
+        state.drag(DVec2::new(105.0, 540.0), constraints);
+        assert_eq!(state.to, DVec2::new(105.0, 540.0));
+        state.drag(DVec2::new(-50.0, 1200.0), constraints);
+        assert_eq!(state.to, DVec2::new(-50.0, 1200.0));
+
+        // Which the defaults would have stopped at the minimum length and the edge.
+        state.drag(DVec2::new(105.0, 540.0), defaults());
+        assert_eq!(state.to, DVec2::new(120.0, 540.0));
+        state.drag(DVec2::new(-50.0, 1200.0), defaults());
+        assert_eq!(state.to, DVec2::new(0.0, 1080.0));
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use tracing::info;
+
+/// Where the time of one frame went: handling a batch of events, drawing
+/// and flushing the requests they made to the X server.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Timings {
+    pub handling: Duration,
+    pub draw: Duration,
+    pub flush: Duration,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.handling + self.draw + self.flush
+    }
+
+    /// The timings in milliseconds, for the debug overlay.
+    pub fn line(&self) -> String {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        format!("events {:.2} draw {:.2} flush {:.2} ms", ms(self.handling), ms(self.draw), ms(self.flush))
+    }
+}
+
+/// Frame timings collected for the `toggle_frame_stats` overlay and the
+/// summary `performance.frame_stats` logs on exit.
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    /// Whether the last frame's timings are drawn on the ruler.
+    pub visible: bool,
+    current: Timings,
+    last: Option<Timings>,
+    frames: u32,
+    sum: Timings,
+    slowest: Duration,
+}
+
+impl FrameStats {
+    /// Adds time spent handling an event, drawing included.
+    pub fn handled(&mut self, elapsed: Duration) {
+        self.current.handling += elapsed;
+    }
+
+    pub fn drew(&mut self, elapsed: Duration) {
+        self.current.draw += elapsed;
+    }
+
+    /// Ends the frame with the flush that sent its requests.
+    pub fn flushed(&mut self, elapsed: Duration) {
+        let mut frame = self.current;
+        frame.handling = frame.handling.saturating_sub(frame.draw);
+        frame.flush = elapsed;
+        self.current = Timings::default();
+
+        self.frames += 1;
+        self.sum.handling += frame.handling;
+        self.sum.draw += frame.draw;
+        self.sum.flush += frame.flush;
+        self.slowest = self.slowest.max(frame.total());
+        self.last = Some(frame);
+    }
+
+    /// The timings of the last finished frame.
+    pub fn last(&self) -> Option<Timings> {
+        self.last
+    }
+
+    pub fn log_summary(&self) {
+        if self.frames == 0 {
+            return;
+        }
+        let average = Timings { handling: self.sum.handling / self.frames, draw: self.sum.draw / self.frames, flush: self.sum.flush / self.frames };
+        info!(frames = self.frames, slowest = ?self.slowest, "average frame: {}", average.line());
+    }
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+use xcb::Connection;
+use xcb::x::{ATOM_STRING, Atom, ChangeProperty, GetProperty, PropMode, Window};
+
+use ruler::unit::Unit;
+
+use crate::backend::{checked, round_trip};
+
+/// State shared by every ruler with `sync` enabled. It lives in a property on
+/// the root window, so rulers pick up each other's changes through the
+/// property notifications they already get for X resources.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Shared {
+    /// Unit picked at runtime, `None` leaving it to each ruler's settings.
+    pub unit: Option<Unit>,
+    pub profile: Option<String>,
+}
+
+/// Reads the shared state, `None` if no ruler has published any yet.
+pub fn read(conn: &Connection, root: Window, property: Atom) -> Result<Option<Shared>, Box<dyn Error>> {
+    let reply = round_trip(conn, &GetProperty {
+        delete: false,
+        window: root,
+        property,
+        r#type: ATOM_STRING,
+        long_offset: 0,
+        long_length: u32::MAX / 4,
+    })?;
+    if reply.value::<u8>().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(reply.value::<u8>())?))
+}
+
+pub fn publish(conn: &Connection, root: Window, property: Atom, shared: &Shared) -> Result<(), Box<dyn Error>> {
+    checked(conn, &ChangeProperty {
+        mode: PropMode::Replace,
+        window: root,
+        property,
+        r#type: ATOM_STRING,
+        data: serde_json::to_string(shared)?.as_bytes(),
+    })?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use glam::DVec2;
+use tracing::debug;
+use xcb::x::{ChangeWindowAttributes, Cw, EventMask, Window};
+
+use ruler::geom::Rect;
+
+use crate::backend::{checked, XCBObjects};
+
+/// Which way `measure_window` lays the ruler across a window, in the order
+/// repeated presses go through them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Span {
+    Width,
+    Height,
+    Diagonal,
+}
+
+impl Span {
+    pub fn next(self) -> Span {
+        match self {
+            Span::Width => Span::Height,
+            Span::Height => Span::Diagonal,
+            Span::Diagonal => Span::Width,
+        }
+    }
+
+    /// Edge to edge through the middle of `rect`, or corner to corner.
+    pub fn endpoints(self, rect: &Rect) -> (DVec2, DVec2) {
+        let center = (rect.min + rect.max) / 2.0;
+        match self {
+            Span::Width => (DVec2::new(rect.min.x, center.y), DVec2::new(rect.max.x, center.y)),
+            Span::Height => (DVec2::new(center.x, rect.min.y), DVec2::new(center.x, rect.max.y)),
+            Span::Diagonal => (rect.min, rect.max),
+        }
+    }
+}
+
+/// The window the ruler was last laid across, so the next press measures it
+/// the next way instead of asking for another while the ruler has not moved.
+#[derive(Debug, Copy, Clone)]
+pub struct Target {
+    pub window: Rect,
+    pub span: Span,
+    /// Where the ruler's endpoints ended up, clamped to the screen.
+    pub endpoints: (DVec2, DVec2),
+}
+
+impl Target {
+    /// The window and way to measure after this one, if the ruler is still
+    /// at `endpoints`.
+    pub fn next(&self, endpoints: (DVec2, DVec2)) -> Option<(Rect, Span)> {
+        (self.endpoints == endpoints).then_some((self.window, self.span.next()))
+    }
+}
+
+/// The topmost of `windows`, listed from the bottom of the stack up, that
+/// `point` is on.
+pub fn window_at(windows: &[Rect], point: DVec2) -> Option<Rect> {
+    windows.iter().rev().find(|window| window.contains(point)).copied()
+}
+
+/// Keeps the ruler across the focused window for `toggle_follow_focus`,
+/// watching that window for moves and resizes.
+#[derive(Debug, Default)]
+pub struct Follower {
+    pub enabled: bool,
+    /// The window followed, other than the ruler.
+    pub window: Option<Window>,
+}
+
+impl Follower {
+    /// Starts following the focused window or stops, returning where the
+    /// focused window is to measure it straight away.
+    pub fn toggle(&mut self, xcb: &XCBObjects) -> Option<Rect> {
+        self.enabled = !self.enabled;
+        if self.enabled {
+            self.focus_changed(xcb)
+        } else {
+            self.watch(xcb, None);
+            None
+        }
+    }
+
+    /// Moves on to the window that got the focus, returning where it is. The
+    /// ruler getting the focus, as it does for its own key bindings, leaves
+    /// the last window followed.
+    pub fn focus_changed(&mut self, xcb: &XCBObjects) -> Option<Rect> {
+        let window = xcb.active_window().filter(|&window| window != xcb.window)?;
+        self.watch(xcb, Some(window));
+        xcb.client_edges(window)
+    }
+
+    /// Where the window followed is now, after it moved or was resized.
+    pub fn moved(&self, xcb: &XCBObjects) -> Option<Rect> {
+        xcb.client_edges(self.window?)
+    }
+
+    fn watch(&mut self, xcb: &XCBObjects, window: Option<Window>) {
+        if self.window == window {
+            return;
+        }
+        // The event masks are the ruler's own, so this leaves other clients
+        // listening to the window be. Checked, since an error from a window
+        // that is gone would otherwise end the event loop.
+        for (window, event_mask) in [(self.window, EventMask::NO_EVENT), (window, EventMask::STRUCTURE_NOTIFY)] {
+            let Some(window) = window else { continue };
+            if let Err(e) = checked(&xcb.conn, &ChangeWindowAttributes { window, value_list: &[Cw::EventMask(event_mask)] }) {
+                debug!(?window, "Failed to watch a window for moves: {}", e);
+            }
+        }
+        self.window = window;
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::process;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use serde::Serialize;
+use tracing::warn;
+use zbus::zvariant::{ObjectPath, OwnedValue, StructureBuilder, Type, Value};
+
+use ruler::unit::Unit;
+
+use crate::backend::Waker;
+use crate::control::{Message, Request};
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+
+const SHOW_HIDE: i32 = 1;
+const UNITS: i32 = 2;
+const SEPARATOR: i32 = 3;
+const QUIT: i32 = 4;
+/// Unit entries are numbered from here in `Unit` order.
+const FIRST_UNIT: i32 = 10;
+const UNIT_ENTRIES: [(Unit, &str); 4] = [(Unit::Px, "Pixels"), (Unit::Mm, "Millimeters"), (Unit::Cm, "Centimeters"), (Unit::In, "Inches")];
+
+/// Puts an icon in the system tray through the StatusNotifierItem protocol,
+/// with a menu to show or hide the ruler, switch units and quit. Clicks come
+/// back as control requests, like those sent over the socket.
+pub fn spawn(waker: Waker, sender: Sender<Message>) {
+    thread::spawn(move || match serve(Actions { waker, sender }) {
+        // The icon goes away with the connection, so hold it until exit.
+        Ok(_conn) => loop {
+            thread::park();
+        },
+        Err(e) => warn!("No tray icon: {}", e),
+    });
+}
+
+#[derive(Clone)]
+struct Actions {
+    waker: Waker,
+    sender: Sender<Message>,
+}
+
+impl Actions {
+    fn send(&self, request: Request) {
+        // Nobody waits for the replies.
+        let (reply, _) = mpsc::channel();
+        if self.sender.send(Message { request, reply }).is_ok() {
+            if let Err(e) = self.waker.wake() {
+                warn!("Failed to pass on a tray click: {}", e);
+            }
+        }
+    }
+}
+
+fn serve(actions: Actions) -> Result<zbus::blocking::Connection, Box<dyn Error>> {
+    let name = format!("org.kde.StatusNotifierItem-{}-1", process::id());
+    let conn = zbus::blocking::connection::Builder::session()?
+        .name(name.as_str())?
+        .serve_at(ITEM_PATH, Item { actions: actions.clone() })?
+        .serve_at(MENU_PATH, Menu { actions })?
+        .build()?;
+    conn.call_method(
+        Some("org.kde.StatusNotifierWatcher"),
+        "/StatusNotifierWatcher",
+        Some("org.kde.StatusNotifierWatcher"),
+        "RegisterStatusNotifierItem",
+        &(name.as_str(),),
+    )?;
+    Ok(conn)
+}
+
+struct Item {
+    actions: Actions,
+}
+
+#[zbus::interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    #[zbus(property)]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property)]
+    fn id(&self) -> &str {
+        "ruler"
+    }
+
+    #[zbus(property)]
+    fn title(&self) -> &str {
+        "Ruler"
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property)]
+    fn icon_name(&self) -> &str {
+        "measure"
+    }
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn menu(&self) -> ObjectPath<'_> {
+        ObjectPath::from_static_str_unchecked(MENU_PATH)
+    }
+
+    fn activate(&self, _x: i32, _y: i32) {
+        self.actions.send(Request::Toggle);
+    }
+
+    fn secondary_activate(&self, _x: i32, _y: i32) {}
+
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+}
+
+/// A menu entry in the `(ia{sv}av)` layout of the dbusmenu protocol.
+#[derive(Serialize, Type)]
+struct Layout {
+    id: i32,
+    properties: HashMap<String, OwnedValue>,
+    children: Vec<OwnedValue>,
+}
+
+fn properties(id: i32) -> HashMap<String, OwnedValue> {
+    let label = match id {
+        SHOW_HIDE => Some("Show or hide"),
+        UNITS => Some("Unit"),
+        QUIT => Some("Quit"),
+        _ => UNIT_ENTRIES.get((id - FIRST_UNIT) as usize).map(|&(_, label)| label),
+    };
+    let mut properties = HashMap::new();
+    let mut insert = |key: &str, value: Value| {
+        if let Ok(value) = OwnedValue::try_from(value) {
+            properties.insert(key.to_string(), value);
+        }
+    };
+    if let Some(label) = label {
+        insert("label", Value::from(label));
+    }
+    match id {
+        UNITS => insert("children-display", Value::from("submenu")),
+        SEPARATOR => insert("type", Value::from("separator")),
+        _ => {}
+    }
+    properties
+}
+
+fn layout(id: i32) -> Layout {
+    let children = match id {
+        0 => vec![SHOW_HIDE, UNITS, SEPARATOR, QUIT],
+        UNITS => (FIRST_UNIT..FIRST_UNIT + UNIT_ENTRIES.len() as i32).collect(),
+        _ => vec![],
+    };
+    let children = children.into_iter().filter_map(|child| {
+        let Layout { id, properties, children } = layout(child);
+        let structure = StructureBuilder::new().add_field(id).add_field(properties).add_field(children).build().ok()?;
+        OwnedValue::try_from(Value::from(structure)).ok()
+    });
+    Layout { id, properties: properties(id), children: children.collect() }
+}
+
+struct Menu {
+    actions: Actions,
+}
+
+#[zbus::interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property)]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    fn get_layout(&self, parent_id: i32, _recursion_depth: i32, _property_names: Vec<String>) -> (u32, Layout) {
+        (1, layout(parent_id))
+    }
+
+    fn get_group_properties(&self, ids: Vec<i32>, _property_names: Vec<String>) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        ids.into_iter().map(|id| (id, properties(id))).collect()
+    }
+
+    fn event(&self, id: i32, event_id: &str, _data: OwnedValue, _timestamp: u32) {
+        if event_id != "clicked" {
+            return;
+        }
+        match id {
+            SHOW_HIDE => self.actions.send(Request::Toggle),
+            QUIT => self.actions.send(Request::Quit),
+            _ => {
+                if let Some(&(unit, _)) = UNIT_ENTRIES.get((id - FIRST_UNIT) as usize) {
+                    self.actions.send(Request::Unit(unit));
+                }
+            }
+        }
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+}
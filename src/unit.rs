@@ -0,0 +1,57 @@
+use clap::ValueEnum;
+use glam::DVec2;
+use serde::{Deserialize, Serialize};
+
+/// Unit of the ruler's scale and length readout.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Px,
+    Mm,
+    Cm,
+    In,
+}
+
+impl Unit {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Mm => "mm",
+            Unit::Cm => "cm",
+            Unit::In => "in",
+        }
+    }
+
+    /// Pixels per unit along `direction`, which matters when the screen's
+    /// pixels are not square.
+    pub fn px_per_unit(self, direction: DVec2, px_per_mm: DVec2) -> f64 {
+        let mm = match self {
+            Unit::Px => return 1.0,
+            Unit::Mm => 1.0,
+            Unit::Cm => 10.0,
+            Unit::In => 25.4,
+        };
+        let mm_per_px = (direction.try_normalize().unwrap_or(DVec2::X) / px_per_mm).length();
+        mm / mm_per_px
+    }
+
+    /// Decimal places a length in the unit is usually given to.
+    fn precision(self) -> usize {
+        match self {
+            Unit::Px => 0,
+            Unit::Mm => 1,
+            Unit::Cm | Unit::In => 2,
+        }
+    }
+
+    /// Formats a length of `value` units with the usual precision for the unit.
+    pub fn format(self, value: f64) -> String {
+        format!("{:.*} {}", self.precision(), value, self.suffix())
+    }
+
+    /// Formats a length of `value` units to two more decimal places than
+    /// `format`, to hundredths of a pixel.
+    pub fn format_precise(self, value: f64) -> String {
+        format!("{:.*} {}", self.precision() + 2, value, self.suffix())
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use ruler::measurement::Measurement;
+use ruler::output::{Report, StreamFormat};
+
+/// A `--watch` record, the report prefixed with the time it was taken.
+#[derive(Debug, Serialize)]
+struct Record {
+    /// Seconds since the epoch, to the millisecond
+    timestamp: f64,
+    #[serde(flatten)]
+    report: Report,
+}
+
+/// Prints the ruler's state whenever it changes, at most once per `interval`.
+pub struct Watch {
+    interval: Duration,
+    format: StreamFormat,
+    last: Option<(Instant, Report)>,
+}
+
+impl Watch {
+    pub fn new(interval: Duration, format: StreamFormat) -> Watch {
+        Watch { interval, format, last: None }
+    }
+
+    /// Records `measurement` if it differs from the last record. Changes
+    /// arriving faster than the interval are dropped unless `force` is set,
+    /// which is used when a drag ends so its final state is never lost.
+    pub fn observe(&mut self, measurement: &Measurement, force: bool) {
+        let report = Report::from(measurement);
+        if let Some((at, last)) = &self.last {
+            let unchanged = last.from == report.from && last.to == report.to;
+            if unchanged || (!force && at.elapsed() < self.interval) {
+                return;
+            }
+        }
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |since| (since.as_millis() as f64) / 1000.0);
+        let record = Record { timestamp, report };
+        match self.format {
+            StreamFormat::Text => println!("{:.3} {}", record.timestamp, record.report),
+            StreamFormat::Json => println!("{}", serde_json::to_string(&record).unwrap_or_default()),
+        }
+        self.last = Some((Instant::now(), report));
+    }
+}
@@ -0,0 +1,29 @@
+use std::env;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "ruler";
+
+fn base_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    match env::var_os(var).map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Some(path),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)),
+    }
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    base_dir("XDG_DATA_HOME", ".local/share").map(|dir| dir.join(APP_DIR))
+}
+
+/// `$XDG_RUNTIME_DIR`, which has no fallback as it has to be private to the user.
+pub fn runtime_dir() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).filter(|path| path.is_absolute())
+}
+
+/// Where screenshots go, `$XDG_PICTURES_DIR` as set by xdg-user-dirs or `~/Pictures`.
+pub fn pictures_dir() -> Option<PathBuf> {
+    base_dir("XDG_PICTURES_DIR", "Pictures")
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    base_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join(APP_DIR))
+}
@@ -0,0 +1,233 @@
+//! End-to-end tests that run the binary on a private Xvfb server, drive it
+//! with XTest and check the window and what the control socket reports.
+//! They need Xvfb, so they are ignored unless run with
+//! `cargo test --test xvfb -- --ignored`.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use xcb::x::{self, GetGeometry, GetSelectionOwner, InternAtom};
+use xcb::{xtest, Connection, Extension};
+
+const SCREEN: (i16, i16) = (1024, 768);
+const TIMEOUT: Duration = Duration::from_secs(5);
+// Escape in the evdev keymap Xvfb starts with.
+const ESCAPE_KEYCODE: u8 = 9;
+
+// Core event codes, which is what FakeInput takes as its type.
+const KEY_PRESS: u8 = 2;
+const KEY_RELEASE: u8 = 3;
+const BUTTON_PRESS: u8 = 4;
+const BUTTON_RELEASE: u8 = 5;
+const MOTION_NOTIFY: u8 = 6;
+
+struct Session {
+    xvfb: Child,
+    display: String,
+    dir: PathBuf,
+    ruler: Option<Child>,
+    conn: Connection,
+}
+
+impl Session {
+    /// Starts an Xvfb server of its own.
+    fn start(name: &str) -> Session {
+        let mut xvfb = Command::new("Xvfb")
+            .args(["-displayfd", "1", "-nolisten", "tcp", "-screen", "0"])
+            .arg(format!("{}x{}x24", SCREEN.0, SCREEN.1))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Xvfb is not installed");
+        let mut number = String::new();
+        BufReader::new(xvfb.stdout.take().unwrap()).read_line(&mut number).unwrap();
+        let display = format!(":{}", number.trim());
+
+        let dir = env::temp_dir().join(format!("ruler-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let (conn, _) = Connection::connect_with_extensions(Some(&display), &[Extension::Test], &[]).unwrap();
+        Session { xvfb, display, dir, ruler: None, conn }
+    }
+
+    /// Runs the ruler with `args`, isolated from the user's config, history and session bus.
+    fn launch(&mut self, args: &[&str], stdout: Stdio) {
+        let child = Command::new(env!("CARGO_BIN_EXE_ruler"))
+            .args(args)
+            .env_clear()
+            .env("PATH", env::var_os("PATH").unwrap_or_default())
+            .env("DISPLAY", &self.display)
+            .env("HOME", &self.dir)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_DATA_HOME", self.dir.join("data"))
+            .env("XDG_RUNTIME_DIR", &self.dir)
+            .env("RULER_HISTORY", "false")
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .spawn()
+            .unwrap();
+        self.ruler = Some(child);
+        self.wait_until(|session| session.try_request("get").is_some());
+    }
+
+    fn try_request(&self, line: &str) -> Option<String> {
+        let mut stream = UnixStream::connect(self.dir.join("ruler.sock")).ok()?;
+        writeln!(stream, "{}", line).ok()?;
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply).ok()?;
+        Some(reply.trim().to_string())
+    }
+
+    fn request(&self, line: &str) -> String {
+        self.try_request(line).expect("the control socket is gone")
+    }
+
+    /// The endpoints from a `get`.
+    fn endpoints(&self) -> ((f64, f64), (f64, f64)) {
+        let reply = self.request("get");
+        let point = |key: &str| {
+            let value = reply.split_whitespace().find_map(|word| word.strip_prefix(key)).unwrap();
+            let (x, y) = value.split_once(',').unwrap();
+            (x.parse().unwrap(), y.parse().unwrap())
+        };
+        (point("from="), point("to="))
+    }
+
+    fn wait_until(&self, condition: impl Fn(&Session) -> bool) {
+        let start = Instant::now();
+        while !condition(self) {
+            assert!(start.elapsed() < TIMEOUT, "timed out");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn wait_for_endpoints(&self, expected: ((f64, f64), (f64, f64))) {
+        let start = Instant::now();
+        loop {
+            let endpoints = self.endpoints();
+            if endpoints == expected {
+                return;
+            }
+            assert!(start.elapsed() < TIMEOUT, "expected endpoints {:?}, the ruler has {:?}", expected, endpoints);
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// Position and size of the ruler's window.
+    fn window_geometry(&self) -> (i16, i16, u16, u16) {
+        let selection = self.conn.wait_for_reply(self.conn.send_request(&InternAtom { only_if_exists: false, name: b"_RULER_INSTANCE" })).unwrap().atom();
+        let owner = self.conn.wait_for_reply(self.conn.send_request(&GetSelectionOwner { selection })).unwrap().owner();
+        let geometry = self.conn.wait_for_reply(self.conn.send_request(&GetGeometry { drawable: x::Drawable::Window(owner) })).unwrap();
+        (geometry.x(), geometry.y(), geometry.width(), geometry.height())
+    }
+
+    fn fake(&self, r#type: u8, detail: u8, position: (i16, i16)) {
+        let root = self.conn.get_setup().roots().next().unwrap().root();
+        self.conn
+            .send_and_check_request(&xtest::FakeInput { r#type, detail, time: x::CURRENT_TIME, root, root_x: position.0, root_y: position.1, deviceid: 0 })
+            .unwrap();
+    }
+
+    fn move_pointer(&self, position: (i16, i16)) {
+        self.fake(MOTION_NOTIFY, 0, position);
+    }
+
+    fn press(&self, button: u8) {
+        self.fake(BUTTON_PRESS, button, (0, 0));
+    }
+
+    fn release(&self, button: u8) {
+        self.fake(BUTTON_RELEASE, button, (0, 0));
+    }
+
+    fn key(&self, keycode: u8) {
+        self.fake(KEY_PRESS, keycode, (0, 0));
+        self.fake(KEY_RELEASE, keycode, (0, 0));
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(ruler) = &mut self.ruler {
+            let _ = ruler.kill();
+            let _ = ruler.wait();
+        }
+        let _ = self.xvfb.kill();
+        let _ = self.xvfb.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn starts_centered() {
+    let mut session = Session::start("starts_centered");
+    session.launch(&[], Stdio::null());
+
+    assert_eq!(session.endpoints(), ((312.0, 384.0), (712.0, 384.0)));
+    session.wait_until(|session| session.window_geometry() == (272, 344, 480, 80));
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn dragging_moves_an_endpoint() {
+    let mut session = Session::start("dragging_moves_an_endpoint");
+    session.launch(&[], Stdio::null());
+
+    session.move_pointer((712, 384));
+    session.press(1);
+    session.move_pointer((800, 300));
+    session.release(1);
+
+    session.wait_for_endpoints(((312.0, 384.0), (800.0, 300.0)));
+    session.wait_until(|session| session.window_geometry() == (272, 260, 568, 164));
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn escape_cancels_a_drag() {
+    let mut session = Session::start("escape_cancels_a_drag");
+    session.launch(&[], Stdio::null());
+
+    session.move_pointer((312, 384));
+    session.press(1);
+    session.move_pointer((200, 500));
+    session.wait_for_endpoints(((200.0, 500.0), (712.0, 384.0)));
+    session.key(ESCAPE_KEYCODE);
+    session.release(1);
+
+    session.wait_for_endpoints(((312.0, 384.0), (712.0, 384.0)));
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn control_socket_moves_the_ruler() {
+    let mut session = Session::start("control_socket_moves_the_ruler");
+    session.launch(&[], Stdio::null());
+
+    assert_eq!(session.request("set from 100 100 to 300 100"), "ok");
+    assert_eq!(session.request("rotate -90"), "ok");
+
+    assert_eq!(session.endpoints(), ((100.0, 100.0), (100.0, 300.0)));
+    session.wait_until(|session| session.window_geometry() == (60, 60, 80, 280));
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn quitting_prints_the_measurement() {
+    let mut session = Session::start("quitting_prints_the_measurement");
+    session.launch(&["--json"], Stdio::piped());
+
+    assert_eq!(session.request("quit"), "ok");
+
+    let output = session.ruler.take().unwrap().wait_with_output().unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["length"]["px"], 400.0);
+    assert_eq!(report["angle"], 0.0);
+}